@@ -0,0 +1,28 @@
+#![no_main]
+
+use connect_4::position::{Position, RenderOpts};
+use libfuzzer_sys::fuzz_target;
+
+// Parse arbitrary strings as positions, both as moves and as a grid. Parsing should
+// never panic, and every parsed position should be valid and have one move per
+// character of the string, or one per stone of the grid.
+fuzz_target!(|data: &[u8]| {
+    let Ok(position_str) = std::str::from_utf8(data) else {
+        return;
+    };
+    if let Ok(pos) = Position::from_string(position_str) {
+        assert!(pos.is_valid());
+        assert_eq!(pos.nb_moves() as usize, position_str.len());
+    }
+    if let Ok(pos) = Position::from_grid_string(position_str) {
+        assert!(pos.is_valid());
+        let stones = position_str
+            .chars()
+            .filter(|c| matches!(c.to_ascii_lowercase(), 'o' | 'x'))
+            .count();
+        assert_eq!(pos.nb_moves() as usize, stones);
+        // Printing the grid and parsing it again gives the same position.
+        let grid = pos.to_ascii_art(RenderOpts::default());
+        assert!(Position::from_grid_string(&grid).is_ok_and(|parsed| parsed == pos));
+    }
+});
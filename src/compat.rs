@@ -0,0 +1,84 @@
+//! The few pieces of `std` the solver needs, with replacements for `no_std` builds
+//! (without the `std` feature). Those only run a single thread and don't keep time.
+
+#[cfg(feature = "std")]
+pub(crate) use std::{sync::Mutex, time::Instant};
+
+#[cfg(not(feature = "std"))]
+pub(crate) use no_std::{Instant, Mutex};
+
+#[cfg(not(feature = "std"))]
+mod no_std {
+    use core::cell::UnsafeCell;
+    use core::convert::Infallible;
+    use core::ops::{Deref, DerefMut};
+    use core::sync::atomic::{AtomicBool, Ordering};
+    use core::time::Duration;
+
+    /// A spin lock with the API of `std::sync::Mutex`. The solver only holds it
+    /// briefly, and never while another lock is held.
+    #[derive(Default)]
+    pub(crate) struct Mutex<T> {
+        locked: AtomicBool,
+        value: UnsafeCell<T>,
+    }
+
+    // SAFETY: the value is only accessed through a `MutexGuard`, and there is at most
+    // one guard at a time.
+    unsafe impl<T: Send> Sync for Mutex<T> {}
+
+    impl<T> Mutex<T> {
+        /// Locking can't fail, there is no poisoning without unwinding threads.
+        pub(crate) fn lock(&self) -> Result<MutexGuard<'_, T>, Infallible> {
+            while self
+                .locked
+                .compare_exchange_weak(false, true, Ordering::Acquire, Ordering::Relaxed)
+                .is_err()
+            {
+                core::hint::spin_loop();
+            }
+            Ok(MutexGuard { mutex: self })
+        }
+    }
+
+    pub(crate) struct MutexGuard<'a, T> {
+        mutex: &'a Mutex<T>,
+    }
+
+    impl<T> Deref for MutexGuard<'_, T> {
+        type Target = T;
+
+        fn deref(&self) -> &T {
+            // SAFETY: this guard holds the lock.
+            unsafe { &*self.mutex.value.get() }
+        }
+    }
+
+    impl<T> DerefMut for MutexGuard<'_, T> {
+        fn deref_mut(&mut self) -> &mut T {
+            // SAFETY: this guard holds the lock.
+            unsafe { &mut *self.mutex.value.get() }
+        }
+    }
+
+    impl<T> Drop for MutexGuard<'_, T> {
+        fn drop(&mut self) {
+            self.mutex.locked.store(false, Ordering::Release);
+        }
+    }
+
+    /// Without a clock, no time ever passes.
+    #[derive(Clone, Copy)]
+    pub(crate) struct Instant;
+
+    impl Instant {
+        pub(crate) fn now() -> Self {
+            Self
+        }
+
+        #[allow(clippy::unused_self)]
+        pub(crate) fn elapsed(&self) -> Duration {
+            Duration::ZERO
+        }
+    }
+}
@@ -0,0 +1,62 @@
+use core::{error::Error, fmt};
+#[cfg(feature = "std")]
+use std::io;
+
+use crate::opening_book::ParseBookEntryError;
+use crate::position::PositionParseError;
+
+/// The error type for all the fallible operations of this crate.
+///
+/// This allows callers to distinguish between actual IO failures
+/// (e.g. a missing file) and files with invalid content.
+#[derive(Debug)]
+pub enum Connect4Error {
+    /// Reading or writing a file failed.
+    #[cfg(feature = "std")]
+    Io(io::Error),
+    /// An entry of an opening book could not be parsed.
+    Parse(ParseBookEntryError),
+    /// A position could not be parsed.
+    Position(PositionParseError),
+}
+
+impl fmt::Display for Connect4Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            #[cfg(feature = "std")]
+            Self::Io(err) => write!(f, "IO error: {err}"),
+            Self::Parse(err) => write!(f, "Invalid book entry: {err}"),
+            Self::Position(err) => write!(f, "Invalid position: {err}"),
+        }
+    }
+}
+
+impl Error for Connect4Error {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            #[cfg(feature = "std")]
+            Self::Io(err) => Some(err),
+            Self::Parse(err) => Some(err),
+            Self::Position(err) => Some(err),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl From<io::Error> for Connect4Error {
+    fn from(err: io::Error) -> Self {
+        Self::Io(err)
+    }
+}
+
+impl From<ParseBookEntryError> for Connect4Error {
+    fn from(err: ParseBookEntryError) -> Self {
+        Self::Parse(err)
+    }
+}
+
+impl From<PositionParseError> for Connect4Error {
+    fn from(err: PositionParseError) -> Self {
+        Self::Position(err)
+    }
+}
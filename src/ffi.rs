@@ -0,0 +1,127 @@
+//! A small C API to use the solver from other languages.
+//!
+//! Positions are passed as an array of 0-based columns, played in order from
+//! the starting position. Each thread that calls into this API gets its own
+//! `Solver` (and thus its own transposition table), which is created on the
+//! first call and lives until the thread exits.
+use std::cell::RefCell;
+
+use crate::position::{Column, Position};
+use crate::solver::{Solver, TieBreak, Verbosity};
+
+/// Returned by [`c4_solve`] if the moves do not describe a valid position.
+pub const C4_INVALID_POSITION: i32 = i32::MIN;
+
+thread_local! {
+    static SOLVER: RefCell<Solver> = RefCell::new(Solver::new(None));
+}
+
+/// Play the 0-based `moves` from the starting position. Returns `None` if
+/// one of the moves is not on the board, in a full column, or makes an alignment.
+fn position_from_moves(moves: &[Column]) -> Option<Position> {
+    let mut pos = Position::new();
+    for &col in moves {
+        if col >= Position::WIDTH || !pos.can_play(col) || pos.is_winning_move(col) {
+            return None;
+        }
+        pos.play_col(col);
+    }
+    Some(pos)
+}
+
+/// Turn the raw pointer and length into a slice.
+///
+/// # Safety
+///
+/// If `moves_ptr` is not null, it must point to `len` readable bytes.
+unsafe fn moves_slice<'a>(moves_ptr: *const u8, len: usize) -> Option<&'a [Column]> {
+    if moves_ptr.is_null() {
+        // An empty position doesn't need a valid pointer.
+        return if len == 0 { Some(&[]) } else { None };
+    }
+    Some(std::slice::from_raw_parts(moves_ptr, len))
+}
+
+/// Solve the position reached by playing the 0-based columns in `moves_ptr`.
+/// Returns the score of the position for the player to move (see `Solver::solve`),
+/// or [`C4_INVALID_POSITION`] if the moves are not valid.
+///
+/// # Safety
+///
+/// `moves_ptr` must either be null (only allowed if `len` is 0) or point to
+/// `len` readable bytes.
+#[no_mangle]
+pub unsafe extern "C" fn c4_solve(moves_ptr: *const u8, len: usize, weak: bool) -> i32 {
+    let Some(pos) = moves_slice(moves_ptr, len).and_then(position_from_moves) else {
+        return C4_INVALID_POSITION;
+    };
+    SOLVER.with(|solver| {
+        solver
+            .borrow_mut()
+            .solve(&pos, weak, Verbosity::Silent, 1)
+            .0 as i32
+    })
+}
+
+/// Get the best 0-based column to play in the position reached by playing the
+/// 0-based columns in `moves_ptr`. Ties are broken by preferring central columns.
+/// Returns -1 if the moves are not valid or there is no playable column.
+///
+/// # Safety
+///
+/// `moves_ptr` must either be null (only allowed if `len` is 0) or point to
+/// `len` readable bytes.
+#[no_mangle]
+pub unsafe extern "C" fn c4_best_move(moves_ptr: *const u8, len: usize, weak: bool) -> i32 {
+    let Some(pos) = moves_slice(moves_ptr, len).and_then(position_from_moves) else {
+        return -1;
+    };
+    SOLVER.with(|solver| {
+        solver
+            .borrow_mut()
+            .best_move(&pos, weak, TieBreak::CenterFirst)
+            .map_or(-1, i32::from)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn solve_valid_and_invalid() {
+        // Same position as "445533" 1-based, 'o' can win immediately.
+        let moves = [3, 3, 4, 4, 2, 2];
+        let score = unsafe { c4_solve(moves.as_ptr(), moves.len(), false) };
+        assert_eq!(
+            score,
+            Position::from_string("445533").unwrap().num_stones_left(1) as i32
+        );
+        // Out of range column.
+        let moves = [3, 7];
+        assert_eq!(
+            unsafe { c4_solve(moves.as_ptr(), moves.len(), false) },
+            C4_INVALID_POSITION
+        );
+        // Full column.
+        let moves = [0; 7];
+        assert_eq!(
+            unsafe { c4_solve(moves.as_ptr(), moves.len(), false) },
+            C4_INVALID_POSITION
+        );
+        // Null pointer with a non-zero length.
+        assert_eq!(
+            unsafe { c4_solve(std::ptr::null(), 3, false) },
+            C4_INVALID_POSITION
+        );
+    }
+
+    #[test]
+    fn best_move() {
+        // 'o' should complete the horizontal line.
+        let moves = [3, 3, 4, 4, 2, 2];
+        let col = unsafe { c4_best_move(moves.as_ptr(), moves.len(), true) };
+        assert!(col == 1 || col == 5);
+        assert_eq!(unsafe { c4_best_move(std::ptr::null(), 1, true) }, -1);
+    }
+}
@@ -0,0 +1,160 @@
+use crate::position::{Column, Position};
+use crate::solver::Outcome;
+
+/// The rules of a game where two players take turns, like Connect 4 or one of its
+/// variants, e.g. Pop Out or a different alignment length. Everything is from the point
+/// of view of the player to move.
+///
+/// `negamax()` searches any `Game`. The `Solver` has its own search for `Position`, which
+/// relies on the bitboards for the move ordering and the transposition table, so
+/// implementing this trait for `Position` costs nothing in standard play.
+pub trait Game: Clone {
+    /// A move, like a 0-based column for Connect 4.
+    type Move: Copy;
+
+    /// The moves that can be played. They are searched in this order, so the moves that
+    /// are likely to be good should come first.
+    fn moves(&self) -> impl Iterator<Item = Self::Move> + '_;
+
+    /// The game after playing `mv`, one of `moves()`. This is not called for the moves
+    /// that win, see `is_win_after()`.
+    fn apply(&self, mv: Self::Move) -> Self;
+
+    /// Returns true if the player to move wins by playing `mv`, one of `moves()`.
+    fn is_win_after(&self, mv: Self::Move) -> bool;
+
+    /// The outcome if the game is over, or `None` if it continues. A game that isn't over
+    /// has at least one move.
+    fn terminal(&self) -> Option<Outcome>;
+}
+
+impl Game for Position {
+    type Move = Column;
+
+    /// The playable columns, starting from the center.
+    #[inline]
+    fn moves(&self) -> impl Iterator<Item = Column> + '_ {
+        (0..Self::WIDTH)
+            .map(|i| {
+                (Self::WIDTH as isize / 2 + (1 - 2 * (i % 2) as isize) * (i as isize + 1) / 2)
+                    as Column
+            })
+            .filter(|&col| self.can_play(col))
+    }
+
+    #[inline]
+    fn apply(&self, mv: Column) -> Self {
+        let mut pos = self.clone();
+        pos.play_col(mv);
+        pos
+    }
+
+    #[inline]
+    fn is_win_after(&self, mv: Column) -> bool {
+        self.is_winning_move(mv)
+    }
+
+    /// Positions with an alignment are not supported, so the game is only over when
+    /// the board is full, which is a draw.
+    #[inline]
+    fn terminal(&self) -> Option<Outcome> {
+        (self.nb_moves() == Self::WIDTH * Self::HEIGHT).then_some(Outcome::Draw)
+    }
+}
+
+/// Find the outcome of `game` with a plain alpha-beta search, without a transposition
+/// table. Returns 1 if the player to move wins, 0 for a draw and -1 for a loss, like a
+/// weak solve. With a narrower window than `[-1, 1]`, scores outside of it are only bounds.
+///
+/// Example:
+/// ```
+/// use connect_4::game::negamax;
+/// use connect_4::position::Position;
+///
+/// let pos = Position::from_string("2252576253462244111563365343671351441").unwrap();
+/// assert_eq!(negamax(&pos, -1, 1), -1);
+/// ```
+pub fn negamax<G: Game>(game: &G, mut alpha: isize, beta: isize) -> isize {
+    debug_assert!(alpha < beta);
+    if let Some(outcome) = game.terminal() {
+        return match outcome {
+            Outcome::Win => 1,
+            Outcome::Draw => 0,
+            Outcome::Loss => -1,
+        };
+    }
+    if game.moves().any(|mv| game.is_win_after(mv)) {
+        return 1;
+    }
+    for mv in game.moves() {
+        let score = -negamax(&game.apply(mv), -beta, -alpha);
+        if score >= beta {
+            return score;
+        }
+        alpha = alpha.max(score);
+    }
+    alpha
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Nim with one heap, where a move takes 1, 2 or 3 stones and taking the last one wins.
+    #[derive(Clone)]
+    struct Nim(u8);
+
+    impl Game for Nim {
+        type Move = u8;
+
+        fn moves(&self) -> impl Iterator<Item = u8> + '_ {
+            (1..=3).filter(|&take| take <= self.0)
+        }
+
+        fn apply(&self, mv: u8) -> Self {
+            Nim(self.0 - mv)
+        }
+
+        fn is_win_after(&self, mv: u8) -> bool {
+            mv == self.0
+        }
+
+        fn terminal(&self) -> Option<Outcome> {
+            // The previous player took the last stone.
+            (self.0 == 0).then_some(Outcome::Loss)
+        }
+    }
+
+    #[test]
+    fn nim() {
+        for heap in 0..20 {
+            let expected = if heap % 4 == 0 { -1 } else { 1 };
+            assert_eq!(negamax(&Nim(heap), -1, 1), expected, "heap {heap}");
+        }
+    }
+
+    #[test]
+    fn position_moves() {
+        let moves = Position::new().moves().collect::<alloc::vec::Vec<_>>();
+        assert_eq!(moves, [3, 2, 4, 1, 5, 0, 6]);
+        let pos = Position::from_string("444444").unwrap();
+        assert!(pos.moves().all(|col| col != 3));
+        assert_eq!(pos.moves().count(), 6);
+        assert_eq!(Position::new().terminal(), None);
+        let full = Position::from_string("656173566152215676422337377473141445425321").unwrap();
+        assert_eq!(full.terminal(), Some(Outcome::Draw));
+        assert_eq!(full.moves().count(), 0);
+    }
+
+    #[test]
+    fn weak_solve() {
+        // The generic search agrees with the scores of the benchmark file.
+        let lines = std::fs::read_to_string("./benchmark_files/end_easy").unwrap();
+        for line in lines.lines().take(100) {
+            let mut parts = line.split(' ');
+            let pos = Position::from_string(parts.next().unwrap()).unwrap();
+            let score = parts.next().unwrap().parse::<isize>().unwrap();
+            assert_eq!(negamax(&pos, -1, 1), score.signum(), "{line}");
+        }
+    }
+}
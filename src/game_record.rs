@@ -0,0 +1,242 @@
+use crate::position::{Column, Position, PositionParseError};
+use alloc::vec::Vec;
+
+/// Named openings, given as the 1-based columns played from the starting position.
+/// Mirrored lines share a name. The names describe where the first stone goes, after
+/// the result of perfect play from there, which is known since the game was solved.
+const OPENINGS: [(&str, &str); 4] = [
+    // The first player wins: L. V. Allis, "A Knowledge-based Approach of Connect-Four",
+    // M.Sc. thesis, Vrije Universiteit Amsterdam, 1988.
+    ("4", "Middle column"),
+    // The second player draws: J. Tromp, "John's Connect Four Playground", 1995.
+    ("3", "Adjacent to the middle"),
+    // The second player wins: J. Tromp, "John's Connect Four Playground", 1995.
+    ("2", "Outer column"),
+    ("1", "Outer column"),
+];
+
+/// A game: a `Position` together with the columns that were played to reach it, for
+/// the tools that care about the order of the moves, like saving or reviewing games.
+///
+/// The solver only needs the board, so the moves are kept out of `Position`, which is
+/// cloned at every node of the search.
+#[derive(Clone)]
+pub struct GameRecord {
+    position: Position,
+    /// `None` if the moves that led to the position are unknown.
+    moves: Option<Vec<Column>>,
+}
+
+impl Default for GameRecord {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// A game from a position whose moves are unknown, like one parsed from a grid with
+/// `Position::from_grid_string()` or found in a book by its key. Its `move_history()`
+/// is empty, and stays empty when more moves are played.
+impl From<Position> for GameRecord {
+    fn from(position: Position) -> Self {
+        Self {
+            position,
+            moves: None,
+        }
+    }
+}
+
+impl GameRecord {
+    /// The starting position, with no moves played.
+    #[must_use]
+    pub fn new() -> Self {
+        Self {
+            position: Position::new(),
+            moves: Some(Vec::new()),
+        }
+    }
+
+    /// Parse a string of 1-based columns, see `Position::from_string()`.
+    ///
+    /// Example:
+    /// ```
+    /// use connect_4::game_record::GameRecord;
+    /// let game = GameRecord::from_string("4456").unwrap();
+    /// assert_eq!(game.move_history(), [3, 3, 4, 5]);
+    /// assert_eq!(game.position().nb_moves(), 4);
+    /// ```
+    pub fn from_string(moves: &str) -> Result<Self, PositionParseError> {
+        let position = Position::from_string(moves)?;
+        // Every character is a valid column, otherwise parsing the position failed.
+        let moves = Some(moves.bytes().map(|c| c - b'1').collect());
+        Ok(Self { position, moves })
+    }
+
+    /// Play the 0-based column `col`, which has to be playable and not winning, like
+    /// for `Position::play_col()`.
+    pub fn play_col(&mut self, col: Column) {
+        self.position.play_col(col);
+        if let Some(moves) = &mut self.moves {
+            moves.push(col);
+        }
+    }
+
+    /// The position reached after the moves of the game.
+    #[must_use]
+    pub fn position(&self) -> &Position {
+        &self.position
+    }
+
+    /// Get the 0-based columns of all the moves played since the beginning of the game,
+    /// in the order they were played. This is best-effort: it is empty if the moves
+    /// are unknown, see `has_history()`.
+    #[must_use]
+    pub fn move_history(&self) -> &[Column] {
+        self.moves.as_deref().unwrap_or_default()
+    }
+
+    /// Returns false for games created from a position whose moves are unknown, see
+    /// `GameRecord::from`.
+    #[must_use]
+    pub fn has_history(&self) -> bool {
+        self.moves.is_some()
+    }
+
+    /// Returns true if both games played the same moves in the same order. Unlike
+    /// comparing the positions, transpositions are different games. Games whose moves
+    /// are unknown are only compared by their position.
+    ///
+    /// Example:
+    /// ```
+    /// use connect_4::game_record::GameRecord;
+    /// let game = GameRecord::from_string("4455").unwrap();
+    /// let transposed = GameRecord::from_string("5544").unwrap();
+    /// assert!(game.position() == transposed.position());
+    /// assert!(!game.same_game(&transposed));
+    /// assert!(game.same_game(&game.clone()));
+    /// ```
+    #[must_use]
+    pub fn same_game(&self, other: &Self) -> bool {
+        self.position == other.position && self.moves == other.moves
+    }
+
+    /// Get the name of the opening that was played, see `OPENINGS`. Returns `None` if no
+    /// moves were played, if the moves are unknown, or once the moves leave the lines of
+    /// the table, which is where the known opening theory stops.
+    ///
+    /// Example:
+    /// ```
+    /// use connect_4::game_record::GameRecord;
+    /// let game = GameRecord::from_string("4").unwrap();
+    /// assert_eq!(game.opening_name(), Some("Middle column"));
+    /// let game = GameRecord::from_string("44").unwrap();
+    /// assert_eq!(game.opening_name(), None);
+    /// ```
+    #[must_use]
+    pub fn opening_name(&self) -> Option<&'static str> {
+        let moves = self.moves.as_deref().filter(|moves| !moves.is_empty())?;
+        let follows = |line: &str, mirrored: bool| {
+            moves.len() <= line.len()
+                && moves.iter().zip(line.bytes()).all(|(&col, c)| {
+                    let col = if mirrored {
+                        Position::WIDTH - 1 - col
+                    } else {
+                        col
+                    };
+                    c == b'1' + col
+                })
+        };
+        OPENINGS
+            .iter()
+            .find(|(line, _)| follows(line, false) || follows(line, true))
+            .map(|&(_, name)| name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::position::RenderOpts;
+
+    #[test]
+    fn move_history() {
+        let moves = [3, 3, 2, 6, 0, 0, 5, 4, 1, 1, 6, 6];
+        let mut game = GameRecord::new();
+        for &col in &moves {
+            game.play_col(col);
+        }
+        assert_eq!(game.move_history(), moves);
+        assert!(GameRecord::new().move_history().is_empty());
+        // A game that fills the whole board without an alignment.
+        let line = "656173566152215676422337377473141445425321";
+        let mut game = GameRecord::new();
+        for (i, c) in line.bytes().enumerate() {
+            assert!(!game.position().is_winning_move(c - b'1'));
+            game.play_col(c - b'1');
+            assert_eq!(
+                game.move_history().len(),
+                game.position().nb_moves() as usize
+            );
+            assert_eq!(game.move_history().last(), Some(&(c - b'1')));
+            assert_eq!(game.position().nb_moves() as usize, i + 1);
+        }
+        assert_eq!(
+            game.position().nb_moves(),
+            Position::WIDTH * Position::HEIGHT
+        );
+    }
+
+    #[test]
+    fn unknown_history() {
+        let pos = Position::from_string("4455").unwrap();
+        let grid = Position::from_grid_string(&pos.to_ascii_art(RenderOpts::default())).unwrap();
+        let mut game = GameRecord::from(grid);
+        assert!(!game.has_history());
+        assert!(game.move_history().is_empty());
+        game.play_col(0);
+        assert!(game.move_history().is_empty());
+        assert_eq!(game.position().nb_moves(), 5);
+        // Only the position is known, so it decides whether the games are the same.
+        let mut other = GameRecord::from(pos);
+        assert!(!game.same_game(&other));
+        other.play_col(0);
+        assert!(game.same_game(&other));
+        assert!(!game.same_game(&GameRecord::from_string("44551").unwrap()));
+    }
+
+    #[test]
+    fn opening_names() {
+        let name = |moves: &str| GameRecord::from_string(moves).unwrap().opening_name();
+        assert_eq!(name(""), None);
+        assert_eq!(name("4"), Some("Middle column"));
+        assert_eq!(name("3"), Some("Adjacent to the middle"));
+        // Mirrored lines have the same name.
+        assert_eq!(name("5"), Some("Adjacent to the middle"));
+        assert_eq!(name("7"), Some("Outer column"));
+        assert_eq!(name("6"), Some("Outer column"));
+        // The table stops after the first move.
+        assert_eq!(name("44"), None);
+        assert_eq!(name("4456"), None);
+        let grid = Position::from_grid_string(
+            &Position::from_string("4")
+                .unwrap()
+                .to_ascii_art(RenderOpts::default()),
+        )
+        .unwrap();
+        assert_eq!(GameRecord::from(grid).opening_name(), None);
+    }
+
+    #[test]
+    fn same_game() {
+        let game = GameRecord::from_string("4455").unwrap();
+        let transposed = GameRecord::from_string("5544").unwrap();
+        assert!(game.position() == transposed.position());
+        assert!(!game.same_game(&transposed));
+        assert!(game.same_game(&GameRecord::from_string("4455").unwrap()));
+        assert!(GameRecord::new().same_game(&GameRecord::default()));
+        assert!(GameRecord::from_string("44").is_ok_and(|game| !game.same_game(&GameRecord::new())));
+        assert_eq!(
+            GameRecord::from_string("48").err(),
+            Some(PositionParseError::InvalidColumn(8))
+        );
+    }
+}
@@ -4,32 +4,65 @@
     clippy::cast_possible_truncation,
     clippy::cast_precision_loss
 )]
+#![cfg_attr(not(feature = "std"), no_std)]
+extern crate alloc;
+
+mod compat;
+pub mod error;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+pub mod game;
+pub mod game_record;
+mod logging;
 pub mod move_sorter;
 pub mod opening_book;
 pub mod position;
 pub mod solver;
+pub mod tablebase;
 pub mod transposition_table;
 
+#[cfg(feature = "std")]
 pub mod game_solver {
 
     use std::cmp::Ordering;
-    use std::io::{self, BufReader, Write};
+    use std::io::{self, BufReader, IsTerminal, Write};
+    use std::sync::{atomic, Arc};
     use std::{
         fs::{self, File},
         path::PathBuf,
     };
     use std::{io::prelude::*, vec};
 
-    use std::time::Instant;
+    use std::time::{Duration, Instant};
 
-    use crate::opening_book::OpeningBook;
-    use crate::position::{self, Position};
-    use crate::solver::Solver;
+    use crate::error::Connect4Error;
+    use crate::game_record::GameRecord;
+    use crate::logging::warning;
+    use crate::opening_book::{BookKind, OpeningBook};
+    use crate::position::{self, Position, RenderOpts};
+    use crate::solver::{splitmix64, MoveTag, Outcome, Solver, TableBound, TieBreak, Verbosity};
+    use crate::transposition_table::TranspositionTable;
 
+    /// The interactive command line interface.
+    ///
+    /// The transposition table of the solver is shared by all the commands, so the
+    /// results of earlier searches speed up the commands that solve related positions:
+    /// `solve`, `solve-board`, `analyze`, `explain`, `window`, `review` and `replay --eval`.
+    /// It is only cleared by `clear-tt`, `solve --cold` and interrupted searches, unless
+    /// `keep_tt` is turned off with `toggle-keep-tt`, in which case it is cleared before
+    /// each of these commands.
+    /// The benchmarks and `stress` always use their own solver.
     pub struct Parser {
         solver: Solver,
         weak: bool,
         num_threads: u8,
+        keep_tt: bool,
+        /// If recording is turned on, the results of `solve` are stored in this book.
+        session_book: Option<OpeningBook>,
+        /// The interrupt flag of the solver, set by the Ctrl-C handler.
+        interrupt: Arc<atomic::AtomicBool>,
+        /// Set when `bench --strict` found a wrong score or couldn't run, see `failed()`.
+        failed: bool,
     }
 
     const DEFAULT_BOOK_PATH: &str = "./opening_book.book";
@@ -37,31 +70,158 @@ pub mod game_solver {
     enum Command {
         PlayMoves(Vec<position::Column>),
         SetPosition(Vec<position::Column>),
-        Solve,
+        /// Solve the current position, clearing the transposition table first if `cold`
+        /// is true, and showing a progress bar instead of the search info if `progress` is.
+        Solve {
+            cold: bool,
+            progress: bool,
+        },
+        /// Solve a board given as a grid, see `Position::from_grid_string`.
+        SolveBoard(Position),
         Analyze,
+        Explain,
         ToggleWeak,
+        ToggleKeepTT,
+        Info,
+        ToggleSessionBook,
+        SaveSessionBook(PathBuf),
         Help(Option<Box<Command>>),
         ClearTT,
-        Bench(Option<PathBuf>, Option<usize>),
+        TTProbe,
+        Bench {
+            path: Option<PathBuf>,
+            max_lines: Option<usize>,
+            resume: Option<PathBuf>,
+            quiet: bool,
+            strict: bool,
+        },
+        BenchMovegen(Option<PathBuf>, Option<usize>),
         LoadBook(PathBuf),
+        CheckBook,
         GenerateBook(usize, PathBuf),
         SetNumThreads(u8),
+        SetAbortInterval(u64),
+        SetVerbosity(Verbosity),
+        Stress(Position, usize, u8),
+        Window(isize, isize),
+        GenDataset(usize, PathBuf, Option<u64>),
+        /// The quotas, the path, the seed and the maximal number of playouts.
+        GenBalancedDataset(OutcomeCounts, PathBuf, Option<u64>, Option<usize>),
+        Replay(PathBuf, ReplayOptions),
+        Review(PathBuf),
         Quit,
     }
 
+    /// How the `replay` command steps through a game.
+    struct ReplayOptions {
+        /// The time to wait between moves, or `None` to wait for Enter.
+        pause: Option<Duration>,
+        /// Whether to solve the position after each move.
+        eval: bool,
+    }
+
+    impl Default for ReplayOptions {
+        fn default() -> Self {
+            Self {
+                pause: Some(Duration::from_secs(1)),
+                eval: false,
+            }
+        }
+    }
+
     impl Parser {
         #[must_use]
         pub fn new(weak: bool) -> Self {
+            let solver = Solver::new(None);
+            let interrupt = solver.interrupt_handle();
             Self {
-                solver: Solver::new(None),
+                solver,
                 weak,
                 num_threads: 1,
+                keep_tt: true,
+                session_book: None,
+                interrupt,
+                failed: false,
+            }
+        }
+
+        /// Load the opening book from the default path `./opening_book.book`, if there is one.
+        ///
+        /// Meant to be called on startup, so a book doesn't have to be loaded by hand every
+        /// session. A book that fails to load is reported, and the solver continues without
+        /// a book. Returns `true` if a book was loaded.
+        pub fn load_default_book(&mut self) -> bool {
+            // Writing to stdout only fails if it was closed, then there's no one to tell.
+            self.load_book_if_present(std::path::Path::new(DEFAULT_BOOK_PATH), &mut io::stdout())
+                .unwrap_or(false)
+        }
+
+        /// `load_default_book()` from the given path, writing the confirmation to `out`.
+        fn load_book_if_present(
+            &mut self,
+            path: &std::path::Path,
+            out: &mut impl Write,
+        ) -> io::Result<bool> {
+            if !path.exists() {
+                return Ok(false);
             }
+            match OpeningBook::load(path) {
+                Ok(book) => {
+                    writeln!(
+                        out,
+                        "Loaded book in {:?} ({} entries)",
+                        path,
+                        book.num_entries()
+                    )?;
+                    self.solver.set_book(book);
+                    Ok(true)
+                }
+                Err(e) => {
+                    eprintln!(
+                        "Could not load the book in {path:?}, continuing without a book: '{e}'"
+                    );
+                    Ok(false)
+                }
+            }
+        }
+
+        /// Returns `true` if a `bench --strict` found a wrong score or couldn't run,
+        /// so the process can exit with an error, e.g. in CI.
+        #[must_use]
+        pub fn failed(&self) -> bool {
+            self.failed
+        }
+
+        /// Make Ctrl-C stop the running search and return to the prompt, instead of
+        /// killing the process. Pressing Ctrl-C again before the next command quits.
+        ///
+        /// Only one parser can handle Ctrl-C, returns `false` if the handler could not be
+        /// installed.
+        pub fn handle_ctrl_c(&self) -> bool {
+            let interrupt = self.interrupt.clone();
+            // The handler runs on its own thread, so it can exit the process directly.
+            ctrlc::set_handler(move || {
+                if interrupt.swap(true, atomic::Ordering::SeqCst) {
+                    std::process::exit(130);
+                }
+            })
+            .is_ok()
+        }
+
+        /// Returns `true` if the last search was interrupted. Since the transposition
+        /// table can contain wrong entries after that, it is cleared.
+        fn was_interrupted(&mut self, out: &mut impl Write) -> io::Result<bool> {
+            if !self.interrupt.load(atomic::Ordering::SeqCst) {
+                return Ok(false);
+            }
+            self.solver.reset_transposition_table();
+            writeln!(out, "\nSearch interrupted, cleared the transposition table")?;
+            Ok(true)
         }
 
         /// Parse the arguments into a [`Command`]. If `recurse` is true,
         /// also recursively parse the argument to [`Command::Help`] as a [`Command`].
-        fn parse_command(mut args: std::str::Split<char>, recurse: bool) -> Option<Command> {
+        fn parse_command(mut args: std::str::SplitWhitespace, recurse: bool) -> Option<Command> {
             let first = args.next()?;
             match &first.to_lowercase() as &str {
                 "moves" | "play" | "move" => {
@@ -90,9 +250,52 @@ pub mod game_solver {
                     };
                     Some(Command::SetPosition(moves))
                 }
-                "solve" => Some(Command::Solve),
+                "solve" => {
+                    let (mut cold, mut progress) = (false, false);
+                    for arg in args {
+                        match arg {
+                            "--cold" => cold = true,
+                            "--progress" => progress = true,
+                            arg => {
+                                eprintln!("Unknown argument for solve: {arg}");
+                                return None;
+                            }
+                        }
+                    }
+                    Some(Command::Solve { cold, progress })
+                }
+                "solve-board" => {
+                    if !recurse {
+                        return Some(Command::SolveBoard(Position::new()));
+                    }
+                    // The rows can also be given as separate arguments.
+                    let grid = args.collect::<Vec<_>>().join("/");
+                    match Position::from_grid_string(&grid) {
+                        Ok(pos) => Some(Command::SolveBoard(pos)),
+                        Err(e) => {
+                            eprintln!("Invalid board: {e}");
+                            None
+                        }
+                    }
+                }
                 "analyze" => Some(Command::Analyze),
+                "explain" => Some(Command::Explain),
                 "toggle-weak" => Some(Command::ToggleWeak),
+                "toggle-keep-tt" => Some(Command::ToggleKeepTT),
+                "info" => Some(Command::Info),
+                "toggle-session-book" => Some(Command::ToggleSessionBook),
+                "save-session-book" => {
+                    if !recurse {
+                        return Some(Command::SaveSessionBook(PathBuf::new()));
+                    }
+                    match args.next() {
+                        Some(path) => Some(Command::SaveSessionBook(PathBuf::from(path))),
+                        None => {
+                            eprintln!("Expected a path to store the book");
+                            None
+                        }
+                    }
+                }
                 "help" => {
                     if recurse {
                         let command = Self::parse_command(args, false);
@@ -105,36 +308,72 @@ pub mod game_solver {
                     }
                 }
                 "clear-tt" => Some(Command::ClearTT),
-                "bench" => match args.next() {
+                "tt-probe" => Some(Command::TTProbe),
+                "bench" | "bench-movegen" => match args.next() {
                     None => {
                         if recurse {
                             eprintln!("Expected bench file path or 'all'");
                             None
+                        } else if first == "bench" {
+                            Some(Command::Bench {
+                                path: None,
+                                max_lines: None,
+                                resume: None,
+                                quiet: false,
+                                strict: false,
+                            })
                         } else {
-                            Some(Command::Bench(None, None))
+                            Some(Command::BenchMovegen(None, None))
                         }
                     }
                     Some(path) => {
-                        let max_lines = match args.next() {
-                            None => None,
-                            Some(num) => match num.parse::<usize>() {
-                                Ok(n) => Some(n),
-                                Err(e) => {
-                                    eprintln!("Expected maximum number of lines to run ({e})");
+                        let mut max_lines = None;
+                        let mut resume = None;
+                        let mut quiet = false;
+                        let mut strict = false;
+                        while let Some(arg) = args.next() {
+                            if arg == "--quiet" && first == "bench" {
+                                quiet = true;
+                            } else if arg == "--strict" && first == "bench" {
+                                strict = true;
+                            } else if arg == "--resume" && first == "bench" {
+                                let Some(state) = args.next() else {
+                                    eprintln!("Expected a path to the state file after '--resume'");
                                     return None;
+                                };
+                                resume = Some(PathBuf::from(state));
+                            } else {
+                                match arg.parse::<usize>() {
+                                    Ok(n) => max_lines = Some(n),
+                                    Err(e) => {
+                                        eprintln!("Expected maximum number of lines to run ({e})");
+                                        return None;
+                                    }
                                 }
-                            },
-                        };
-                        if path == "all" {
-                            Some(Command::Bench(None, max_lines))
+                            }
+                        }
+                        let path = if path == "all" {
+                            None
                         } else if std::path::Path::new(path).exists() {
-                            Some(Command::Bench(Some(PathBuf::from(path)), max_lines))
+                            Some(PathBuf::from(path))
                         } else {
                             eprintln!("Invalid path {path}");
-                            None
+                            return None;
+                        };
+                        if first == "bench" {
+                            Some(Command::Bench {
+                                path,
+                                max_lines,
+                                resume,
+                                quiet,
+                                strict,
+                            })
+                        } else {
+                            Some(Command::BenchMovegen(path, max_lines))
                         }
                     }
                 },
+                "check-book" => Some(Command::CheckBook),
                 "load-book" => {
                     if !recurse {
                         return Some(Command::LoadBook(PathBuf::from("")));
@@ -198,6 +437,217 @@ pub mod game_solver {
                         },
                     }
                 }
+                "abort-interval" => {
+                    if !recurse {
+                        return Some(Command::SetAbortInterval(0));
+                    }
+                    match args.next() {
+                        None => None,
+                        Some(num) => match num.parse::<u64>() {
+                            Ok(n) if n.is_power_of_two() => Some(Command::SetAbortInterval(n)),
+                            Ok(n) => {
+                                eprintln!("The interval must be a power of two, not {n}");
+                                None
+                            }
+                            Err(e) => {
+                                eprintln!("Expected a number of nodes ({e})");
+                                None
+                            }
+                        },
+                    }
+                }
+                "verbosity" => {
+                    if !recurse {
+                        return Some(Command::SetVerbosity(Verbosity::Full));
+                    }
+                    match args.next() {
+                        Some("silent") => Some(Command::SetVerbosity(Verbosity::Silent)),
+                        Some("summary") => Some(Command::SetVerbosity(Verbosity::Summary)),
+                        Some("full") => Some(Command::SetVerbosity(Verbosity::Full)),
+                        _ => {
+                            eprintln!("Expected 'silent', 'summary' or 'full'");
+                            None
+                        }
+                    }
+                }
+                "stress" => {
+                    if !recurse {
+                        return Some(Command::Stress(Position::new(), 0, 0));
+                    }
+                    let Some(position_str) = args.next() else {
+                        eprintln!("Expected a position to solve");
+                        return None;
+                    };
+                    let pos = match Position::from_string(position_str) {
+                        Ok(pos) => pos,
+                        Err(e) => {
+                            eprintln!("Invalid position {position_str} ({e})");
+                            return None;
+                        }
+                    };
+                    let runs = match args.next().map(str::parse::<usize>) {
+                        Some(Ok(n)) if n > 0 => n,
+                        _ => {
+                            eprintln!("Expected a positive number of runs");
+                            return None;
+                        }
+                    };
+                    let threads = match args.next().map(str::parse::<u8>) {
+                        Some(Ok(n)) => n,
+                        _ => {
+                            eprintln!("Expected a number of threads");
+                            return None;
+                        }
+                    };
+                    Some(Command::Stress(pos, runs, threads))
+                }
+                "window" => {
+                    if !recurse {
+                        return Some(Command::Window(0, 0));
+                    }
+                    let bounds = args
+                        .take(2)
+                        .map(str::parse::<isize>)
+                        .collect::<Result<Vec<_>, _>>();
+                    match bounds.as_deref() {
+                        Ok(&[alpha, beta]) if alpha < beta => Some(Command::Window(alpha, beta)),
+                        Ok(&[alpha, beta]) => {
+                            eprintln!("The window [{alpha}, {beta}] is empty");
+                            None
+                        }
+                        Ok(_) => {
+                            eprintln!("Expected two bounds alpha and beta");
+                            None
+                        }
+                        Err(e) => {
+                            eprintln!("Bounds should be numbers ({e})");
+                            None
+                        }
+                    }
+                }
+                "gen-dataset" => {
+                    if !recurse {
+                        return Some(Command::GenDataset(0, PathBuf::new(), None));
+                    }
+                    let num_positions = match args.next().map(str::parse::<usize>) {
+                        Some(Ok(n)) => n,
+                        _ => {
+                            eprintln!("Expected the number of positions");
+                            return None;
+                        }
+                    };
+                    let Some(path) = args.next() else {
+                        eprintln!("Expected a path for the dataset");
+                        return None;
+                    };
+                    let seed = match args.next().map(str::parse::<u64>) {
+                        None => None,
+                        Some(Ok(seed)) => Some(seed),
+                        Some(Err(e)) => {
+                            eprintln!("Expected a seed ({e})");
+                            return None;
+                        }
+                    };
+                    Some(Command::GenDataset(
+                        num_positions,
+                        PathBuf::from(path),
+                        seed,
+                    ))
+                }
+                "gen-balanced-dataset" => {
+                    if !recurse {
+                        return Some(Command::GenBalancedDataset(
+                            OutcomeCounts::default(),
+                            PathBuf::new(),
+                            None,
+                            None,
+                        ));
+                    }
+                    let mut quotas = [0; 3];
+                    for quota in &mut quotas {
+                        match args.next().map(str::parse::<usize>) {
+                            Some(Ok(n)) => *quota = n,
+                            _ => {
+                                eprintln!("Expected the number of wins, draws and losses");
+                                return None;
+                            }
+                        }
+                    }
+                    let [wins, draws, losses] = quotas;
+                    let Some(path) = args.next() else {
+                        eprintln!("Expected a path for the dataset");
+                        return None;
+                    };
+                    let mut seed = None;
+                    let mut max_attempts = None;
+                    while let Some(arg) = args.next() {
+                        if arg == "--max-attempts" {
+                            match args.next().map(str::parse::<usize>) {
+                                Some(Ok(n)) => max_attempts = Some(n),
+                                _ => {
+                                    eprintln!("Expected the maximal number of playouts");
+                                    return None;
+                                }
+                            }
+                        } else {
+                            match arg.parse::<u64>() {
+                                Ok(n) => seed = Some(n),
+                                Err(e) => {
+                                    eprintln!("Expected a seed ({e})");
+                                    return None;
+                                }
+                            }
+                        }
+                    }
+                    Some(Command::GenBalancedDataset(
+                        OutcomeCounts {
+                            wins,
+                            draws,
+                            losses,
+                        },
+                        PathBuf::from(path),
+                        seed,
+                        max_attempts,
+                    ))
+                }
+                "review" => {
+                    if !recurse {
+                        return Some(Command::Review(PathBuf::new()));
+                    }
+                    let Some(path) = args.next() else {
+                        eprintln!("Expected the path of a game to review");
+                        return None;
+                    };
+                    Some(Command::Review(PathBuf::from(path)))
+                }
+                "replay" => {
+                    if !recurse {
+                        return Some(Command::Replay(PathBuf::new(), ReplayOptions::default()));
+                    }
+                    let Some(path) = args.next() else {
+                        eprintln!("Expected the path of a game to replay");
+                        return None;
+                    };
+                    let mut options = ReplayOptions::default();
+                    while let Some(arg) = args.next() {
+                        match arg {
+                            "--eval" => options.eval = true,
+                            "--enter" => options.pause = None,
+                            "--delay" => match args.next().map(str::parse::<u64>) {
+                                Some(Ok(ms)) => options.pause = Some(Duration::from_millis(ms)),
+                                _ => {
+                                    eprintln!("Expected a delay in milliseconds");
+                                    return None;
+                                }
+                            },
+                            arg => {
+                                eprintln!("Unknown argument for replay: {arg}");
+                                return None;
+                            }
+                        }
+                    }
+                    Some(Command::Replay(PathBuf::from(path), options))
+                }
                 "quit" => Some(Command::Quit),
                 _ => {
                     eprintln!("Don't know the command: {first}");
@@ -210,228 +660,1019 @@ pub mod game_solver {
             }
         }
 
+        /// Play the 1-based columns `moves` and show the resulting position.
+        /// If one of the moves is invalid, the moves before it are still played.
+        fn play_moves(
+            out: &mut impl Write,
+            pos: &mut Position,
+            moves: &[position::Column],
+        ) -> io::Result<()> {
+            let (played, result) = pos.play_sequence_partial(moves);
+            if position::play_result_ok(result) {
+                writeln!(out, "Played columns: {moves:?}")?;
+            } else if played > 0 {
+                writeln!(
+                    out,
+                    "Only played the first {played} column(s): {:?}",
+                    &moves[..played]
+                )?;
+            }
+            writeln!(out, "\nCurrent position:")?;
+            write!(out, "{}", pos.to_ascii_art(RenderOpts::default()))
+        }
+
+        /// Print the configuration the solver was built with, and the current settings.
+        pub fn print_info(&self) {
+            // Writing to stdout only fails if it was closed, then there's no one to tell.
+            let _ = self.write_info(&mut io::stdout());
+        }
+
+        /// `print_info()` to the given writer.
+        fn write_info(&self, out: &mut impl Write) -> io::Result<()> {
+            writeln!(
+                out,
+                "Board: {}x{} (width x height), scores between {} and {}",
+                Position::WIDTH,
+                Position::HEIGHT,
+                Position::MIN_SCORE,
+                Position::MAX_SCORE
+            )?;
+            writeln!(
+                out,
+                "Transposition table: {} entries (log size {})",
+                TranspositionTable::SIZE,
+                TranspositionTable::LOG_SIZE
+            )?;
+            writeln!(
+                out,
+                "Weak: {}, threads: {}, keep transposition table: {}",
+                self.weak, self.num_threads, self.keep_tt
+            )?;
+            writeln!(
+                out,
+                "Opening book: {} entries",
+                self.solver.config().book_entries
+            )
+        }
+
+        /// Read commands from stdin and run them until `quit` or the end of the input.
         pub fn run(&mut self) -> io::Result<()> {
+            self.solver.set_verbosity(Verbosity::Full);
+            self.run_commands(io::stdin().lock(), io::stdout(), true)
+        }
+
+        /// Like `run()`, but read the commands from `input` and write the output to `out`,
+        /// e.g. to run a script of commands and check the output in a test.
+        ///
+        /// Errors are still printed to stderr. The steps of the searches, which the solver
+        /// prints itself, are not shown, unless the `verbosity full` command turns them on,
+        /// see `Solver::set_verbosity`. The summary of `verbosity summary` goes to `out`.
+        pub fn run_with(&mut self, input: impl BufRead, out: impl Write) -> io::Result<()> {
+            self.solver.set_verbosity(Verbosity::Silent);
+            self.run_commands(input, out, false)
+        }
+
+        /// The loop of `run()` and `run_with()`, `to_stdout` is true if `out` is stdout.
+        fn run_commands(
+            &mut self,
+            mut input: impl BufRead,
+            mut out: impl Write,
+            to_stdout: bool,
+        ) -> io::Result<()> {
             let mut pos = Position::new();
-            let mut input = String::new();
-            print!("> ");
-            io::stdout().flush()?;
-            while io::stdin().read_line(&mut input).is_ok() {
-                let args = input.trim().split(' ');
-                if let Some(command) = Self::parse_command(args, true) {
-                    match command {
-                        Command::PlayMoves(moves) => {
-                            if position::play_result_ok(pos.play_sequence(&moves)) {
-                                println!("Played columns: {moves:?}");
-                            }
-                            println!("\nCurrent position:");
-                            pos.display_position();
-                        }
-                        Command::SetPosition(moves) => {
-                            pos = Position::new();
-                            if position::play_result_ok(pos.play_sequence(&moves)) {
-                                println!("Played columns: {moves:?}");
+            let mut line = String::new();
+            write!(out, "> ")?;
+            out.flush()?;
+            // Stop at the end of the input, e.g. of a script piped into the program, which
+            // would otherwise keep reading nothing.
+            'lines: while matches!(input.read_line(&mut line), Ok(n) if n > 0) {
+                // A Ctrl-C at the prompt only counts towards quitting.
+                self.interrupt.store(false, atomic::Ordering::SeqCst);
+                // Several commands can be given on one line, separated by ';'. A command
+                // that can't be parsed is skipped, but a search stopped with Ctrl-C also
+                // stops the rest of the line.
+                for segment in line.split(';') {
+                    if self.interrupt.load(atomic::Ordering::SeqCst) {
+                        break;
+                    }
+                    // An empty line gives no command, so we just prompt again.
+                    let args = segment.split_whitespace();
+                    if let Some(command) = Self::parse_command(args, true) {
+                        let clear_tt = match command {
+                            Command::Solve { cold, .. } => cold || !self.keep_tt,
+                            Command::SolveBoard(_) => !self.keep_tt,
+                            Command::Analyze | Command::Explain | Command::Window(_, _) => {
+                                !self.keep_tt
                             }
-                            println!("\nCurrent position:");
-                            pos.display_position();
-                        }
-                        Command::Solve => {
-                            let now = Instant::now();
-                            self.solve(&pos);
-                            println!("Took {:?}", now.elapsed());
-                        }
-                        Command::Analyze => {
-                            let now = Instant::now();
-                            self.analyze(&pos);
-                            println!("Took {:?}", now.elapsed());
-                        }
-                        Command::ToggleWeak => {
-                            self.weak = !self.weak;
-                            println!("Weak set to {}", self.weak);
+                            Command::Replay(_, ReplayOptions { eval, .. }) => eval && !self.keep_tt,
+                            Command::Review(_) => !self.keep_tt,
+                            _ => false,
+                        };
+                        if clear_tt {
+                            self.solver.reset_transposition_table();
                         }
-                        Command::Help(command) => {
-                            if let Some(command) = command {
-                                match *command {
-                                    Command::PlayMoves(_) => {
-                                        println!("moves/play/move <column> <column> ...");
-                                        println!(
-                                            "Play a sequence of moves from the current position"
-                                        );
-                                    }
-                                    Command::SetPosition(_) => {
-                                        println!("position <column> <column> ...");
-                                        println!("Set up a position by playing a sequence of moves from the starting position");
-                                    }
-                                    Command::Solve => {
-                                        println!("Solve the current position");
+                        match command {
+                            Command::PlayMoves(moves) => {
+                                Self::play_moves(&mut out, &mut pos, &moves)?;
+                            }
+                            Command::SolveBoard(board) => {
+                                write!(out, "{}", board.to_ascii_art(RenderOpts::default()))?;
+                                self.solve(&mut out, &board, false)?;
+                                if !self.was_interrupted(&mut out)? {
+                                    let best = self.solver.best_moves(&board, self.weak);
+                                    if !best.is_empty() {
+                                        let best =
+                                            best.iter().map(|col| col + 1).collect::<Vec<_>>();
+                                        writeln!(out, "Best move(s): {best:?}")?;
                                     }
-                                    Command::Analyze => {
-                                        println!("Analyze all the possible moves in the current position");
+                                }
+                            }
+                            Command::SetPosition(moves) => {
+                                pos = Position::new();
+                                Self::play_moves(&mut out, &mut pos, &moves)?;
+                            }
+                            Command::Solve { progress, .. } => {
+                                let now = Instant::now();
+                                self.solve(&mut out, &pos, progress)?;
+                                writeln!(out, "Took {:?}", now.elapsed())?;
+                            }
+                            Command::Analyze => {
+                                let now = Instant::now();
+                                self.analyze(&mut out, &pos)?;
+                                writeln!(out, "Took {:?}", now.elapsed())?;
+                            }
+                            Command::Explain => match self.solver.explain_best_move(&pos) {
+                                Some(text) => writeln!(out, "\n{text}")?,
+                                None => {
+                                    if !self.was_interrupted(&mut out)? {
+                                        writeln!(out, "No playable columns")?;
                                     }
-                                    Command::ToggleWeak => {
-                                        println!("Toggle using the weak or strong solver.");
-                                        println!("A weak solver only calculates win/draw/loss but not in how many moves");
+                                }
+                            },
+                            Command::ToggleWeak => {
+                                self.weak = !self.weak;
+                                writeln!(out, "Weak set to {}", self.weak)?;
+                            }
+                            Command::ToggleKeepTT => {
+                                self.keep_tt = !self.keep_tt;
+                                writeln!(out, "Keep transposition table set to {}", self.keep_tt)?;
+                            }
+                            Command::Info => self.write_info(&mut out)?,
+                            Command::ToggleSessionBook => {
+                                if self.session_book.take().is_some() {
+                                    writeln!(out, "Stopped recording solved positions, the session book was discarded")?;
+                                } else {
+                                    let kind = if self.weak {
+                                        BookKind::Weak
+                                    } else {
+                                        BookKind::Strong
+                                    };
+                                    self.session_book = Some(OpeningBook::with_kind(kind));
+                                    writeln!(
+                                        out,
+                                        "Recording solved positions in a {} session book",
+                                        kind.name()
+                                    )?;
+                                }
+                            }
+                            Command::SaveSessionBook(path) => match &self.session_book {
+                                None => eprintln!(
+                                    "Not recording a session book, use 'toggle-session-book' first"
+                                ),
+                                Some(book) => {
+                                    if let Err(e) = book.store(&path) {
+                                        eprintln!("Err while storing book: '{e}'");
+                                    } else {
+                                        writeln!(
+                                            out,
+                                            "Stored session book in {:?} ({} entries)",
+                                            path,
+                                            book.num_entries()
+                                        )?;
                                     }
-                                    Command::Help(_) => {
-                                        println!("help <command>");
-                                        println!("Get help about a specific command");
+                                }
+                            },
+                            Command::Help(command) => {
+                                if let Some(command) = command {
+                                    match *command {
+                                        Command::PlayMoves(_) => {
+                                            writeln!(out, "moves/play/move <column> <column> ...")?;
+                                            writeln!(
+                                            out,
+                                            "Play a sequence of moves from the current position"
+                                        )?;
+                                            writeln!(out, "Example: 'play 7 5 1 2' plays the columns 7, 5, 1 and 2 (columns are numbered from 1).")?;
+                                        }
+                                        Command::SetPosition(_) => {
+                                            writeln!(out, "position <column> <column> ...")?;
+                                            writeln!(out, "Set up a position by playing a sequence of moves from the starting position")?;
+                                            writeln!(out, "Example: 'position 4 4 5 3' (columns are numbered from 1).")?;
+                                        }
+                                        Command::SolveBoard(_) => {
+                                            writeln!(out, "solve-board <grid>")?;
+                                            writeln!(out, "Solve a board without playing its moves, and show the best moves. The current position stays the same.")?;
+                                            writeln!(out, "The grid has the rows from the top down, separated by '/' or spaces, with 'o' for the player who started, 'x' for the other player and '.' for an empty cell.")?;
+                                            writeln!(
+                                                out,
+                                                "A digit stands for that many empty cells."
+                                            )?;
+                                            writeln!(out, "Example: 'solve-board 7/7/7/7/3x3/2oox2' solves the position after the moves 4 4 3 5.")?;
+                                        }
+                                        Command::Solve { .. } => {
+                                            writeln!(out, "solve [--cold] [--progress]")?;
+                                            writeln!(out, "Solve the current position")?;
+                                            writeln!(out, "The transposition table is kept between commands, so solving the same position again searches fewer nodes.")?;
+                                            writeln!(out, "With '--cold', the table is cleared first, so the number of nodes is the same every time.")?;
+                                            writeln!(out, "With '--progress', a progress bar is shown instead of the search info. It compares the nodes searched to a rough estimate, so it can be far off.")?;
+                                            writeln!(out, "Example: 'solve --cold --progress'")?;
+                                        }
+                                        Command::Analyze => {
+                                            writeln!(out, "Analyze all the possible moves in the current position")?;
+                                            writeln!(out, "Prints the score of every column, see 'help' for the meaning of the scores.")?;
+                                            writeln!(out, "With the weak solver, see 'toggle-weak', it prints whether each column is a win, a draw or a loss.")?;
+                                            writeln!(out, "Example: 'analyze'")?;
+                                        }
+                                        Command::Explain => {
+                                            writeln!(out, "Explain the best move in the current position: its result, the threats it makes and the main line.")?;
+                                            writeln!(out, "Threats on odd rows (counted from 1 at the bottom) are good for the first player, on even rows for the second player.")?;
+                                            writeln!(out, "Example: 'explain'")?;
+                                        }
+                                        Command::ToggleWeak => {
+                                            writeln!(
+                                                out,
+                                                "Toggle using the weak or strong solver."
+                                            )?;
+                                            writeln!(out, "A weak solver only calculates win/draw/loss but not in how many moves")?;
+                                            writeln!(out, "Example: 'toggle-weak', then 'solve' only gives 1 (win), 0 (draw) or -1 (loss).")?;
+                                        }
+                                        Command::Info => {
+                                            writeln!(out, "Show the board dimensions, the size of the transposition table and the current settings.")?;
+                                            writeln!(out, "Example: 'info'")?;
+                                        }
+                                        Command::ToggleSessionBook => {
+                                            writeln!(out, "Toggle recording the results of 'solve' in a session book.")?;
+                                            writeln!(out, "The book is strong if the strong solver is used when recording starts, and weak otherwise.")?;
+                                            writeln!(out, "Weak solves are not recorded in a strong book. Turning recording off discards the book.")?;
+                                            writeln!(out, "Example: 'toggle-session-book', then 'solve' a few positions and 'save-session-book ./session.book'.")?;
+                                        }
+                                        Command::SaveSessionBook(_) => {
+                                            writeln!(out, "save-session-book <path>")?;
+                                            writeln!(out, "Store the positions recorded since 'toggle-session-book' as an opening book.")?;
+                                            writeln!(
+                                                out,
+                                                "Example: 'save-session-book ./session.book'"
+                                            )?;
+                                        }
+                                        Command::ToggleKeepTT => {
+                                            writeln!(out, "Toggle keeping the transposition table between commands.")?;
+                                            writeln!(out, "When on (the default), the table is only cleared by 'clear-tt', which speeds up solving related positions.")?;
+                                            writeln!(out, "When off, it is cleared before every 'solve', 'solve-board', 'analyze', 'explain', 'window', 'review' and 'replay --eval', which makes the node counts reproducible.")?;
+                                            writeln!(out, "Example: 'toggle-keep-tt'")?;
+                                        }
+                                        Command::Help(_) => {
+                                            writeln!(out, "help <command>")?;
+                                            writeln!(out, "Get help about a specific command")?;
+                                            writeln!(out, "Example: 'help solve'")?;
+                                        }
+                                        Command::ClearTT => {
+                                            writeln!(
+                                                out,
+                                                "Clear the transposition table used by the solver."
+                                            )?;
+                                            writeln!(out, "Example: 'clear-tt'")?;
+                                        }
+                                        Command::TTProbe => {
+                                            writeln!(out, "Show the entry of the transposition table for the current position.")?;
+                                            writeln!(out, "The entry has the exact score, or a lower or upper bound, and the best column of the search that stored it.")?;
+                                            writeln!(out, "Forced moves and positions that weren't searched have no entry.")?;
+                                            writeln!(out, "Example: 'solve', then 'tt-probe'")?;
+                                        }
+                                        Command::Bench { .. } => {
+                                            writeln!(out, "bench <path> | 'all' [max_lines] [--resume <state-file>] [--quiet] [--strict]")?;
+                                            writeln!(out, "Run the benchmarks in the given file.")?;
+                                            writeln!(
+                                            out,
+                                            "Use 'all' instead of a path to run all benchmarks."
+                                        )?;
+                                            writeln!(out, "A number max_lines can be specified to only solve at most that many positions per file.")?;
+                                            writeln!(out, "With '--resume', the solved positions are recorded in the state file, and positions already in it are skipped, so an interrupted run can be continued.")?;
+                                            writeln!(out, "With '--quiet', the line being solved isn't shown, only the averages and the wrong scores. This is also done when the output isn't a terminal.")?;
+                                            writeln!(out, "With '--strict', a wrong score or an error makes the program exit with a failure code when it quits, for use in CI.")?;
+                                            writeln!(out, "Example: 'bench ./benchmark_files/end_easy 100' or 'bench all 100 --resume ./bench_state.txt --quiet'")?;
+                                        }
+                                        Command::BenchMovegen(_, _) => {
+                                            writeln!(
+                                                out,
+                                                "bench-movegen <path> | 'all' [max_lines] "
+                                            )?;
+                                            writeln!(out, "Time only the move generation and move scoring on the positions in the given file, without solving them.")?;
+                                            writeln!(out, "The arguments are the same as for 'bench', except '--resume' and '--quiet'.")?;
+                                            writeln!(out, "Example: 'bench-movegen all 100'")?;
+                                        }
+                                        Command::LoadBook(_) => {
+                                            writeln!(out, "load-book [path]")?;
+                                            writeln!(out, "Load opening book from file.")?;
+                                            writeln!(out, "If path is not given the default path '{DEFAULT_BOOK_PATH}' is used.")?;
+                                            writeln!(
+                                                out,
+                                                "Example: 'load-book ./my_openings.book'"
+                                            )?;
+                                        }
+                                        Command::CheckBook => {
+                                            writeln!(out, "Compare the opening book with the solver in the current position.")?;
+                                            writeln!(out, "The position and the positions after each move are solved without the book, and compared to the scores in the book.")?;
+                                            writeln!(out, "The book doesn't store moves, its best move is the move with the best score in the book.")?;
+                                            writeln!(out, "This move only has to be one of the best moves, so it can be the mirror image of the move of the solver.")?;
+                                            writeln!(
+                                                out,
+                                                "Example: 'position 4 4', then 'check-book'"
+                                            )?;
+                                        }
+                                        Command::GenerateBook(_, _) => {
+                                            writeln!(out, "generate-book <depth> [path]")?;
+                                            writeln!(out, "Generate an opening book to the given depth from the current position.")?;
+                                            writeln!(out, "By default the book is stored in '{DEFAULT_BOOK_PATH}', but another path can be specified.")?;
+                                            writeln!(out, "If no book is loaded and the weak solver is used, a (smaller) weak book is generated.")?;
+                                            writeln!(out, "Example: 'generate-book 3 ./my_openings.book' stores the positions with at most 3 moves.")?;
+                                        }
+                                        Command::SetNumThreads(_) => {
+                                            writeln!(out, "threads <num_threads>")?;
+                                            writeln!(
+                                            out,
+                                            "Set the number of threads to be used by the solver."
+                                        )?;
+                                            writeln!(out, "Example: 'threads 4'")?;
+                                        }
+                                        Command::SetAbortInterval(_) => {
+                                            writeln!(out, "abort-interval <nodes>")?;
+                                            writeln!(out, "Set after how many nodes the search checks whether it was stopped with Ctrl-C.")?;
+                                            writeln!(out, "A smaller interval stops sooner, a larger one wastes less time checking. It must be a power of two, the default is {}.", Solver::DEFAULT_ABORT_CHECK_INTERVAL)?;
+                                            writeln!(out, "Example: 'abort-interval 64'")?;
+                                        }
+                                        Command::SetVerbosity(_) => {
+                                            writeln!(out, "verbosity <silent|summary|full>")?;
+                                            writeln!(out, "Set what the searches show besides their results: nothing, a line with the score, nodes and time, or every step of the search.")?;
+                                            writeln!(out, "The default is 'full'.")?;
+                                            writeln!(out, "Example: 'verbosity summary'")?;
+                                        }
+                                        Command::Stress(_, _, _) => {
+                                            writeln!(out, "stress <position> <runs> <threads>")?;
+                                            writeln!(out, "Solve the position (a string of moves, as in the benchmark files) several times with the given number of threads.")?;
+                                            writeln!(out, "Reports an error if the runs don't all give the same score.")?;
+                                            writeln!(out, "Example: 'stress 4455221 10 4' solves the position 10 times with 4 threads.")?;
+                                        }
+                                        Command::Window(_, _) => {
+                                            writeln!(out, "window <alpha> <beta>")?;
+                                            writeln!(out, "Search the current position once with the window [alpha, beta], without narrowing down the exact score.")?;
+                                            writeln!(out, "Useful for debugging the search.")?;
+                                            writeln!(out, "Example: 'window 0 1' tells whether the score is at most 0, or at least 1.")?;
+                                        }
+                                        Command::GenDataset(_, _, _) => {
+                                            writeln!(
+                                                out,
+                                                "gen-dataset <num_positions> <path> [seed]"
+                                            )?;
+                                            writeln!(out, "Generate random positions by random playouts, solve them and store them as training data in a CSV file.")?;
+                                            writeln!(out, "Each row has the values of 'Position::feature_planes', followed by the score and the outcome (1, 0 or -1).")?;
+                                            writeln!(out, "A seed can be given to generate the same positions again.")?;
+                                            writeln!(
+                                                out,
+                                                "Example: 'gen-dataset 1000 ./dataset.csv 42'"
+                                            )?;
+                                        }
+                                        Command::GenBalancedDataset(_, _, _, _) => {
+                                            writeln!(out, "gen-balanced-dataset <wins> <draws> <losses> <path> [seed] [--max-attempts <n>]")?;
+                                            writeln!(out, "Like 'gen-dataset', but with the given number of won, drawn and lost positions for the player to move, spread over the numbers of moves.")?;
+                                            writeln!(out, "Playouts whose outcome already has enough positions are skipped. The generation stops after 100 playouts per position, or the given maximum.")?;
+                                            writeln!(out, "Example: 'gen-balanced-dataset 300 300 300 ./dataset.csv 42'")?;
+                                        }
+                                        Command::Replay(_, _) => {
+                                            writeln!(
+                                                out,
+                                                "replay <path> [--delay <ms> | --enter] [--eval]"
+                                            )?;
+                                            writeln!(out, "Show the game in the file (a string of moves, as in the benchmark files) move by move.")?;
+                                            writeln!(out, "By default the next move is shown after a second, use '--delay' to change the pause or '--enter' to wait for Enter instead.")?;
+                                            writeln!(out, "With '--eval', every position is solved and the moves that make the score worse for the player who played them are marked as mistakes.")?;
+                                            writeln!(out, "Afterwards the final position of the game is the current position.")?;
+                                            writeln!(
+                                                out,
+                                                "Example: 'replay ./game.txt --delay 500 --eval'"
+                                            )?;
+                                        }
+                                        Command::Review(_) => {
+                                            writeln!(out, "review <path>")?;
+                                            writeln!(out, "Solve every position of the game in the file (a string of moves, as in the benchmark files), and grade each move.")?;
+                                            writeln!(out, "A move is 'best' if it has the best score, an 'inaccuracy' if it keeps the result but wins later or loses sooner, and a 'blunder' if it gives away a win or a draw.")?;
+                                            writeln!(out, "Example: 'review ./game.txt'")?;
+                                        }
+                                        Command::Quit => {
+                                            writeln!(out, "Quit the program.")?;
+                                            writeln!(out, "Example: 'quit'")?;
+                                        }
                                     }
-                                    Command::ClearTT => {
-                                        println!(
-                                            "Clear the transposition table used by the solver."
-                                        );
+                                } else {
+                                    writeln!(
+                                        out,
+                                        "Valid commands are: {:?}",
+                                        vec![
+                                            "moves/play/move",
+                                            "position",
+                                            "solve",
+                                            "solve-board",
+                                            "analyze",
+                                            "explain",
+                                            "toggle-weak",
+                                            "toggle-keep-tt",
+                                            "info",
+                                            "toggle-session-book",
+                                            "save-session-book",
+                                            "help",
+                                            "clear-tt",
+                                            "tt-probe",
+                                            "bench",
+                                            "bench-movegen",
+                                            "load-book",
+                                            "check-book",
+                                            "generate-book",
+                                            "threads",
+                                            "abort-interval",
+                                            "verbosity",
+                                            "stress",
+                                            "window",
+                                            "gen-dataset",
+                                            "gen-balanced-dataset",
+                                            "replay",
+                                            "review",
+                                            "quit",
+                                        ]
+                                    )?;
+                                    writeln!(
+                                    out,
+                                    "Type 'help <command>' for more info about a specific command"
+                                )?;
+                                    writeln!(out)?;
+                                    writeln!(
+                                        out,
+                                        "Columns are numbered from 1 to {}, from left to right.",
+                                        Position::WIDTH
+                                    )?;
+                                    writeln!(out, "Scores are given for the player to move: a positive score means they can win,")?;
+                                    writeln!(out, "a negative score means they lose against perfect play, and 0 means it's a draw.")?;
+                                    writeln!(out, "The higher the score, the sooner the win: a score of 1 means the win comes with")?;
+                                    writeln!(out, "the winner's last stone, and every extra point is one of their moves earlier.")?;
+                                    writeln!(
+                                        out,
+                                        "'solve' also prints the number of moves this takes."
+                                    )?;
+                                }
+                            }
+                            Command::ClearTT => {
+                                self.solver.reset_transposition_table();
+                                writeln!(out, "Cleared transposition table")?;
+                            }
+                            Command::TTProbe => match self.solver.probe_table(&pos) {
+                                Some(entry) => {
+                                    match entry.bound {
+                                        TableBound::Exact(score) => {
+                                            writeln!(out, "Exact score: {score}")?
+                                        }
+                                        TableBound::Lower(score) => {
+                                            writeln!(out, "Lower bound: score >= {score}")?;
+                                        }
+                                        TableBound::Upper(score) => {
+                                            writeln!(out, "Upper bound: score <= {score}")?;
+                                        }
                                     }
-                                    Command::Bench(_, _) => {
-                                        println!("bench <path> | 'all' [max_lines] ");
-                                        println!("Run the benchmarks in the given file.");
-                                        println!(
-                                            "Use 'all' instead of a path to run all benchmarks."
-                                        );
-                                        println!("A number max_lines can be specified to only solve at most that many positions per file.");
+                                    writeln!(out, "Best column: {}", entry.column + 1)?;
+                                }
+                                None => writeln!(
+                                    out,
+                                    "No entry in the transposition table for this position"
+                                )?,
+                            },
+                            Command::Bench {
+                                path,
+                                max_lines,
+                                resume,
+                                quiet,
+                                strict,
+                            } => {
+                                // Carriage returns don't overwrite the line in a file.
+                                let quiet = quiet || !to_stdout || !io::stdout().is_terminal();
+                                match Self::handle_bench(
+                                    &mut out,
+                                    path,
+                                    max_lines,
+                                    resume,
+                                    quiet,
+                                    self.weak,
+                                    self.num_threads,
+                                ) {
+                                    Ok(0) => {}
+                                    Ok(mismatches) => {
+                                        eprintln!("{mismatches} wrong score(s)");
+                                        self.failed |= strict;
                                     }
-                                    Command::LoadBook(_) => {
-                                        println!("load-book [path]");
-                                        println!("Load opening book from file.");
-                                        println!("If path is not given the default path '{DEFAULT_BOOK_PATH}' is used.");
+                                    Err(e) => {
+                                        eprintln!("Error while running bench: '{e}'");
+                                        self.failed |= strict;
                                     }
-                                    Command::GenerateBook(_, _) => {
-                                        println!("generate-book <depth> [path]");
-                                        println!("Generate an opening book to the given depth from the current position.");
-                                        println!("By default the book is stored in '{DEFAULT_BOOK_PATH}', but another path can be specified.")
+                                }
+                            }
+                            Command::BenchMovegen(path, max_lines) => {
+                                if let Err(e) =
+                                    Self::handle_bench_movegen(&mut out, path, max_lines)
+                                {
+                                    eprintln!("Error while running bench: '{e}'");
+                                }
+                            }
+                            Command::LoadBook(path) => match OpeningBook::load(&path) {
+                                Ok(book) => {
+                                    writeln!(
+                                        out,
+                                        "Loaded book in {:?} ({} entries)",
+                                        path,
+                                        book.num_entries()
+                                    )?;
+                                    self.solver.set_book(book)
+                                }
+                                Err(e) => eprintln!("Error while loading book: '{e}'"),
+                            },
+                            Command::CheckBook => self.check_book(&mut out, &pos)?,
+                            Command::GenerateBook(depth, path) => {
+                                // The entries are written as they are solved, so a crash
+                                // doesn't lose the positions solved so far.
+                                let result = self
+                                    .solver
+                                    .generate_book_streaming(&pos, depth, self.weak, &path);
+                                if self.was_interrupted(&mut out)? {
+                                    writeln!(
+                                        out,
+                                        "The book only contains the positions solved so far"
+                                    )?;
+                                }
+                                if let Err(e) = result {
+                                    eprintln!("Err while storing book: '{e}'");
+                                } else {
+                                    writeln!(
+                                        out,
+                                        "Stored book in {:?} ({} entries)",
+                                        path,
+                                        self.solver.get_book().num_entries()
+                                    )?;
+                                }
+                            }
+                            Command::SetNumThreads(n) => {
+                                writeln!(out, "Set number of threads to {n}")?;
+                                self.num_threads = n;
+                            }
+                            Command::SetAbortInterval(n) => {
+                                writeln!(out, "Check for Ctrl-C every {n} nodes")?;
+                                self.solver.set_abort_check_interval(n);
+                            }
+                            Command::SetVerbosity(verbosity) => {
+                                writeln!(out, "Set verbosity to {verbosity:?}")?;
+                                self.solver.set_verbosity(verbosity);
+                            }
+                            Command::Stress(pos, runs, threads) => {
+                                match stress_position(&mut out, &pos, runs, self.weak, threads)? {
+                                    Ok(score) => {
+                                        writeln!(
+                                            out,
+                                            "All {runs} runs returned the score {score}"
+                                        )?;
                                     }
-                                    Command::SetNumThreads(_) => {
-                                        println!("threads <num_threads>");
-                                        println!(
-                                            "Set the number of threads to be used by the solver."
+                                    Err((expected, got)) => {
+                                        eprintln!(
+                                            "Inconsistent scores: got {got} after {expected}"
                                         );
                                     }
-                                    Command::Quit => {
-                                        println!("Quit the program.");
-                                    }
                                 }
-                            } else {
-                                println!(
-                                    "Valid commands are: {:?}",
-                                    vec![
-                                        "moves/play/move",
-                                        "position",
-                                        "solve",
-                                        "analyze",
-                                        "toggle-weak",
-                                        "help",
-                                        "clear-tt",
-                                        "bench",
-                                        "load-book",
-                                        "generate-book",
-                                        "threads",
-                                        "quit",
-                                    ]
-                                );
-                                println!(
-                                    "Type 'help <command>' for more info about a specific command"
-                                );
                             }
-                        }
-                        Command::ClearTT => {
-                            self.solver.reset_transposition_table();
-                            println!("Cleared transposition table");
-                        }
-                        Command::Bench(path, max_lines) => {
-                            if let Err(e) =
-                                Self::handle_bench(path, max_lines, self.weak, self.num_threads)
-                            {
-                                eprintln!("Error while running bench: '{e}'");
+                            Command::Window(alpha, beta) => {
+                                let now = Instant::now();
+                                let (score, nodes) = self.solver.solve_window(&pos, alpha, beta);
+                                if self.was_interrupted(&mut out)? {
+                                    continue;
+                                }
+                                match score {
+                                    s if s <= alpha => writeln!(out, "Score is at most {s}")?,
+                                    s if s >= beta => writeln!(out, "Score is at least {s}")?,
+                                    s => writeln!(out, "Score is {s}")?,
+                                }
+                                writeln!(out, "Searched {nodes} nodes in {:?}", now.elapsed())?;
                             }
-                        }
-                        Command::LoadBook(path) => match OpeningBook::load(&path) {
-                            Ok(book) => {
-                                println!(
-                                    "Loaded book in {:?} ({} entries)",
-                                    path,
-                                    book.num_entries()
-                                );
-                                self.solver.set_book(book)
+                            Command::GenDataset(num_positions, path, seed) => {
+                                let seed = seed.unwrap_or_else(time_seed);
+                                if let Err(e) = generate_dataset(
+                                    &mut out,
+                                    num_positions,
+                                    &path,
+                                    seed,
+                                    self.num_threads,
+                                ) {
+                                    eprintln!("Error while generating dataset: '{e}'");
+                                }
                             }
-                            Err(e) => eprintln!("Error while loading book: '{e}'"),
-                        },
-                        Command::GenerateBook(depth, path) => {
-                            self.solver.generate_book(&pos, depth);
-                            if let Err(e) = self.solver.get_book().store(&path) {
-                                eprintln!("Err while storing book: '{e}'");
-                            } else {
-                                println!(
-                                    "Stored book in {:?} ({} entries)",
-                                    path,
-                                    self.solver.get_book().num_entries()
-                                );
+                            Command::GenBalancedDataset(quotas, path, seed, max_attempts) => {
+                                let seed = seed.unwrap_or_else(time_seed);
+                                let max_attempts = max_attempts.unwrap_or(100 * quotas.total());
+                                if let Err(e) = generate_balanced_dataset(
+                                    &mut out,
+                                    quotas,
+                                    max_attempts,
+                                    &path,
+                                    seed,
+                                    self.num_threads,
+                                ) {
+                                    eprintln!("Error while generating dataset: '{e}'");
+                                }
+                            }
+                            Command::Replay(path, options) => match load_game(&path) {
+                                Ok(moves) => {
+                                    pos = Position::new();
+                                    self.replay(&mut input, &mut out, &mut pos, &moves, &options)?;
+                                }
+                                Err(e) => eprintln!("Error while loading game: '{e}'"),
+                            },
+                            Command::Review(path) => match load_game(&path) {
+                                Ok(moves) => self.review(&mut out, &moves)?,
+                                Err(e) => eprintln!("Error while loading game: '{e}'"),
+                            },
+                            Command::Quit => {
+                                break 'lines;
                             }
                         }
-                        Command::SetNumThreads(n) => {
-                            println!("Set number of threads to {n}");
-                            self.num_threads = n;
-                        }
-                        Command::Quit => {
-                            break;
-                        }
-                    }
-                };
-                input = String::from("");
-                print!("\n> ");
-                io::stdout().flush()?;
+                    };
+                }
+                line = String::from("");
+                write!(out, "\n> ")?;
+                out.flush()?;
             }
             Ok(())
         }
 
-        fn analyze(&mut self, pos: &Position) {
+        fn analyze(&mut self, out: &mut impl Write, pos: &Position) -> io::Result<()> {
             let scores = self.solver.analyze(pos, self.weak);
-            if let Some(mut max) = scores.first() {
-                print!("\nScores for the playable columns: ");
-                for score in &scores {
-                    print!(" {score} ");
-                    if score > max {
-                        max = score;
-                    }
+            if self.was_interrupted(out)? {
+                return Ok(());
+            }
+            if let Some(&max) = scores.iter().flatten().max() {
+                write!(out, "\n{}", format_scores(&scores, self.weak))?;
+                if self.weak {
+                    write!(out, "\nThe best result is: {}", outcome_label(max))?;
+                } else {
+                    write!(out, "\nThe best score is: {max}")?;
                 }
-                print!("\nThe best score is: {max}");
-                self.explain_score(pos, *max);
+                self.explain_score(out, pos, max)?;
             } else {
-                println!("No playable columns");
+                writeln!(out, "No playable columns")?;
             }
-            println!("\n");
+            writeln!(out, "\n")
         }
 
-        fn solve(&mut self, pos: &Position) {
-            let (score, nodes) = self.solver.solve(pos, self.weak, true, self.num_threads);
-            print!("\nScore is {score}");
-            self.explain_score(pos, score);
-            println!("\nTotal number of nodes: {nodes}");
+        fn solve(
+            &mut self,
+            out: &mut impl Write,
+            pos: &Position,
+            show_progress: bool,
+        ) -> io::Result<()> {
+            let verbosity = self.solver.config().verbosity;
+            let (score, nodes) = if show_progress {
+                self.solve_with_progress(out, pos)?
+            } else if verbosity == Verbosity::Summary {
+                // Write the summary to `out`, instead of letting the solver print it.
+                let report =
+                    self.solver
+                        .solve_full(pos, self.weak, Verbosity::Silent, self.num_threads);
+                if !report.interrupted {
+                    writeln!(
+                        out,
+                        "Score: {}, nodes: {}, took: {:?}",
+                        report.score, report.nodes, report.elapsed
+                    )?;
+                }
+                (report.score, report.nodes)
+            } else {
+                self.solver
+                    .solve(pos, self.weak, verbosity, self.num_threads)
+            };
+            if self.was_interrupted(out)? {
+                return Ok(());
+            }
+            self.record_solve(pos, score);
+            write!(out, "\nScore is {score}")?;
+            self.explain_score(out, pos, score)?;
+            writeln!(out, "\nTotal number of nodes: {nodes}")
         }
 
-        fn explain_score(&mut self, pos: &Position, score: isize) {
-            match score.cmp(&0) {
-                Ordering::Greater => print!(", which means '{}' can win", pos.current_player().1),
-                Ordering::Less => print!(", which means '{}' can win", pos.current_player().0),
-                Ordering::Equal => (),
-            }
-            if !self.weak {
-                print!(" in {} move(s)", Solver::score_to_moves_to_win(pos, score),);
-            }
-            if score == 0 {
-                print!(", which means it's a draw");
+        /// Solve the position while drawing a progress bar of the number of nodes searched,
+        /// compared to `Solver::estimate_nodes`.
+        fn solve_with_progress(
+            &mut self,
+            out: &mut impl Write,
+            pos: &Position,
+        ) -> io::Result<(isize, u64)> {
+            let estimate = Solver::estimate_nodes(pos);
+            let progress = self.solver.progress();
+            let (weak, num_threads) = (self.weak, self.num_threads);
+            let solver = &mut self.solver;
+            // The search runs on another thread, so the bar is drawn on this one and the
+            // output doesn't have to be shared between threads.
+            let result = std::thread::scope(|scope| {
+                let search =
+                    scope.spawn(move || solver.solve(pos, weak, Verbosity::Silent, num_threads));
+                while !search.is_finished() {
+                    write!(out, "\r{}", progress_bar(progress.nodes(), estimate, false))?;
+                    out.flush()?;
+                    std::thread::sleep(Duration::from_millis(100));
+                }
+                io::Result::Ok(search.join().expect("the search panicked"))
+            })?;
+            if !self.interrupt.load(atomic::Ordering::SeqCst) {
+                writeln!(out, "\r{}", progress_bar(result.1, estimate, true))?;
             }
+            Ok(result)
         }
 
-        fn handle_bench(
-            path: Option<PathBuf>,
-            max_lines: Option<usize>,
+        /// Play the 0-based columns `moves` from `pos` one by one, showing the board after
+        /// each move. With `options.eval`, every position is solved and the moves after which
+        /// the score got worse for the player who played them are reported.
+        /// Returns the indices of these mistakes in `moves`.
+        ///
+        /// A last move that wins the game is shown, but `pos` stays the position before it,
+        /// since a `Position` can't contain an alignment.
+        ///
+        /// Consecutive positions are solved with the same transposition table, so each
+        /// solve profits from the previous one unless `keep_tt` is turned off.
+        fn replay(
+            &mut self,
+            input: &mut impl BufRead,
+            out: &mut impl Write,
+            pos: &mut Position,
+            moves: &[position::Column],
+            options: &ReplayOptions,
+        ) -> io::Result<Vec<usize>> {
+            let mut scores = Vec::with_capacity(moves.len() + 1);
+            let mut eval = options.eval;
+            writeln!(out, "\nStart position:")?;
+            write!(out, "{}", pos.to_ascii_art(RenderOpts::default()))?;
+            for (i, &col) in moves.iter().enumerate() {
+                if eval {
+                    match self.evaluate(out, pos)? {
+                        Some(score) => scores.push(score),
+                        None => eval = false,
+                    }
+                }
+                if i > 0 || eval {
+                    Self::replay_pause(input, out, options.pause)?;
+                }
+                if pos.is_winning_move(col) {
+                    let mut won = pos.clone();
+                    won.play_col(col);
+                    writeln!(out, "\nMove {}: column {} wins the game", i + 1, col + 1)?;
+                    write!(out, "{}", won.to_ascii_art(RenderOpts::default()))?;
+                    if eval {
+                        // The player to move after the winning move has lost.
+                        scores.push(-pos.num_stones_left(1));
+                    }
+                    eval = false;
+                    break;
+                }
+                pos.play_col(col);
+                writeln!(out, "\nMove {}: column {}", i + 1, col + 1)?;
+                write!(out, "{}", pos.to_ascii_art(RenderOpts::default()))?;
+            }
+            if eval {
+                if let Some(score) = self.evaluate(out, pos)? {
+                    scores.push(score);
+                }
+            }
+            let mistakes = find_mistakes(&scores);
+            if !mistakes.is_empty() {
+                let moves = mistakes.iter().map(|i| i + 1).collect::<Vec<_>>();
+                writeln!(out, "\nMistakes were made in the moves {moves:?}")?;
+            }
+            Ok(mistakes)
+        }
+
+        /// Grade the moves of the game with the 0-based columns `moves`, see
+        /// `Solver::review_game`, and print a line for every move and a summary.
+        fn review(&mut self, out: &mut impl Write, moves: &[position::Column]) -> io::Result<()> {
+            let reviews = self.solver.review_game(&Position::new(), moves);
+            if self.was_interrupted(out)? {
+                return Ok(());
+            }
+            // The number of inaccuracies and blunders of each player.
+            let mut counts = [[0; 2]; 2];
+            for review in &reviews {
+                let player = if review.first_player { "o" } else { "x" };
+                let tag = match review.tag {
+                    MoveTag::Best => "best",
+                    MoveTag::Inaccuracy => "inaccuracy",
+                    MoveTag::Blunder => "blunder",
+                };
+                writeln!(
+                    out,
+                    "Move {}: '{player}' played {} (best {}), score {} -> {}: {tag}",
+                    review.ply + 1,
+                    review.played + 1,
+                    review.best + 1,
+                    review.eval_before,
+                    review.eval_after
+                )?;
+                let player = usize::from(!review.first_player);
+                match review.tag {
+                    MoveTag::Best => {}
+                    MoveTag::Inaccuracy => counts[player][0] += 1,
+                    MoveTag::Blunder => counts[player][1] += 1,
+                }
+            }
+            if reviews.len() < moves.len() {
+                writeln!(
+                    out,
+                    "Only reviewed the first {} move(s): the game was over or a column was full",
+                    reviews.len()
+                )?;
+            }
+            for (player, [inaccuracies, blunders]) in ["o", "x"].into_iter().zip(counts) {
+                writeln!(
+                    out,
+                    "'{player}': {inaccuracies} inaccuracies, {blunders} blunders"
+                )?;
+            }
+            Ok(())
+        }
+
+        /// Solve the position for `replay()` and print the score.
+        /// Returns `None` if the search was interrupted.
+        fn evaluate(&mut self, out: &mut impl Write, pos: &Position) -> io::Result<Option<isize>> {
+            if pos.nb_moves() == Position::WIDTH * Position::HEIGHT {
+                // The board is full, so there is nothing to solve.
+                writeln!(out, "Score: 0")?;
+                return Ok(Some(0));
+            }
+            let (score, _) = self
+                .solver
+                .solve(pos, self.weak, Verbosity::Silent, self.num_threads);
+            if self.was_interrupted(out)? {
+                return Ok(None);
+            }
+            self.record_solve(pos, score);
+            writeln!(out, "Score: {score}")?;
+            Ok(Some(score))
+        }
+
+        fn replay_pause(
+            input: &mut impl BufRead,
+            out: &mut impl Write,
+            pause: Option<Duration>,
+        ) -> io::Result<()> {
+            match pause {
+                Some(pause) => std::thread::sleep(pause),
+                None => {
+                    write!(out, "Press Enter for the next move")?;
+                    out.flush()?;
+                    input.read_line(&mut String::new())?;
+                }
+            }
+            Ok(())
+        }
+
+        /// Compare the scores and the best move of the opening book with the solver,
+        /// see `check_book()`.
+        fn check_book(&mut self, out: &mut impl Write, pos: &Position) -> io::Result<()> {
+            let Some(check) = check_book(&mut self.solver, pos, self.weak) else {
+                if !self.was_interrupted(out)? {
+                    eprintln!("No opening book loaded, use 'load-book' or 'generate-book' first");
+                }
+                return Ok(());
+            };
+            if self.was_interrupted(out)? {
+                return Ok(());
+            }
+            match check.book_score {
+                Some(score) => writeln!(out, "\nBook score: {score}")?,
+                None => writeln!(out, "\nThe position is not in the book")?,
+            }
+            writeln!(out, "Solver score: {}", check.solved_score)?;
+            match check.book_move {
+                Some(col) => writeln!(out, "Book move: {}", col + 1)?,
+                None => writeln!(out, "The book doesn't have the scores of all the moves")?,
+            }
+            if let Some(col) = check.solver_move {
+                writeln!(out, "Solver move: {}", col + 1)?;
+            }
+            if check.agrees() {
+                writeln!(out, "The book agrees with the solver")?;
+            } else {
+                if !check.score_agrees {
+                    writeln!(out, "Mismatch: the book score is wrong")?;
+                }
+                if !check.move_agrees {
+                    writeln!(out, "Mismatch: the book move is not one of the best moves")?;
+                }
+            }
+            Ok(())
+        }
+
+        /// Store the result of a completed solve in the session book, if we are recording.
+        fn record_solve(&mut self, pos: &Position, score: isize) {
+            let Some(book) = &mut self.session_book else {
+                return;
+            };
+            if pos.nb_moves() > Position::KEY3_MAX_MOVES
+                || (self.weak && book.kind() == BookKind::Strong)
+            {
+                return;
+            }
+            book.put(pos, score);
+        }
+
+        fn explain_score(
+            &mut self,
+            out: &mut impl Write,
+            pos: &Position,
+            score: isize,
+        ) -> io::Result<()> {
+            match score.cmp(&0) {
+                Ordering::Greater => {
+                    write!(out, ", which means '{}' can win", pos.current_player().1)?
+                }
+                Ordering::Less => {
+                    write!(out, ", which means '{}' can win", pos.current_player().0)?
+                }
+                Ordering::Equal => (),
+            }
+            if !self.weak {
+                write!(
+                    out,
+                    " in {} move(s)",
+                    Solver::score_to_moves_to_win(pos, score),
+                )?;
+            }
+            if score == 0 {
+                write!(out, ", which means it's a draw")?;
+            }
+            Ok(())
+        }
+
+        fn handle_bench(
+            out: &mut impl Write,
+            path: Option<PathBuf>,
+            max_lines: Option<usize>,
+            resume: Option<PathBuf>,
+            quiet: bool,
             weak: bool,
             num_threads: u8,
-        ) -> std::io::Result<()> {
+        ) -> Result<usize, Connect4Error> {
+            let mut checkpoint = match resume {
+                Some(state) => Some(BenchCheckpoint::open(state)?),
+                None => None,
+            };
+            let paths = if let Some(path) = path {
+                vec![path]
+            } else {
+                let mut paths = fs::read_dir("./benchmark_files")?
+                    .map(|dir| dir.map(|dir| dir.path()))
+                    .collect::<Result<Vec<_>, _>>()?;
+                // Run the files in the same order when resuming.
+                if checkpoint.is_some() {
+                    paths.sort();
+                }
+                paths
+            };
+            let mut mismatches = 0;
+            for path in paths {
+                let summary = match checkpoint.as_mut() {
+                    Some(checkpoint) => resume_bench_file(
+                        out,
+                        path,
+                        max_lines,
+                        weak,
+                        num_threads,
+                        quiet,
+                        checkpoint,
+                    )?,
+                    None => bench_file(out, path, max_lines, weak, num_threads, quiet)?,
+                };
+                mismatches += summary.mismatches;
+            }
+            Ok(mismatches)
+        }
+
+        fn handle_bench_movegen(
+            out: &mut impl Write,
+            path: Option<PathBuf>,
+            max_lines: Option<usize>,
+        ) -> Result<(), Connect4Error> {
             if let Some(path) = path {
-                bench_file(path, max_lines, weak, num_threads)?;
+                bench_movegen_file(out, path, max_lines)?;
             } else {
                 let paths = fs::read_dir("./benchmark_files")?;
                 for dir in paths {
-                    bench_file(dir?.path(), max_lines, weak, num_threads)?;
+                    bench_movegen_file(out, dir?.path(), max_lines)?;
                 }
             }
             Ok(())
@@ -465,51 +1706,743 @@ pub mod game_solver {
         sum / (length as f64)
     }
 
+    /// The result of comparing an opening book with the solver, see `check_book()`.
+    struct BookCheck {
+        /// The score of the position in the book.
+        book_score: Option<isize>,
+        /// The score of the position found by the solver.
+        solved_score: isize,
+        /// The column with the best score in the book.
+        book_move: Option<position::Column>,
+        /// The best column according to the solver.
+        solver_move: Option<position::Column>,
+        /// Whether the book score, if any, is the solved score.
+        score_agrees: bool,
+        /// Whether the book move, if any, has the best solved score.
+        move_agrees: bool,
+    }
+
+    impl BookCheck {
+        fn agrees(&self) -> bool {
+            self.score_agrees && self.move_agrees
+        }
+    }
+
+    /// Solve the position and the positions after each move without the opening book of
+    /// the `solver`, and compare the results with the book. For a weak book, or if `weak`
+    /// is true, only the signs of the scores are compared.
+    ///
+    /// The book doesn't store moves, and shares each entry with the mirrored position.
+    /// Its best move is derived from the book scores of the positions after each move,
+    /// so in a symmetric position it can be the mirror image of the move of the solver.
+    /// That is not a mismatch: a book move agrees if it has the best solved score.
+    ///
+    /// Returns `None` if the solver has no book, or if the search was interrupted.
+    fn check_book(solver: &mut Solver, pos: &Position, weak: bool) -> Option<BookCheck> {
+        let book = solver.take_book()?;
+        let weak = weak || book.kind() == BookKind::Weak;
+        let book_score = book.get(pos);
+        solver.set_book(book);
+        let book_move = solver
+            .book_scores(pos, weak)
+            .and_then(|scores| TieBreak::default().select(&scores));
+
+        let book = solver.take_book()?;
+        let scores = solver.analyze(pos, weak);
+        solver.set_book(book);
+        if solver.interrupt_handle().load(atomic::Ordering::SeqCst) {
+            return None;
+        }
+        let solved_score = *scores.iter().flatten().max()?;
+        Some(BookCheck {
+            book_score,
+            solved_score,
+            book_move,
+            solver_move: TieBreak::default().select(&scores),
+            score_agrees: book_score.is_none_or(|score| same_score(score, solved_score, weak)),
+            move_agrees: book_move.is_none_or(|col| is_best_move(&scores, col, weak)),
+        })
+    }
+
+    /// Whether the scores are the same, or only have the same sign if `weak` is true.
+    fn same_score(a: isize, b: isize, weak: bool) -> bool {
+        if weak {
+            a.signum() == b.signum()
+        } else {
+            a == b
+        }
+    }
+
+    /// Whether `col` has the best of the solved `scores` of `Solver::analyze`.
+    fn is_best_move(scores: &[Option<isize>], col: position::Column, weak: bool) -> bool {
+        let Some(&best) = scores.iter().flatten().max() else {
+            return false;
+        };
+        scores[col as usize].is_some_and(|score| same_score(score, best, weak))
+    }
+
+    /// Draw a bar showing `nodes` out of the `estimate`. The estimate can be too low,
+    /// so the bar stays at 99% until the solve is `done`.
+    fn progress_bar(nodes: u64, estimate: u64, done: bool) -> String {
+        const WIDTH: u64 = 40;
+        let percentage = if done {
+            100
+        } else {
+            (nodes.saturating_mul(100) / estimate.max(1)).min(99)
+        };
+        let filled = (percentage * WIDTH / 100) as usize;
+        format!(
+            "[{}{}] {percentage:>3}% ({nodes} nodes)",
+            "#".repeat(filled),
+            " ".repeat(WIDTH as usize - filled)
+        )
+    }
+
+    /// Read a game stored as a string of moves, as in the benchmark files.
+    /// Returns the 0-based columns that were played. The last move may win the game.
+    fn load_game(path: &std::path::Path) -> Result<Vec<position::Column>, Connect4Error> {
+        let game = fs::read_to_string(path)?;
+        let game = game.split_whitespace().next().unwrap_or_default();
+        Ok(parse_game_moves(game)?)
+    }
+
+    /// Parse the moves of a game, a string of 1-based columns like in
+    /// `Position::from_string()`, and return the 0-based columns. Unlike a position, a game
+    /// can end with a winning move, but no move can follow it.
+    fn parse_game_moves(
+        moves: &str,
+    ) -> Result<Vec<position::Column>, position::PositionParseError> {
+        let mut chars = moves.chars();
+        let Some(last) = chars.next_back() else {
+            return Ok(Vec::new());
+        };
+        // Without the last move the game is a position, after it the game can be over.
+        let game = GameRecord::from_string(chars.as_str())?;
+        let col = last
+            .to_digit(10)
+            .ok_or(position::PositionParseError::InvalidChar(last))?
+            as position::Column;
+        if col == 0 || col > Position::WIDTH {
+            return Err(position::PositionParseError::InvalidColumn(col));
+        }
+        if !game.position().can_play(col - 1) {
+            return Err(position::PositionParseError::Unplayable(col));
+        }
+        let mut columns = game.move_history().to_vec();
+        columns.push(col - 1);
+        Ok(columns)
+    }
+
+    /// Read the games of a game database, with one game per line, for example to analyze
+    /// each of them. Each game has the result and the moves, as a string of 1-based
+    /// columns like in the benchmark files, in either order. They can be separated by
+    /// `|`, `,`, `;`, a tab or spaces, and the moves can contain spaces. The result is
+    /// for the player who started: `1-0`, `1` or `win`, `0-1`, `-1` or `loss`,
+    /// `1/2-1/2`, `0` or `draw`, and `*` or `?` if it is unknown. A line with only
+    /// moves has no result either.
+    ///
+    /// Returns the 0-based columns of each game, and its result if it is known.
+    /// Empty lines and lines starting with `#` are skipped, and so are lines that can't
+    /// be parsed, with a warning, so a few bad lines don't stop a large import.
+    pub fn load_games(
+        path: &std::path::Path,
+    ) -> Result<impl Iterator<Item = (Vec<position::Column>, Option<Outcome>)>, Connect4Error> {
+        let file = BufReader::new(File::open(path)?);
+        Ok(file.lines().enumerate().filter_map(|(i, line)| {
+            let line = match line {
+                Ok(line) => line,
+                Err(e) => {
+                    warning!("Skipping line {}: {e}", i + 1);
+                    return None;
+                }
+            };
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                return None;
+            }
+            match parse_game_line(line) {
+                Ok(game) => Some(game),
+                Err(e) => {
+                    warning!("Skipping line {}: {e}", i + 1);
+                    None
+                }
+            }
+        }))
+    }
+
+    /// Why a line of a game database can't be read, see `load_games()`.
+    #[derive(Debug)]
+    enum GameLineError {
+        /// Neither of the fields is a result.
+        Result,
+        /// The moves can't be played.
+        Moves(position::PositionParseError),
+    }
+
+    impl std::fmt::Display for GameLineError {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            match self {
+                Self::Result => write!(f, "Expected a result and a string of moves"),
+                Self::Moves(e) => write!(f, "Invalid moves: {e}"),
+            }
+        }
+    }
+
+    /// Parse a game of a game database, see `load_games()`.
+    fn parse_game_line(
+        line: &str,
+    ) -> Result<(Vec<position::Column>, Option<Outcome>), GameLineError> {
+        let (first, second) = match line.split_once(['|', ',', ';', '\t']) {
+            Some(fields) => fields,
+            None => line.split_once(' ').unwrap_or((line, "")),
+        };
+        let (moves, result) = match (parse_game_result(first), parse_game_result(second)) {
+            (Some(result), _) if !second.trim().is_empty() => (second, result),
+            (_, Some(result)) => (first, result),
+            _ => return Err(GameLineError::Result),
+        };
+        let moves = moves.split_whitespace().collect::<String>();
+        let moves = parse_game_moves(&moves).map_err(GameLineError::Moves)?;
+        Ok((moves, result))
+    }
+
+    /// Parse the result of a game for the player who started, see `load_games()`.
+    /// Returns `Some(None)` for an unknown result, and `None` if it is not a result.
+    fn parse_game_result(result: &str) -> Option<Option<Outcome>> {
+        match result.trim().to_lowercase().as_str() {
+            "1-0" | "1" | "+1" | "win" | "w" => Some(Some(Outcome::Win)),
+            "0-1" | "-1" | "loss" | "l" => Some(Some(Outcome::Loss)),
+            "1/2-1/2" | "½-½" | "0" | "draw" | "d" | "=" => Some(Some(Outcome::Draw)),
+            "*" | "?" | "" => Some(None),
+            _ => None,
+        }
+    }
+
+    /// Given the scores of the consecutive positions of a game, find the moves
+    /// after which the score got worse for the player who played them.
+    /// The score of the position after move `i` is `scores[i + 1]`.
+    fn find_mistakes(scores: &[isize]) -> Vec<usize> {
+        scores
+            .windows(2)
+            .enumerate()
+            .filter(|(_, scores)| -scores[1] < scores[0])
+            .map(|(i, _)| i)
+            .collect()
+    }
+
+    /// Format the scores of `Solver::analyze` as a table with a column for each
+    /// column of the board. Unplayable columns are shown as "-".
+    ///
+    /// The scores of a weak solve only say who wins, a score of 1 isn't a win with the
+    /// last stone, so with `weak` they are shown as "win", "draw" or "loss".
+    fn format_scores(scores: &[Option<isize>], weak: bool) -> String {
+        let width = if weak { 6 } else { 4 };
+        let mut columns = String::from("column:");
+        let mut row = String::from(if weak { "result:" } else { "score: " });
+        for (col, score) in scores.iter().enumerate() {
+            columns.push_str(&format!("{:>width$}", col + 1));
+            match score {
+                Some(score) if weak => row.push_str(&format!("{:>width$}", outcome_label(*score))),
+                Some(score) => row.push_str(&format!("{score:>width$}")),
+                None => row.push_str(&format!("{:>width$}", "-")),
+            }
+        }
+        format!("{columns}\n{row}")
+    }
+
+    /// "win", "draw" or "loss", for the player to move.
+    fn outcome_label(score: isize) -> &'static str {
+        match Outcome::from_score(score) {
+            Outcome::Win => "win",
+            Outcome::Draw => "draw",
+            Outcome::Loss => "loss",
+        }
+    }
+
+    /// Solve the position `runs` times, each time starting from an empty
+    /// transposition table, using `num_threads` threads. Because the threads
+    /// share the table, the number of nodes differs between runs, but the
+    /// score never should.
+    ///
+    /// Returns the score if all runs agree, otherwise returns the first
+    /// score and the first score that was different from it. Each run is
+    /// reported to `out`, and an error while writing to it stops the runs.
+    ///
+    /// Panics if `runs` is 0, since no score would be checked.
+    pub fn stress_position(
+        out: &mut impl Write,
+        pos: &Position,
+        runs: usize,
+        weak: bool,
+        num_threads: u8,
+    ) -> io::Result<Result<isize, (isize, isize)>> {
+        assert!(runs > 0, "Expected at least one run");
+        let mut solver = Solver::new(None);
+        let mut expected = None;
+        for run in 0..runs {
+            solver.reset_transposition_table();
+            let now = Instant::now();
+            let (score, nodes) = solver.solve(pos, weak, Verbosity::Silent, num_threads);
+            writeln!(
+                out,
+                "Run {}: score {score}, nodes {nodes}, took {:?}",
+                run + 1,
+                now.elapsed()
+            )?;
+            match expected {
+                None => expected = Some(score),
+                Some(expected) if expected != score => return Ok(Err((expected, score))),
+                Some(_) => (),
+            }
+        }
+        Ok(Ok(expected.expect("there is at least one run")))
+    }
+
     /// Calls solve on the positions in the file. Returns `Err` if
     /// the file couldn't be read. If `max_lines` is not `None`, it
     /// will only run the lines upto `max_lines`.
     ///
     /// The recorded times are averaged, as well as the number of nodes.
-    /// These are then printed to `std_out`. If the solver returns the wrong
+    /// These are then written to `out`. If the solver returns the wrong
     /// score, an error message is printed, but the benchmark continues.
+    /// The wrong scores are counted in the returned `BenchSummary`.
+    /// Unless `quiet` is set, the line being solved is shown as well.
     pub fn bench_file(
+        out: &mut impl Write,
         path: PathBuf,
         max_lines: Option<usize>,
         weak: bool,
         num_threads: u8,
-    ) -> std::io::Result<()> {
-        println!("\nStarting benchmark: {}", path.display());
-        let file = File::open(path)?;
-        let file = BufReader::new(file);
+        quiet: bool,
+    ) -> Result<BenchSummary, Connect4Error> {
+        run_bench_file(out, path, max_lines, weak, num_threads, quiet, None)
+    }
+
+    /// Like `bench_file()`, but skips the lines that are already recorded in the
+    /// `checkpoint`, and records every line after it is solved. The averages also
+    /// include the recorded lines, so they cover the whole file.
+    ///
+    /// The checkpoint doesn't store the settings, so the same `max_lines`, `weak`
+    /// and number of threads should be used when resuming. It doesn't store the
+    /// scores either, so the summary only counts the wrong scores of the new lines.
+    pub fn resume_bench_file(
+        out: &mut impl Write,
+        path: PathBuf,
+        max_lines: Option<usize>,
+        weak: bool,
+        num_threads: u8,
+        quiet: bool,
+        checkpoint: &mut BenchCheckpoint,
+    ) -> Result<BenchSummary, Connect4Error> {
+        run_bench_file(
+            out,
+            path,
+            max_lines,
+            weak,
+            num_threads,
+            quiet,
+            Some(checkpoint),
+        )
+    }
+
+    fn run_bench_file(
+        out: &mut impl Write,
+        path: PathBuf,
+        max_lines: Option<usize>,
+        weak: bool,
+        num_threads: u8,
+        quiet: bool,
+        mut checkpoint: Option<&mut BenchCheckpoint>,
+    ) -> Result<BenchSummary, Connect4Error> {
+        writeln!(out, "\nStarting benchmark: {}", path.display())?;
         let max_lines = max_lines.unwrap_or_default();
         let mut solver = Solver::new(None);
         let mut times = Vec::with_capacity(max_lines);
         let mut nodes = Vec::with_capacity(max_lines);
+        let mut skipped = 0;
+        let mut summary = BenchSummary::default();
+        for entry in load_bench_file(path.clone(), max_lines)? {
+            summary.lines += 1;
+            if let Some(result) = checkpoint.as_ref().and_then(|c| c.get(&path, entry.line)) {
+                times.push(result.time);
+                nodes.push(result.nodes as f64);
+                skipped += 1;
+                continue;
+            }
+            if !quiet {
+                write!(out, "\rProcessing line: {}...", entry.line + 1)?;
+                out.flush()?;
+            }
+            let now = Instant::now();
+            let (score, num_nodes) = solver.solve(&entry.pos, weak, Verbosity::Silent, num_threads);
+            let score = conv_score(score, weak);
+            let time = now.elapsed().as_secs_f64();
+            times.push(time);
+            nodes.push(num_nodes as f64);
+            if let Some(checkpoint) = checkpoint.as_mut() {
+                checkpoint.record(&path, entry.line, time, num_nodes)?;
+            }
+            if let Some(expected_result) = entry.expected_score {
+                if score != conv_score(expected_result, weak) {
+                    summary.mismatches += 1;
+                    eprintln!(
+                        "Expected score: {}, got: {} in pos {} on line {}",
+                        conv_score(expected_result, weak),
+                        score,
+                        entry.position_str,
+                        entry.line
+                    );
+                }
+            }
+        }
+        if !quiet {
+            writeln!(out, "\n")?;
+        }
+        writeln!(out, "Finished benchmark")?;
+        if skipped > 0 {
+            writeln!(out, "Skipped {skipped} line(s) that were already solved")?;
+        }
+        writeln!(out, "Average time: {:?}", average(times))?;
+        writeln!(out, "Average number of nodes: {:?}", average(nodes))?;
+        Ok(summary)
+    }
+
+    /// The result of checking the scores of a benchmark file, see `bench_file()`.
+    #[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+    pub struct BenchSummary {
+        /// The number of lines of the file that were benchmarked, including the
+        /// lines skipped by `resume_bench_file()`.
+        pub lines: usize,
+        /// The number of solved lines with another score than the expected one.
+        pub mismatches: usize,
+    }
+
+    /// Play random moves from the starting position to get a position with
+    /// between `DATASET_MIN_MOVES` and `Position::KEY3_MAX_MOVES` moves.
+    /// Moves that win directly are never played. Returns `None` if the
+    /// playout got stuck before reaching the chosen length.
+    fn random_position(seed: &mut u64) -> Option<Position> {
+        let mut next_random = || {
+            *seed = seed.wrapping_add(1);
+            splitmix64(*seed)
+        };
+        let range = u64::from(Position::KEY3_MAX_MOVES - DATASET_MIN_MOVES + 1);
+        let num_moves = DATASET_MIN_MOVES + (next_random() % range) as u8;
+        let mut pos = Position::new();
+        while pos.nb_moves() < num_moves {
+            let moves = (0..Position::WIDTH)
+                .filter(|&col| pos.can_play(col) && !pos.is_winning_move(col))
+                .collect::<Vec<_>>();
+            if moves.is_empty() {
+                return None;
+            }
+            pos.play_col(moves[(next_random() % moves.len() as u64) as usize]);
+        }
+        Some(pos)
+    }
+
+    /// A seed for the random generators from the current time.
+    fn time_seed() -> u64 {
+        std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map_or(0, |d| d.as_nanos() as u64)
+    }
+
+    /// Positions with fewer moves take too long to solve.
+    const DATASET_MIN_MOVES: u8 = 16;
+
+    /// Generate `num_positions` different random positions, solve them strongly and store
+    /// them in a CSV file at `path`. Each row contains the values of `Position::feature_planes`,
+    /// followed by the score and the outcome (1, 0 or -1) for the player to move.
+    ///
+    /// Positions are generated by random playouts, see `random_position()`, and mirrored
+    /// positions are only stored once. The same `seed` gives the same dataset. The
+    /// progress is written to `out`.
+    pub fn generate_dataset(
+        out: &mut impl Write,
+        num_positions: usize,
+        path: &std::path::Path,
+        mut seed: u64,
+        num_threads: u8,
+    ) -> Result<(), Connect4Error> {
+        let mut file = create_dataset_file(path)?;
+        let mut solver = Solver::new(None);
+        let mut seen = std::collections::HashSet::new();
+        let now = Instant::now();
+        while seen.len() < num_positions {
+            let Some(pos) = random_position(&mut seed) else {
+                continue;
+            };
+            if !seen.insert(pos.key3()) {
+                continue;
+            }
+            let (score, _) = solver.solve(&pos, false, Verbosity::Silent, num_threads);
+            write_dataset_row(&mut file, &pos, score)?;
+            write!(out, "\rSolved positions: {}...", seen.len())?;
+            out.flush()?;
+        }
+        file.flush()?;
+        let elapsed = now.elapsed();
+        writeln!(
+            out,
+            "\nStored {num_positions} positions in {} in {elapsed:?} ({:.1} positions/s)",
+            path.display(),
+            num_positions as f64 / elapsed.as_secs_f64()
+        )?;
+        Ok(())
+    }
+
+    /// A number of positions for each outcome, see `generate_balanced_dataset()`.
+    #[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+    pub struct OutcomeCounts {
+        pub wins: usize,
+        pub draws: usize,
+        pub losses: usize,
+    }
+
+    impl OutcomeCounts {
+        /// The number of positions with the given outcome.
+        #[must_use]
+        pub fn get(&self, outcome: Outcome) -> usize {
+            match outcome {
+                Outcome::Win => self.wins,
+                Outcome::Draw => self.draws,
+                Outcome::Loss => self.losses,
+            }
+        }
+
+        fn get_mut(&mut self, outcome: Outcome) -> &mut usize {
+            match outcome {
+                Outcome::Win => &mut self.wins,
+                Outcome::Draw => &mut self.draws,
+                Outcome::Loss => &mut self.losses,
+            }
+        }
+
+        /// The number of positions of all outcomes together.
+        #[must_use]
+        pub fn total(&self) -> usize {
+            self.wins + self.draws + self.losses
+        }
+    }
+
+    /// Like `generate_dataset()`, but store `quotas.wins` won, `quotas.draws` drawn and
+    /// `quotas.losses` lost positions (for the player to move), spread evenly over the
+    /// possible numbers of moves.
+    ///
+    /// Random playouts mostly give decided positions, so a position is only kept if its
+    /// outcome still needs positions with its number of moves. The outcome is found with a
+    /// weak solve, and only the positions that are kept are solved strongly. The generation
+    /// stops when all quotas are met, or after `max_attempts` playouts. Returns the number
+    /// of stored positions of each outcome, which is less than `quotas` if it was stopped.
+    pub fn generate_balanced_dataset(
+        out: &mut impl Write,
+        quotas: OutcomeCounts,
+        max_attempts: usize,
+        path: &std::path::Path,
+        mut seed: u64,
+        num_threads: u8,
+    ) -> Result<OutcomeCounts, Connect4Error> {
+        let mut file = create_dataset_file(path)?;
+        let num_lengths = usize::from(Position::KEY3_MAX_MOVES - DATASET_MIN_MOVES + 1);
+        // The number of stored positions for every number of moves.
+        let mut per_length = vec![OutcomeCounts::default(); num_lengths];
+        let mut counts = OutcomeCounts::default();
+        let mut solver = Solver::new(None);
+        let mut seen = std::collections::HashSet::new();
+        let now = Instant::now();
+        let mut attempts = 0;
+        while counts.total() < quotas.total() && attempts < max_attempts {
+            attempts += 1;
+            let Some(pos) = random_position(&mut seed) else {
+                continue;
+            };
+            let length = usize::from(pos.nb_moves() - DATASET_MIN_MOVES);
+            let is_open = |outcome| {
+                counts.get(outcome) < quotas.get(outcome)
+                    && per_length[length].get(outcome) < quotas.get(outcome).div_ceil(num_lengths)
+            };
+            if ![Outcome::Win, Outcome::Draw, Outcome::Loss]
+                .into_iter()
+                .any(is_open)
+            {
+                continue;
+            }
+            if !seen.insert(pos.key3()) {
+                continue;
+            }
+            let (weak_score, _) = solver.solve(&pos, true, Verbosity::Silent, num_threads);
+            let outcome = Outcome::from_score(weak_score);
+            if !is_open(outcome) {
+                continue;
+            }
+            let (score, _) = solver.solve(&pos, false, Verbosity::Silent, num_threads);
+            write_dataset_row(&mut file, &pos, score)?;
+            *counts.get_mut(outcome) += 1;
+            *per_length[length].get_mut(outcome) += 1;
+            write!(
+                out,
+                "\rSolved positions: {} wins, {} draws, {} losses...",
+                counts.wins, counts.draws, counts.losses
+            )?;
+            out.flush()?;
+        }
+        file.flush()?;
+        let elapsed = now.elapsed();
+        writeln!(
+            out,
+            "\nStored {} positions in {} in {elapsed:?} after {attempts} playouts",
+            counts.total(),
+            path.display(),
+        )?;
+        for (name, outcome) in [
+            ("wins", Outcome::Win),
+            ("draws", Outcome::Draw),
+            ("losses", Outcome::Loss),
+        ] {
+            writeln!(
+                out,
+                "{name}: {} of {} ({:.1}%)",
+                counts.get(outcome),
+                quotas.get(outcome),
+                100.0 * counts.get(outcome) as f64 / counts.total().max(1) as f64
+            )?;
+        }
+        Ok(counts)
+    }
+
+    /// Create a CSV file for a dataset at `path` and write the header.
+    fn create_dataset_file(path: &std::path::Path) -> Result<io::BufWriter<File>, Connect4Error> {
+        let mut file = io::BufWriter::new(File::create(path)?);
+        let header = (0..Position::FEATURE_LEN)
+            .map(|i| format!("f{i}"))
+            .chain(["score".to_string(), "outcome".to_string()])
+            .collect::<Vec<_>>();
+        writeln!(file, "{}", header.join(","))?;
+        Ok(file)
+    }
+
+    /// Write a row of a dataset, see `generate_dataset()`.
+    fn write_dataset_row(file: &mut impl Write, pos: &Position, score: isize) -> io::Result<()> {
+        let outcome = match Outcome::from_score(score) {
+            Outcome::Win => 1,
+            Outcome::Draw => 0,
+            Outcome::Loss => -1,
+        };
+        let features = pos
+            .feature_planes()
+            .iter()
+            .map(|f| f.to_string())
+            .collect::<Vec<_>>();
+        writeln!(file, "{},{score},{outcome}", features.join(","))
+    }
+
+    /// The result of solving a line of a benchmark file, see `BenchCheckpoint`.
+    #[derive(Clone, Copy, Debug, PartialEq)]
+    struct BenchResult {
+        /// The time it took to solve the position, in seconds.
+        time: f64,
+        nodes: u64,
+    }
+
+    /// The state of a benchmark run that can be resumed, see `resume_bench_file()`.
+    ///
+    /// It is stored in a file with a line for every solved position, containing the
+    /// path of the benchmark file, the index of the line, the time in seconds and the
+    /// number of nodes, separated by tabs. Lines are appended as soon as a position is
+    /// solved, so the file is up to date when the run is interrupted.
+    pub struct BenchCheckpoint {
+        file: File,
+        completed: std::collections::HashMap<(PathBuf, usize), BenchResult>,
+    }
+
+    impl BenchCheckpoint {
+        /// Open the state file at `path`, or create it if it doesn't exist yet.
+        /// Lines that can't be parsed are reported and ignored.
+        pub fn open(path: PathBuf) -> Result<Self, Connect4Error> {
+            let file = fs::OpenOptions::new()
+                .read(true)
+                .append(true)
+                .create(true)
+                .open(path)?;
+            let mut completed = std::collections::HashMap::new();
+            for (i, line) in BufReader::new(&file).lines().enumerate() {
+                let line = line?;
+                match Self::parse_line(&line) {
+                    Some((key, result)) => {
+                        completed.insert(key, result);
+                    }
+                    None => eprintln!("Couldn't parse line {i} of the state file: {line}"),
+                }
+            }
+            Ok(Self { file, completed })
+        }
+
+        fn parse_line(line: &str) -> Option<((PathBuf, usize), BenchResult)> {
+            // The path is split off last, in case it contains a tab.
+            let mut parts = line.rsplitn(4, '\t');
+            let nodes = parts.next()?.parse().ok()?;
+            let time = parts.next()?.parse().ok()?;
+            let line = parts.next()?.parse().ok()?;
+            let path = PathBuf::from(parts.next()?);
+            Some(((path, line), BenchResult { time, nodes }))
+        }
+
+        /// The number of solved lines, in all the benchmark files.
+        #[must_use]
+        pub fn num_completed(&self) -> usize {
+            self.completed.len()
+        }
+
+        fn get(&self, path: &std::path::Path, line: usize) -> Option<BenchResult> {
+            self.completed.get(&(path.to_path_buf(), line)).copied()
+        }
+
+        fn record(
+            &mut self,
+            path: &std::path::Path,
+            line: usize,
+            time: f64,
+            nodes: u64,
+        ) -> Result<(), Connect4Error> {
+            writeln!(self.file, "{}\t{line}\t{time}\t{nodes}", path.display())?;
+            self.file.flush()?;
+            self.completed
+                .insert((path.to_path_buf(), line), BenchResult { time, nodes });
+            Ok(())
+        }
+    }
+
+    /// A position from a benchmark file.
+    struct BenchEntry {
+        /// The index of the line in the file.
+        line: usize,
+        position_str: String,
+        pos: Position,
+        /// The score given in the file, if any.
+        expected_score: Option<isize>,
+    }
+
+    /// Read the positions of a benchmark file, where each line contains a position
+    /// (in the notation of `Position::parse_pons`) and optionally its expected score.
+    /// Lines that can't be parsed are reported and skipped. If `max_lines` is not 0, at most that many
+    /// lines are read.
+    fn load_bench_file(path: PathBuf, max_lines: usize) -> Result<Vec<BenchEntry>, Connect4Error> {
+        let file = File::open(path)?;
+        let file = BufReader::new(file);
+        let mut entries = Vec::with_capacity(max_lines);
         for (i, line) in file.lines().enumerate() {
             let line = line?;
             let mut parts = line.trim().split(' ');
             if let Some(position_str) = parts.next() {
-                if let Some(pos) = Position::from_string(position_str) {
-                    print!("\rProcessing line: {}...", i + 1);
-                    io::stdout().flush().unwrap();
-                    let now = Instant::now();
-                    let (score, num_nodes) = solver.solve(&pos, weak, false, num_threads);
-                    let score = conv_score(score, weak);
-                    times.push(now.elapsed().as_secs_f64());
-                    nodes.push(num_nodes as f64);
-                    if let Some(expected_result) = parts.next() {
-                        if let Ok(expected_result) = expected_result.parse::<isize>() {
-                            if score != conv_score(expected_result, weak) {
-                                eprintln!(
-                                    "Expected score: {}, got: {} in pos {} on line {}",
-                                    conv_score(expected_result, weak),
-                                    score,
-                                    position_str,
-                                    i
-                                );
-                            }
-                        }
-                    }
+                match Position::parse_pons(position_str) {
+                    Ok(pos) => entries.push(BenchEntry {
+                        line: i,
+                        position_str: position_str.to_string(),
+                        pos,
+                        expected_score: parts.next().and_then(|s| s.parse::<isize>().ok()),
+                    }),
+                    Err(e) => eprintln!("Couldn't parse line {i}: {e}"),
                 }
             } else {
                 eprintln!("Couldn't parse line {i}: {line}");
@@ -518,9 +2451,555 @@ pub mod game_solver {
                 break;
             }
         }
-        println!("\n\nFinished benchmark");
-        println!("Average time: {:?}", average(times));
-        println!("Average number of nodes: {:?}", average(nodes));
+        Ok(entries)
+    }
+
+    /// Time only the move generation and move scoring done in each node of the search,
+    /// on the positions in the file. This measures the performance of `Position`
+    /// independently of the solver. See `bench_file()` for the arguments.
+    pub fn bench_movegen_file(
+        out: &mut impl Write,
+        path: PathBuf,
+        max_lines: Option<usize>,
+    ) -> Result<(), Connect4Error> {
+        writeln!(
+            out,
+            "\nStarting move generation benchmark: {}",
+            path.display()
+        )?;
+        let positions = load_bench_file(path, max_lines.unwrap_or_default())?
+            .into_iter()
+            .map(|entry| entry.pos)
+            .filter(|pos| !pos.can_win_next())
+            .collect::<Vec<_>>();
+        const ITERATIONS: usize = 1000;
+        let mut num_moves: u64 = 0;
+        // Make sure the work isn't optimized away.
+        let mut checksum: u64 = 0;
+        let now = Instant::now();
+        for _ in 0..ITERATIONS {
+            for pos in &positions {
+                let possible = std::hint::black_box(pos).possible_non_losing_moves();
+                for col in 0..Position::WIDTH {
+                    let bmove = possible & Position::column_mask(col);
+                    if bmove != 0 {
+                        checksum += u64::from(pos.move_score(bmove));
+                        num_moves += 1;
+                    }
+                }
+            }
+        }
+        let elapsed = now.elapsed();
+        std::hint::black_box(checksum);
+        writeln!(out, "Finished benchmark")?;
+        writeln!(
+            out,
+            "Scored {num_moves} moves in {elapsed:?} ({:.0} moves/s)",
+            num_moves as f64 / elapsed.as_secs_f64()
+        )?;
         Ok(())
     }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        #[test]
+        fn stress_is_consistent() {
+            let pos = Position::from_string("4455221").unwrap();
+            assert_eq!(
+                stress_position(&mut io::sink(), &pos, 3, false, 4).unwrap(),
+                Ok(5)
+            );
+            assert_eq!(
+                stress_position(&mut io::sink(), &pos, 2, true, 4).unwrap(),
+                Ok(1)
+            );
+            // An error while reporting a run stops the runs.
+            assert!(stress_position(&mut &mut [0u8; 0][..], &pos, 2, false, 4).is_err());
+            assert!(Parser::parse_command("stress 4455221 0 4".split_whitespace(), true).is_none());
+            assert!(matches!(
+                Parser::parse_command("stress 4455221 2 4".split_whitespace(), true),
+                Some(Command::Stress(_, 2, 4))
+            ));
+        }
+
+        #[test]
+        fn session_book() {
+            let pos = Position::from_string("4455221").unwrap();
+            let mut parser = Parser::new(false);
+            // Nothing is recorded by default.
+            parser.record_solve(&pos, 5);
+            assert!(parser.session_book.is_none());
+
+            parser.session_book = Some(OpeningBook::with_kind(BookKind::Strong));
+            parser.solve(&mut io::sink(), &pos, false).unwrap();
+            let book = parser.session_book.as_ref().unwrap();
+            assert_eq!(book.get(&pos), Some(5));
+            // Weak results can't be stored in a strong book.
+            let pos2 = Position::from_string("44552213").unwrap();
+            parser.weak = true;
+            parser.record_solve(&pos2, 1);
+            assert!(!parser.session_book.as_ref().unwrap().contains(&pos2));
+        }
+
+        #[test]
+        fn run_with() {
+            let mut parser = Parser::new(false);
+            let script = "position 4 4; play 5\ntoggle-weak\nquit\nclear-tt\n";
+            let mut out = Vec::new();
+            parser.run_with(script.as_bytes(), &mut out).unwrap();
+            let expected = "\
+> Played columns: [4, 4]
+
+Current position:
+.......
+.......
+.......
+.......
+...x...
+...o...
+Played columns: [5]
+
+Current position:
+.......
+.......
+.......
+.......
+...x...
+...oo..
+
+> Weak set to true
+
+> ";
+            assert_eq!(String::from_utf8(out).unwrap(), expected);
+            assert!(parser.weak);
+        }
+
+        #[test]
+        fn run_with_solve() {
+            let mut parser = Parser::new(false);
+            let script =
+                "position 4 4 5 5 6 6; solve\nbench-movegen ./benchmark_files/end_easy 2\n";
+            let mut out = Vec::new();
+            parser.run_with(script.as_bytes(), &mut out).unwrap();
+            let out = String::from_utf8(out).unwrap();
+            // The results go to `out`, and the solver doesn't print the steps of its searches.
+            assert!(out.contains("\nScore is 18, which means 'o' can win in 1 move(s)\n"));
+            assert!(out.contains("Total number of nodes: 0\nTook "));
+            assert!(out.contains("Starting move generation benchmark: ./benchmark_files/end_easy"));
+            assert!(out.contains("Finished benchmark\nScored "));
+            assert_eq!(parser.solver.config().verbosity, Verbosity::Silent);
+            // With a summary, each solve also gives a single line with its statistics.
+            let script = "verbosity summary\nposition 4 4 5 5 6 6; solve\nverbosity loud\n";
+            let mut out = Vec::new();
+            parser.run_with(script.as_bytes(), &mut out).unwrap();
+            let out = String::from_utf8(out).unwrap();
+            assert!(out.contains("Set verbosity to Summary\n"));
+            assert!(out.contains("\nScore: 18, nodes: 0, took: "));
+            assert!(out.contains("\nScore is 18, which means"));
+            assert_eq!(parser.solver.config().verbosity, Verbosity::Summary);
+            let mut out = Vec::new();
+            parser
+                .run_with("verbosity silent\n".as_bytes(), &mut out)
+                .unwrap();
+            assert!(String::from_utf8(out)
+                .unwrap()
+                .contains("Set verbosity to Silent\n"));
+            assert!(Parser::parse_command("verbosity".split_whitespace(), true).is_none());
+        }
+
+        #[test]
+        fn load_book_if_present() {
+            let mut parser = Parser::new(false);
+            let mut out = Vec::new();
+            let missing = std::env::temp_dir().join("connect4_no_default_book.book");
+            let _ = std::fs::remove_file(&missing);
+            assert!(!parser.load_book_if_present(&missing, &mut out).unwrap());
+            assert!(out.is_empty());
+            // A broken book is not fatal, the solver just has no book.
+            let broken = std::env::temp_dir().join("connect4_broken_default_book.book");
+            std::fs::write(&broken, "not a book\n").unwrap();
+            assert!(!parser.load_book_if_present(&broken, &mut out).unwrap());
+            assert!(out.is_empty());
+            std::fs::remove_file(&broken).unwrap();
+            assert_eq!(parser.solver.config().book_entries, 0);
+            let path = std::env::temp_dir().join("connect4_test_default_book.book");
+            let mut book = OpeningBook::new();
+            book.put(&Position::new(), 1);
+            book.put(&Position::from_string("4").unwrap(), -1);
+            book.store(&path).unwrap();
+            assert!(parser.load_book_if_present(&path, &mut out).unwrap());
+            assert_eq!(
+                String::from_utf8(out).unwrap(),
+                format!("Loaded book in {path:?} (2 entries)\n")
+            );
+            assert_eq!(parser.solver.config().book_entries, 2);
+            let mut info = Vec::new();
+            parser.write_info(&mut info).unwrap();
+            assert!(String::from_utf8(info)
+                .unwrap()
+                .ends_with("\nOpening book: 2 entries\n"));
+            std::fs::remove_file(&path).unwrap();
+        }
+
+        #[test]
+        fn score_table() {
+            let scores = [
+                None,
+                Some(-3),
+                Some(15),
+                Some(2),
+                Some(2),
+                Some(15),
+                Some(-18),
+            ];
+            let table = format_scores(&scores, false);
+            let lines = table.lines().collect::<Vec<_>>();
+            assert_eq!(lines[0], "column:   1   2   3   4   5   6   7");
+            assert_eq!(lines[1], "score:    -  -3  15   2   2  15 -18");
+            // Weak scores only give the result, even if a column has an exact score.
+            let scores = [
+                None,
+                Some(-1),
+                Some(15),
+                Some(0),
+                Some(1),
+                Some(1),
+                Some(-1),
+            ];
+            let table = format_scores(&scores, true);
+            let lines = table.lines().collect::<Vec<_>>();
+            assert_eq!(
+                lines[0],
+                "column:     1     2     3     4     5     6     7"
+            );
+            assert_eq!(
+                lines[1],
+                "result:     -  loss   win  draw   win   win  loss"
+            );
+        }
+
+        #[test]
+        fn replay_game() {
+            let path = std::env::temp_dir().join("connect4_replay_game.txt");
+            fs::write(&path, "4455221\n").unwrap();
+            let moves = load_game(&path).unwrap();
+            assert_eq!(moves, vec![3, 3, 4, 4, 1, 1, 0]);
+            fs::write(&path, "4455").unwrap();
+            assert!(load_game(&path).is_ok());
+            fs::write(&path, "44444444").unwrap();
+            assert!(load_game(&path).is_err());
+            fs::remove_file(&path).unwrap();
+
+            let mut parser = Parser::new(false);
+            let mut pos = Position::new();
+            let options = ReplayOptions {
+                pause: Some(Duration::ZERO),
+                eval: false,
+            };
+            assert!(parser
+                .replay(
+                    &mut io::empty(),
+                    &mut io::sink(),
+                    &mut pos,
+                    &moves,
+                    &options
+                )
+                .unwrap()
+                .is_empty());
+            assert_eq!(pos.key(), Position::from_string("4455221").unwrap().key());
+
+            assert_eq!(find_mistakes(&[3, -3, 3, -2, 2]), vec![2]);
+            // The first move gives away two points, which the second move gives back.
+            assert_eq!(find_mistakes(&[3, -1, 3, -3, 3]), vec![0, 1]);
+            assert_eq!(find_mistakes(&[0, 0, 0]), Vec::<usize>::new());
+            assert_eq!(find_mistakes(&[5]), Vec::<usize>::new());
+        }
+
+        #[test]
+        fn won_game() {
+            let path = std::env::temp_dir().join("connect4_won_game.txt");
+            fs::write(&path, "1212121\n").unwrap();
+            assert_eq!(load_game(&path).unwrap(), vec![0, 1, 0, 1, 0, 1, 0]);
+            // No move can follow the winning move.
+            fs::write(&path, "12121212").unwrap();
+            assert!(load_game(&path).is_err());
+            // The second player wins with the last move.
+            let game = "5275331143367744242212155342";
+            fs::write(&path, game).unwrap();
+            let moves = load_game(&path).unwrap();
+            fs::remove_file(&path).unwrap();
+            assert_eq!(moves.len(), game.len());
+
+            // Start late in the game, so solving the positions is fast.
+            let start = Position::from_string(&game[..23]).unwrap();
+            let mut parser = Parser::new(false);
+            let reviews = parser.solver.review_game(&start, &moves[23..]);
+            assert_eq!(reviews.len(), 5);
+            assert!(reviews.iter().all(|review| review.tag == MoveTag::Best));
+            let last = reviews[4];
+            assert!(!last.first_player);
+            assert_eq!(last.played, 1);
+
+            let mut pos = start.clone();
+            let options = ReplayOptions {
+                pause: Some(Duration::ZERO),
+                eval: true,
+            };
+            let mut out = Vec::new();
+            let mistakes = parser
+                .replay(&mut io::empty(), &mut out, &mut pos, &moves[23..], &options)
+                .unwrap();
+            assert!(mistakes.is_empty());
+            assert!(String::from_utf8(out)
+                .unwrap()
+                .contains("Move 5: column 2 wins the game"));
+            // The winning move isn't played, the position can't contain an alignment.
+            assert_eq!(pos.key(), Position::from_string(&game[..27]).unwrap().key());
+        }
+
+        #[test]
+        fn check_opening_book() {
+            // A symmetric position, so the book shares the entries of mirrored moves.
+            let pos = Position::from_string("4444117722662266").unwrap();
+            assert_eq!(pos.key(), pos.mirrored_key());
+            let mut solver = Solver::new(None);
+            assert!(check_book(&mut solver, &pos, false).is_none());
+            solver.generate_book(&pos, pos.nb_moves() as usize + 1, false);
+            solver.reset_transposition_table();
+            let check = check_book(&mut solver, &pos, false).unwrap();
+            assert!(check.agrees());
+            assert_eq!(check.book_score, Some(check.solved_score));
+            let book_move = check.book_move.unwrap();
+
+            // The mirror image of a best move is also a best move, so it isn't a mismatch.
+            let scores = solver.book_scores(&pos, false).unwrap();
+            let mirrored = Position::WIDTH - 1 - book_move;
+            assert_eq!(scores[book_move as usize], scores[mirrored as usize]);
+            assert!(is_best_move(&scores, book_move, false));
+            assert!(is_best_move(&scores, mirrored, false));
+
+            // A wrong score in the book is flagged.
+            let mut book = solver.take_book().unwrap();
+            book.put(&pos, check.solved_score + 1);
+            solver.set_book(book);
+            let check = check_book(&mut solver, &pos, false).unwrap();
+            assert!(!check.score_agrees);
+            assert!(check.move_agrees);
+            // But not when only the outcome is compared, since the position is won.
+            let check = check_book(&mut solver, &pos, true).unwrap();
+            assert!(check.solved_score > 0);
+            assert!(check.agrees());
+        }
+
+        #[test]
+        fn progress_bars() {
+            assert_eq!(
+                progress_bar(0, 1000, false),
+                format!("[{}]   0% (0 nodes)", " ".repeat(40))
+            );
+            assert_eq!(
+                progress_bar(500, 1000, false),
+                format!("[{}{}]  50% (500 nodes)", "#".repeat(20), " ".repeat(20))
+            );
+            // The estimate was too low.
+            assert!(progress_bar(5000, 1000, false).contains(" 99% (5000 nodes)"));
+            assert!(progress_bar(10, 1000, true).contains("100% (10 nodes)"));
+            assert!(progress_bar(10, 0, false).contains(" 99%"));
+        }
+
+        #[test]
+        fn parse_whitespace() {
+            assert!(Parser::parse_command("".split_whitespace(), true).is_none());
+            assert!(Parser::parse_command("  \t ".split_whitespace(), true).is_none());
+            assert!(matches!(
+                Parser::parse_command("  moves 1   2 3 ".split_whitespace(), true),
+                Some(Command::PlayMoves(moves)) if moves == [1, 2, 3]
+            ));
+            assert!(matches!(
+                Parser::parse_command("window  -1 1\n".split_whitespace(), true),
+                Some(Command::Window(-1, 1))
+            ));
+            assert!(matches!(
+                Parser::parse_command("solve-board 7/7/7/7 3x3 2oox2".split_whitespace(), true),
+                Some(Command::SolveBoard(pos)) if pos.nb_moves() == 4
+            ));
+            assert!(
+                Parser::parse_command("solve-board 7/7/7/7/7/xx5".split_whitespace(), true)
+                    .is_none()
+            );
+            assert!(matches!(
+                Parser::parse_command("abort-interval 64".split_whitespace(), true),
+                Some(Command::SetAbortInterval(64))
+            ));
+            assert!(Parser::parse_command("abort-interval 100".split_whitespace(), true).is_none());
+        }
+
+        #[test]
+        fn dataset() {
+            let path = &std::env::temp_dir().join("connect4_dataset.csv");
+            generate_dataset(&mut io::sink(), 5, path, 42, 1).unwrap();
+            let contents = fs::read_to_string(path).unwrap();
+            fs::remove_file(path).unwrap();
+            let lines = contents.lines().collect::<Vec<_>>();
+            assert_eq!(lines.len(), 6);
+            for line in &lines[1..] {
+                let values = line.split(',').collect::<Vec<_>>();
+                assert_eq!(values.len(), Position::FEATURE_LEN + 2);
+                let score = values[Position::FEATURE_LEN].parse::<isize>().unwrap();
+                let outcome = values[Position::FEATURE_LEN + 1].parse::<isize>().unwrap();
+                assert_eq!(score.signum(), outcome);
+            }
+            // The same seed gives the same positions.
+            let mut seed = 42;
+            let first = random_position(&mut seed);
+            let mut seed = 42;
+            assert_eq!(
+                random_position(&mut seed).map(|pos| pos.key()),
+                first.map(|pos| pos.key())
+            );
+        }
+
+        #[test]
+        fn balanced_dataset() {
+            let path = std::env::temp_dir().join("connect4_balanced_dataset.csv");
+            let quotas = OutcomeCounts {
+                wins: 3,
+                draws: 3,
+                losses: 3,
+            };
+            let counts =
+                generate_balanced_dataset(&mut io::sink(), quotas, 10_000, &path, 7, 1).unwrap();
+            assert_eq!(counts, quotas);
+            let contents = fs::read_to_string(&path).unwrap();
+            let mut outcomes = OutcomeCounts::default();
+            for line in contents.lines().skip(1) {
+                let outcome = line.rsplit(',').next().unwrap().parse::<isize>().unwrap();
+                *outcomes.get_mut(Outcome::from_score(outcome)) += 1;
+            }
+            assert_eq!(outcomes, quotas);
+
+            // Stops after the maximal number of playouts.
+            let counts =
+                generate_balanced_dataset(&mut io::sink(), quotas, 2, &path, 7, 1).unwrap();
+            fs::remove_file(&path).unwrap();
+            assert!(counts.total() <= 2);
+        }
+
+        #[test]
+        fn load_bench_positions() {
+            let entries = load_bench_file(PathBuf::from("./benchmark_files/end_easy"), 5).unwrap();
+            assert_eq!(entries.len(), 5);
+            for (i, entry) in entries.iter().enumerate() {
+                assert_eq!(entry.line, i);
+                assert!(entry.expected_score.is_some());
+                assert_eq!(entry.pos.nb_moves() as usize, entry.position_str.len());
+            }
+        }
+
+        #[test]
+        fn resume_bench() {
+            let state = std::env::temp_dir().join("connect4_resume_bench.txt");
+            let _ = fs::remove_file(&state);
+            let bench = PathBuf::from("./benchmark_files/end_easy");
+            let mut checkpoint = BenchCheckpoint::open(state.clone()).unwrap();
+            resume_bench_file(
+                &mut io::sink(),
+                bench.clone(),
+                Some(3),
+                false,
+                1,
+                true,
+                &mut checkpoint,
+            )
+            .unwrap();
+            assert_eq!(checkpoint.num_completed(), 3);
+            drop(checkpoint);
+
+            let mut checkpoint = BenchCheckpoint::open(state.clone()).unwrap();
+            assert_eq!(checkpoint.num_completed(), 3);
+            let recorded = checkpoint.get(&bench, 2).unwrap();
+            // The solved lines are skipped, and only the new ones are appended.
+            resume_bench_file(
+                &mut io::sink(),
+                bench.clone(),
+                Some(5),
+                false,
+                1,
+                true,
+                &mut checkpoint,
+            )
+            .unwrap();
+            assert_eq!(checkpoint.num_completed(), 5);
+            assert_eq!(checkpoint.get(&bench, 2), Some(recorded));
+            let contents = fs::read_to_string(&state).unwrap();
+            assert_eq!(contents.lines().count(), 5);
+            fs::remove_file(&state).unwrap();
+        }
+
+        #[test]
+        fn game_database() {
+            let path = std::env::temp_dir().join("connect4_games.txt");
+            let games = [
+                "# result|moves",
+                "1-0|4453",
+                // The last move wins the game.
+                "1-0|1212121",
+                "0-1 | 4 4 5",
+                "",
+                "1/2-1/2;44",
+                "4455\t-1",
+                "*,1",
+                "4",
+                "1 4 4 5",
+                // Malformed lines are skipped.
+                "win|448",
+                "maybe|4453",
+                "1|4444444",
+                "1-0|12121212",
+            ];
+            fs::write(&path, games.join("\n")).unwrap();
+            let loaded = load_games(&path).unwrap().collect::<Vec<_>>();
+            fs::remove_file(&path).unwrap();
+            assert_eq!(
+                loaded,
+                vec![
+                    (vec![3, 3, 4, 2], Some(Outcome::Win)),
+                    (vec![0, 1, 0, 1, 0, 1, 0], Some(Outcome::Win)),
+                    (vec![3, 3, 4], Some(Outcome::Loss)),
+                    (vec![3, 3], Some(Outcome::Draw)),
+                    (vec![3, 3, 4, 4], Some(Outcome::Loss)),
+                    (vec![0], None),
+                    (vec![3], None),
+                    (vec![3, 3, 4], Some(Outcome::Win)),
+                ]
+            );
+            assert!(load_games(&std::env::temp_dir().join("connect4_no_games.txt")).is_err());
+        }
+
+        #[test]
+        fn bench_mismatches() {
+            let path = std::env::temp_dir().join("connect4_bench_mismatches.txt");
+            let lines = fs::read_to_string("./benchmark_files/end_easy").unwrap();
+            let mut lines = lines.lines().take(3).map(str::to_owned).collect::<Vec<_>>();
+            // Give the second line a wrong score.
+            let (moves, score) = lines[1].split_once(' ').unwrap();
+            lines[1] = format!("{moves} {}", score.parse::<isize>().unwrap() + 1);
+            fs::write(&path, lines.join("\n")).unwrap();
+            let summary = bench_file(&mut io::sink(), path.clone(), None, false, 1, true).unwrap();
+            fs::remove_file(&path).unwrap();
+            assert_eq!(
+                summary,
+                BenchSummary {
+                    lines: 3,
+                    mismatches: 1
+                }
+            );
+            assert!(matches!(
+                Parser::parse_command("bench all --strict".split_whitespace(), true),
+                Some(Command::Bench { strict: true, .. })
+            ));
+        }
+    }
 }
@@ -0,0 +1,55 @@
+//! The messages of the solver are printed to `std_out`, or with the `log` feature,
+//! logged with the [`log`](https://docs.rs/log) crate, so applications using the
+//! library can filter them or send them somewhere else. Without either `log` or
+//! `std` they are dropped. Errors and warnings are printed to `std_err` instead.
+
+/// Log a message at the info level, or print it without the `log` feature.
+macro_rules! info {
+    ($($arg:tt)*) => {{
+        #[cfg(feature = "log")]
+        ::log::info!($($arg)*);
+        #[cfg(all(not(feature = "log"), feature = "std"))]
+        println!($($arg)*);
+        #[cfg(all(not(feature = "log"), not(feature = "std")))]
+        let _ = format_args!($($arg)*);
+    }};
+}
+
+/// Log a message at the debug level, or print it without the `log` feature.
+macro_rules! debug {
+    ($($arg:tt)*) => {{
+        #[cfg(feature = "log")]
+        ::log::debug!($($arg)*);
+        #[cfg(all(not(feature = "log"), feature = "std"))]
+        println!($($arg)*);
+        #[cfg(all(not(feature = "log"), not(feature = "std")))]
+        let _ = format_args!($($arg)*);
+    }};
+}
+
+/// Log a message at the error level, or print it to `std_err` without the `log` feature.
+#[cfg(feature = "std")]
+macro_rules! error {
+    ($($arg:tt)*) => {{
+        #[cfg(feature = "log")]
+        ::log::error!($($arg)*);
+        #[cfg(not(feature = "log"))]
+        eprintln!($($arg)*);
+    }};
+}
+
+/// Log a message at the warn level, or print it to `std_err` without the `log` feature.
+/// It isn't called `warn!`, which would clash with the `warn` lint attribute.
+#[cfg(feature = "std")]
+macro_rules! warning {
+    ($($arg:tt)*) => {{
+        #[cfg(feature = "log")]
+        ::log::warn!($($arg)*);
+        #[cfg(not(feature = "log"))]
+        eprintln!($($arg)*);
+    }};
+}
+
+pub(crate) use {debug, info};
+#[cfg(feature = "std")]
+pub(crate) use {error, warning};
@@ -1,6 +1,50 @@
 use connect_4::game_solver;
-fn main() -> std::io::Result<()> {
+use std::process::ExitCode;
+
+/// With the `log` feature, the messages of the solver are logged instead of printed.
+/// This logger prints them all again, so the command line interface shows the same
+/// output with and without the feature.
+#[cfg(feature = "log")]
+struct PrintLogger;
+
+#[cfg(feature = "log")]
+impl log::Log for PrintLogger {
+    fn enabled(&self, _: &log::Metadata) -> bool {
+        true
+    }
+
+    fn log(&self, record: &log::Record) {
+        if record.level() <= log::Level::Warn {
+            eprintln!("{}", record.args());
+        } else {
+            println!("{}", record.args());
+        }
+    }
+
+    fn flush(&self) {}
+}
+
+fn main() -> std::io::Result<ExitCode> {
+    #[cfg(feature = "log")]
+    if log::set_logger(&PrintLogger).is_ok() {
+        log::set_max_level(log::LevelFilter::Debug);
+    }
     println!("Connect 4 solver by Pascal Pons ported to rust by Wannes Malfait");
     let mut parser = game_solver::Parser::new(false);
-    parser.run()
+    if !parser.handle_ctrl_c() {
+        eprintln!("Could not install the Ctrl-C handler, Ctrl-C will quit the solver");
+    }
+    // `--no-book` starts without the opening book, even if there is one in the default path.
+    // It is loaded first, so the info shows the book entries.
+    if !std::env::args().skip(1).any(|arg| arg == "--no-book") {
+        parser.load_default_book();
+    }
+    parser.print_info();
+    parser.run()?;
+    // `bench --strict` reports wrong scores through the exit code.
+    Ok(if parser.failed() {
+        ExitCode::FAILURE
+    } else {
+        ExitCode::SUCCESS
+    })
 }
@@ -46,8 +46,12 @@ impl MoveSorter {
         }
     }
     /// Add a move in the container with its score.
-    /// You cannot add more than `Position::WIDTH` moves
+    /// You cannot add more than `Position::WIDTH` moves, this panics.
     pub fn add(&mut self, bmove: position::Bitboard, col: position::Column, score: u8) {
+        assert!(
+            self.size < Position::WIDTH as usize,
+            "Cannot add more than Position::WIDTH moves to the MoveSorter"
+        );
         let mut pos = self.size;
         let new = Inner { score, col, bmove };
         // Shift elements to the right until we are in the right place.
@@ -65,6 +69,12 @@ impl MoveSorter {
     pub fn reset(&mut self) {
         self.size = 0;
     }
+
+    /// The number of moves that can still be added.
+    #[must_use]
+    pub fn remaining_capacity(&self) -> usize {
+        Position::WIDTH as usize - self.size
+    }
 }
 
 impl Iterator for MoveSorter {
@@ -96,8 +106,30 @@ mod tests {
         for i in 0..Position::WIDTH {
             ms.add(u64::from(i), i, Position::WIDTH - i + 4);
         }
-        for (i, (bmove, _)) in ms.into_iter().enumerate() {
+        for (i, (bmove, _)) in ms.enumerate() {
             assert_eq!(bmove, i as position::Bitboard);
         }
     }
+
+    #[test]
+    fn capacity() {
+        let mut ms = MoveSorter::new();
+        assert_eq!(ms.remaining_capacity(), Position::WIDTH as usize);
+        ms.add(1, 0, 3);
+        ms.add(2, 1, 2);
+        assert_eq!(ms.remaining_capacity(), Position::WIDTH as usize - 2);
+        ms.next();
+        assert_eq!(ms.remaining_capacity(), Position::WIDTH as usize - 1);
+        ms.reset();
+        assert_eq!(ms.remaining_capacity(), Position::WIDTH as usize);
+    }
+
+    #[test]
+    #[should_panic(expected = "Cannot add more than Position::WIDTH moves")]
+    fn too_many_moves() {
+        let mut ms = MoveSorter::new();
+        for i in 0..=Position::WIDTH {
+            ms.add(u64::from(i), i, i);
+        }
+    }
 }
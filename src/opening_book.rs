@@ -1,8 +1,12 @@
+#[cfg(feature = "std")]
+use crate::error::Connect4Error;
 use crate::position::{Column, Position};
+use alloc::vec::Vec;
+use core::error::Error;
+#[cfg(feature = "std")]
 use std::{
-    error::Error,
     fs::File,
-    io::{BufRead, BufReader, BufWriter, Write},
+    io::{BufRead, BufReader, BufWriter, Read, Write},
     path::Path,
 };
 
@@ -13,22 +17,36 @@ struct BookEntry {
     /// The best possible score in the position
     score: isize,
 }
+/// Errors that can occur when parsing an entry of an opening book.
 #[derive(Debug)]
-enum ParseBookEntryError {
+pub enum ParseBookEntryError {
     /// The number of values in the string is not 3
     NumValues,
     /// The position key stored was not valid
     Pos,
     /// The score stored was not valid
     Score,
+    /// The header of the book was not valid
+    Header,
+    /// The file ended in the middle of an entry
+    Truncated,
+    /// The file was made with another version of the keys, see `Position::KEY_FORMAT_VERSION`
+    KeyFormat(u32),
 }
 
-impl std::fmt::Display for ParseBookEntryError {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+impl core::fmt::Display for ParseBookEntryError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
         match self {
             Self::NumValues => write!(f, "Expected 2 values in the entry"),
             Self::Pos => write!(f, "Could not parse first value into a valid position"),
             Self::Score => write!(f, "Could not parse third value into a valid score"),
+            Self::Header => write!(f, "Unknown book header"),
+            Self::Truncated => write!(f, "The last entry of the book is incomplete"),
+            Self::KeyFormat(version) => write!(
+                f,
+                "The file uses key format {version}, but only key format {} is supported",
+                Position::KEY_FORMAT_VERSION
+            ),
         }
     }
 }
@@ -39,13 +57,8 @@ impl Error for ParseBookEntryError {
     }
 }
 
-impl From<ParseBookEntryError> for std::io::Error {
-    fn from(err: ParseBookEntryError) -> Self {
-        std::io::Error::new(std::io::ErrorKind::InvalidInput, err)
-    }
-}
-
 impl BookEntry {
+    #[cfg(feature = "std")]
     pub fn from_string(str: &str) -> Result<Self, ParseBookEntryError> {
         let v: Vec<&str> = str.split(' ').collect();
         if v.len() != 2 {
@@ -61,21 +74,126 @@ impl BookEntry {
         };
         Ok(Self { pos, score })
     }
+
+    /// Parse an entry of a weak book, stored as the key in little endian
+    /// followed by a single byte for the score.
+    #[cfg(feature = "std")]
+    fn from_bytes(bytes: &[u8; 9]) -> Self {
+        let mut pos = [0; 8];
+        pos.copy_from_slice(&bytes[..8]);
+        Self {
+            pos: u64::from_le_bytes(pos),
+            score: isize::from(bytes[8] as i8),
+        }
+    }
+
+    /// The inverse of `from_bytes()`.
+    #[cfg(feature = "std")]
+    fn to_bytes(self) -> [u8; 9] {
+        let mut bytes = [0; 9];
+        bytes[..8].copy_from_slice(&self.pos.to_le_bytes());
+        bytes[8] = self.score as i8 as u8;
+        bytes
+    }
+}
+
+/// The kind of scores stored in an opening book.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum BookKind {
+    /// The exact score of each position is stored.
+    #[default]
+    Strong,
+    /// Only whether each position is a win (1), a draw (0) or a loss (-1) is stored.
+    /// On disk every score takes up a single byte.
+    Weak,
+}
+
+impl BookKind {
+    /// The name of the kind, as used in the header of a book file.
+    #[must_use]
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Strong => "strong",
+            Self::Weak => "weak",
+        }
+    }
+}
+
+/// A summary of the scores in an opening book, see `OpeningBook::stats`.
+///
+/// Wins, draws and losses are from the point of view of the player to move.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct BookStats {
+    /// The number of entries in the book.
+    pub total: usize,
+    /// The number of entries with a positive score.
+    pub wins: usize,
+    /// The number of entries with a score of 0.
+    pub draws: usize,
+    /// The number of entries with a negative score.
+    pub losses: usize,
+    /// The lowest score, `None` if the book is empty.
+    pub min_score: Option<isize>,
+    /// The highest score, `None` if the book is empty.
+    pub max_score: Option<isize>,
+    /// The average score, `None` if the book is empty.
+    pub mean_score: Option<f64>,
+}
+
+/// The first line of a book file starts with this, followed by the `BookKind`.
+/// Books without a header are strong books.
+#[cfg(feature = "std")]
+const HEADER_PREFIX: &str = "#connect4-book ";
+
+/// In a header, this is followed by the `Position::KEY_FORMAT_VERSION` of the keys in the file.
+#[cfg(feature = "std")]
+const KEY_FORMAT_TAG: &str = "key-format";
+
+/// Format the end of a header, giving the version of the keys.
+#[cfg(feature = "std")]
+pub(crate) fn key_format_header() -> String {
+    format!("{KEY_FORMAT_TAG} {}", Position::KEY_FORMAT_VERSION)
+}
+
+/// Check the end of a header, as written by `key_format_header()`. Files from before
+/// the version was added don't have it, and use the first version.
+#[cfg(feature = "std")]
+pub(crate) fn check_key_format(header_end: &str) -> Result<(), ParseBookEntryError> {
+    let mut words = header_end.split_whitespace();
+    let version = match (words.next(), words.next(), words.next()) {
+        (None, _, _) => 1,
+        (Some(KEY_FORMAT_TAG), Some(version), None) => {
+            version.parse().map_err(|_| ParseBookEntryError::Header)?
+        }
+        _ => return Err(ParseBookEntryError::Header),
+    };
+    if version == Position::KEY_FORMAT_VERSION {
+        Ok(())
+    } else {
+        Err(ParseBookEntryError::KeyFormat(version))
+    }
 }
 
-/// An `OpeningBook` is a way to store the best moves in common positions
-/// in the opening, which might take a long time to solve. For each position
-/// in the opening book we store the best move and the score associated with
-/// this move.
+/// An `OpeningBook` is a way to store the scores of common positions in the
+/// opening, which might take a long time to solve. For each position in the
+/// opening book we store its score. The best moves are found by looking up the
+/// positions after each move.
 ///
-/// **Warning**: Only one entry is stored per position.
+/// **Warning**: Only one entry is stored per position, and a position shares its
+/// entry with its mirror image. This is fine for scores, which are the same for
+/// both, but the book can't store anything that depends on the orientation of
+/// the board, like a best column.
 pub struct OpeningBook {
     entries: Vec<BookEntry>,
+    kind: BookKind,
 }
 
 impl From<Vec<BookEntry>> for OpeningBook {
     fn from(vec: Vec<BookEntry>) -> Self {
-        let mut book = OpeningBook { entries: vec };
+        let mut book = OpeningBook {
+            entries: vec,
+            kind: BookKind::Strong,
+        };
         if book.is_valid() {
             return book;
         }
@@ -98,11 +216,9 @@ impl Iterator for BookMoves<'_> {
 
     fn next(&mut self) -> Option<Self::Item> {
         for col in self.next_col..(Position::WIDTH) {
-            if !self.pos.can_play(col) {
+            let Some(next_pos) = self.pos.with_move(col) else {
                 continue;
-            }
-            let mut next_pos = self.pos.clone();
-            next_pos.play_col(col);
+            };
             if self.book.get(&next_pos).is_some() {
                 self.next_col = col + 1;
                 return Some(col);
@@ -122,24 +238,83 @@ impl Default for OpeningBook {
 impl OpeningBook {
     #[must_use]
     pub fn new() -> Self {
+        Self::with_kind(BookKind::Strong)
+    }
+
+    /// Create an empty book storing the given kind of scores.
+    #[must_use]
+    pub fn with_kind(kind: BookKind) -> Self {
         OpeningBook {
             entries: Vec::new(),
+            kind,
         }
     }
 
+    /// The kind of scores stored in the book.
+    #[must_use]
+    pub fn kind(&self) -> BookKind {
+        self.kind
+    }
+
     #[must_use]
     pub fn num_entries(&self) -> usize {
         self.entries.len()
     }
 
+    /// Remove the entries of the positions with more than `max_depth` moves, e.g. to
+    /// ship a smaller book. The other entries stay as they are.
+    ///
+    /// The number of moves doesn't have to be stored: every stone is a nonzero digit
+    /// of the base 3 key, see `Position::key3`, so it can be counted from the key.
+    /// ```
+    /// use connect_4::opening_book::OpeningBook;
+    /// use connect_4::position::Position;
+    /// let mut book = OpeningBook::new();
+    /// book.put(&Position::from_string("4").unwrap(), 1);
+    /// book.put(&Position::from_string("44").unwrap(), -1);
+    /// book.truncate_to_depth(1);
+    /// assert_eq!(book.num_entries(), 1);
+    /// assert!(book.contains(&Position::from_string("4").unwrap()));
+    /// ```
+    pub fn truncate_to_depth(&mut self, max_depth: u8) {
+        self.entries
+            .retain(|entry| key3_moves(entry.pos) <= u32::from(max_depth));
+    }
+
+    /// Count the wins, draws and losses in the book, and compute the range and the
+    /// average of the scores. Each entry is only counted once, even though it is
+    /// shared with the mirrored position.
+    #[must_use]
+    pub fn stats(&self) -> BookStats {
+        let mut stats = BookStats {
+            total: self.entries.len(),
+            ..BookStats::default()
+        };
+        // The scores are not necessarily from a 7x6 board, so don't rely on their range.
+        let mut sum: i128 = 0;
+        for entry in &self.entries {
+            match entry.score.cmp(&0) {
+                core::cmp::Ordering::Greater => stats.wins += 1,
+                core::cmp::Ordering::Equal => stats.draws += 1,
+                core::cmp::Ordering::Less => stats.losses += 1,
+            }
+            stats.min_score = Some(stats.min_score.map_or(entry.score, |m| m.min(entry.score)));
+            stats.max_score = Some(stats.max_score.map_or(entry.score, |m| m.max(entry.score)));
+            sum += entry.score as i128;
+        }
+        if stats.total > 0 {
+            stats.mean_score = Some(sum as f64 / stats.total as f64);
+        }
+        stats
+    }
+
     fn is_valid(&self) -> bool {
         // `is_sorted()` is unstable, so create our own version.
         let mut prev = match self.entries.first() {
             Some(e) => e.pos,
             None => return true,
         };
-        return self
-            .entries
+        self.entries
             .iter()
             .skip(1)
             .map(|entry| entry.pos)
@@ -150,38 +325,112 @@ impl OpeningBook {
                 };
                 prev = curr;
                 true
-            });
+            })
     }
 
     /// Load an opening book from a file. If errors occured while
     /// loading or parsing the file an `Err` is returned.
-    pub fn load(path: &Path) -> Result<Self, std::io::Error> {
+    #[cfg(feature = "std")]
+    pub fn load(path: &Path) -> Result<Self, Connect4Error> {
         let file = File::open(path)?;
-        let file = BufReader::new(file);
+        let mut file = BufReader::new(file);
         let mut entries = Vec::new();
-        for line in file.lines() {
-            let line = line?;
-            let entry = BookEntry::from_string(&line)?;
-            entries.push(entry);
+        let mut first_line = String::new();
+        file.read_line(&mut first_line)?;
+        let first_line = first_line.trim_end();
+        let header = first_line
+            .strip_prefix(HEADER_PREFIX)
+            .map(|header| header.split_once(' ').unwrap_or((header, "")));
+        if let Some((_, key_format)) = header {
+            check_key_format(key_format)?;
         }
-        Ok(Self::from(entries))
+        let kind = match header {
+            Some((kind, _)) if kind == BookKind::Strong.name() => BookKind::Strong,
+            Some((kind, _)) if kind == BookKind::Weak.name() => BookKind::Weak,
+            Some(_) => return Err(ParseBookEntryError::Header.into()),
+            None => {
+                // Old books have no header, and the first line is an entry.
+                if !first_line.is_empty() {
+                    entries.push(BookEntry::from_string(first_line)?);
+                }
+                BookKind::Strong
+            }
+        };
+        match kind {
+            BookKind::Strong => {
+                for line in file.lines() {
+                    let line = line?;
+                    let entry = BookEntry::from_string(&line)?;
+                    entries.push(entry);
+                }
+            }
+            BookKind::Weak => {
+                let mut bytes = Vec::new();
+                file.read_to_end(&mut bytes)?;
+                let chunks = bytes.chunks_exact(9);
+                if !chunks.remainder().is_empty() {
+                    return Err(ParseBookEntryError::Truncated.into());
+                }
+                for chunk in chunks {
+                    entries.push(BookEntry::from_bytes(chunk.try_into().unwrap()));
+                }
+            }
+        }
+        let mut book = Self::from(entries);
+        book.kind = kind;
+        Ok(book)
+    }
+
+    /// Store the opening book in a file, overwriting the file if it exists.
+    #[cfg(feature = "std")]
+    pub fn store(&self, path: &Path) -> Result<(), Connect4Error> {
+        let mut file = self.create_file(path)?;
+        file.flush()?;
+        Ok(())
     }
 
-    pub fn store(&self, path: &Path) -> Result<(), std::io::Error> {
-        let file = File::options().write(true).create(true).open(path)?;
-        file.set_len(0)?;
+    /// Create the file at `path`, overwriting it if it exists, and write the header
+    /// and the entries of the book to it.
+    #[cfg(feature = "std")]
+    fn create_file(&self, path: &Path) -> Result<BufWriter<File>, Connect4Error> {
+        let file = File::options()
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(path)?;
         let mut file = BufWriter::new(file);
-        for entry in &self.entries {
-            writeln!(&mut file, "{} {}", entry.pos, entry.score)?;
+        writeln!(
+            &mut file,
+            "{HEADER_PREFIX}{} {}",
+            self.kind.name(),
+            key_format_header()
+        )?;
+        for &entry in &self.entries {
+            write_entry(&mut file, self.kind, entry)?;
         }
+        Ok(file)
+    }
+
+    /// Store the opening book in a file like `store()`, and return a `BookWriter`
+    /// to append more entries to the file.
+    #[cfg(feature = "std")]
+    pub fn store_and_append(&self, path: &Path) -> Result<BookWriter, Connect4Error> {
+        let mut file = self.create_file(path)?;
         file.flush()?;
-        Ok(())
+        Ok(BookWriter {
+            file,
+            kind: self.kind,
+        })
     }
 
     /// Get the associated value of the given position. If no entry was found
     /// it returns `None`, otherwise it returns `Some(score)`.
     #[must_use]
     pub fn get(&self, pos: &Position) -> Option<isize> {
+        if pos.nb_moves() > Position::KEY3_MAX_MOVES {
+            // Such positions can't be in the book.
+            return None;
+        }
         self.get_by_key(pos.key3())
     }
 
@@ -199,12 +448,37 @@ impl OpeningBook {
         }
     }
 
+    /// Check whether the given position is in the book.
+    #[must_use]
+    pub fn contains(&self, pos: &Position) -> bool {
+        self.get(pos).is_some()
+    }
+
+    /// Remove the entry of the given position from the book, and return its score.
+    /// Returns `None` if the position was not in the book.
+    ///
+    /// Since the key is symmetric, this also removes the mirrored position.
+    pub fn remove(&mut self, pos: &Position) -> Option<isize> {
+        if pos.nb_moves() > Position::KEY3_MAX_MOVES {
+            return None;
+        }
+        let key = pos.key3();
+        let index = self
+            .entries
+            .binary_search_by_key(&key, |entry| entry.pos)
+            .ok()?;
+        Some(self.entries.remove(index).score)
+    }
+
     /// Insert an entry in the book for the given key of the position.
     /// If the position is already in the book, it is overwritten.
     ///
     /// WARNING: the key should be the symmetric base 3 key of the position.
     fn put_by_key(&mut self, key: u64, score: isize) {
-        let entry = BookEntry { pos: key, score };
+        let entry = BookEntry {
+            pos: key,
+            score: Self::stored_score(self.kind, score),
+        };
         match self.entries.binary_search_by_key(&key, |entry| entry.pos) {
             Ok(index) => {
                 // We already have an entry, so just overwrite it.
@@ -214,17 +488,61 @@ impl OpeningBook {
         }
     }
 
+    /// Insert many entries at once, like calling `put_by_key()` for each of them, but the
+    /// entries are only sorted once at the end, instead of shifting the entries after
+    /// every insert. If a key appears several times, the last score is kept.
+    pub(crate) fn put_all_by_key(&mut self, entries: impl IntoIterator<Item = (u64, isize)>) {
+        let len = self.entries.len();
+        let kind = self.kind;
+        self.entries
+            .extend(entries.into_iter().map(|(pos, score)| BookEntry {
+                pos,
+                score: Self::stored_score(kind, score),
+            }));
+        if self.entries.len() == len {
+            return;
+        }
+        // The sort is stable, so after reversing the last entry of a key comes first,
+        // which is the one `dedup_by_key` keeps.
+        self.entries.sort_by_key(|entry| entry.pos);
+        self.entries.reverse();
+        self.entries.dedup_by_key(|entry| entry.pos);
+        self.entries.reverse();
+    }
+
+    /// The score that a book of the given kind stores for `score`: for a weak book only its sign.
+    fn stored_score(kind: BookKind, score: isize) -> isize {
+        match kind {
+            BookKind::Strong => score,
+            BookKind::Weak => score.signum(),
+        }
+    }
+
     /// Insert an entry in the book for the given position.
     /// If the position is already in the book, it is overwritten.
+    /// For a weak book only the sign of the score is stored.
+    ///
+    /// The entry is shared with the mirrored position, so this also overwrites
+    /// the score of the mirrored position:
+    /// ```
+    /// use connect_4::opening_book::OpeningBook;
+    /// use connect_4::position::Position;
+    /// let mut book = OpeningBook::new();
+    /// book.put(&Position::from_string("12").unwrap(), 3);
+    /// assert_eq!(book.get(&Position::from_string("76").unwrap()), Some(3));
+    /// ```
+    ///
+    /// The position should have at most `Position::KEY3_MAX_MOVES` moves.
     #[inline]
     pub fn put(&mut self, pos: &Position, score: isize) {
+        debug_assert!(pos.nb_moves() <= Position::KEY3_MAX_MOVES);
         self.put_by_key(pos.key3(), score);
     }
 
     /// Get the playable moves from this position that are in the book.
     /// The moves are sorted by column.
     #[must_use]
-    pub fn book_moves_from_position(&self, pos: Position) -> BookMoves {
+    pub fn book_moves_from_position(&self, pos: Position) -> BookMoves<'_> {
         BookMoves {
             book: self,
             pos,
@@ -233,12 +551,70 @@ impl OpeningBook {
     }
 }
 
+/// The number of moves of the position with the base 3 key `key`, which is the number
+/// of its nonzero digits.
+fn key3_moves(mut key: u64) -> u32 {
+    let mut moves = 0;
+    while key > 0 {
+        moves += u32::from(!key.is_multiple_of(3));
+        key /= 3;
+    }
+    moves
+}
+
+/// Write a single entry in the format of a book of the given kind.
+#[cfg(feature = "std")]
+fn write_entry(file: &mut impl Write, kind: BookKind, entry: BookEntry) -> std::io::Result<()> {
+    match kind {
+        BookKind::Strong => writeln!(file, "{} {}", entry.pos, entry.score),
+        BookKind::Weak => file.write_all(&entry.to_bytes()),
+    }
+}
+
+/// Appends entries to a book file as soon as they are known, so a long generation
+/// doesn't lose its work when it is stopped. See `OpeningBook::store_and_append()`.
+///
+/// The appended entries are not sorted, but `OpeningBook::load()` sorts the entries,
+/// so the file can be loaded as usual. A position shouldn't be appended twice.
+#[cfg(feature = "std")]
+pub struct BookWriter {
+    file: BufWriter<File>,
+    kind: BookKind,
+}
+
+#[cfg(feature = "std")]
+impl BookWriter {
+    /// Append the entry of the given position to the file. For a weak book only the
+    /// sign of the score is stored. The entry is written to the file immediately.
+    ///
+    /// The position should have at most `Position::KEY3_MAX_MOVES` moves.
+    pub fn append(&mut self, pos: &Position, score: isize) -> Result<(), Connect4Error> {
+        debug_assert!(pos.nb_moves() <= Position::KEY3_MAX_MOVES);
+        let score = match self.kind {
+            BookKind::Strong => score,
+            BookKind::Weak => score.signum(),
+        };
+        let entry = BookEntry {
+            pos: pos.key3(),
+            score,
+        };
+        write_entry(&mut self.file, self.kind, entry)?;
+        self.file.flush()?;
+        Ok(())
+    }
+}
+
 #[cfg(test)]
 mod tests {
+    use crate::error::Connect4Error;
     use crate::position::{Column, Position};
 
+    use super::key3_moves;
     use super::BookEntry;
+    use super::BookKind;
+    use super::BookStats;
     use super::OpeningBook;
+    use super::ParseBookEntryError;
     #[test]
     fn adding_book_entries() {
         let mut book = OpeningBook::new();
@@ -279,6 +655,44 @@ mod tests {
         assert!(book.is_valid());
     }
 
+    #[test]
+    fn removing_book_entries() {
+        let mut book = OpeningBook::new();
+        let mut pos = Position::new();
+        let mut positions = Vec::new();
+        for j in 0..10 {
+            pos.play_col(j * 3 % Position::WIDTH);
+            book.put(&pos, isize::from(j));
+            positions.push(pos.clone());
+        }
+        assert!(book.contains(&positions[4]));
+        assert_eq!(book.remove(&positions[4]), Some(4));
+        assert!(!book.contains(&positions[4]));
+        assert_eq!(book.get(&positions[4]), None);
+        assert_eq!(book.num_entries(), 9);
+        assert!(book.is_valid());
+        // Removing again does nothing.
+        assert_eq!(book.remove(&positions[4]), None);
+        assert_eq!(book.remove(&Position::new()), None);
+        assert_eq!(book.num_entries(), 9);
+        assert_eq!(book.get(&positions[5]), Some(5));
+    }
+
+    #[test]
+    fn book_stats() {
+        assert_eq!(OpeningBook::new().stats(), BookStats::default());
+        let entries = [(1, 3), (2, 0), (3, -5), (4, 0), (5, 40), (6, isize::MAX)]
+            .map(|(pos, score)| BookEntry { pos, score });
+        let book = OpeningBook::from(entries.to_vec());
+        let stats = book.stats();
+        assert_eq!(stats.total, 6);
+        assert_eq!((stats.wins, stats.draws, stats.losses), (3, 2, 1));
+        assert_eq!(stats.min_score, Some(-5));
+        assert_eq!(stats.max_score, Some(isize::MAX));
+        let mean = (38.0 + isize::MAX as f64) / 6.0;
+        assert!((stats.mean_score.unwrap() - mean).abs() < 1.0);
+    }
+
     #[test]
     fn book_moves() {
         let mut pos = Position::new();
@@ -328,7 +742,7 @@ mod tests {
         pos.display_position();
         book.put(&pos, 0);
 
-        let book_path = std::path::Path::new("test_book.book");
+        let book_path = &std::env::temp_dir().join("connect4_test_book.book");
         book.store(book_path).unwrap();
         let book = OpeningBook::load(book_path).unwrap();
 
@@ -346,4 +760,160 @@ mod tests {
         assert_eq!(moves.next(), Some(6));
         assert_eq!(moves.next(), None);
     }
+
+    #[test]
+    fn put_all_by_key() {
+        let positions =
+            ["4", "44", "445", "4453", "3"].map(|moves| Position::from_string(moves).unwrap());
+        let mut book = OpeningBook::new();
+        book.put(&positions[0], 1);
+        book.put(&positions[1], 2);
+        book.put_all_by_key([
+            (positions[2].key3(), 3),
+            (positions[1].key3(), -2),
+            (positions[3].key3(), 4),
+            (positions[2].key3(), 5),
+        ]);
+        assert!(book.is_valid());
+        assert_eq!(book.num_entries(), 4);
+        // The last score of a key is kept.
+        let scores = positions.each_ref().map(|pos| book.get(pos));
+        assert_eq!(scores, [Some(1), Some(-2), Some(5), Some(4), None]);
+
+        let mut weak = OpeningBook::with_kind(BookKind::Weak);
+        weak.put_all_by_key([(positions[4].key3(), -7)]);
+        assert_eq!(weak.get(&positions[4]), Some(-1));
+    }
+
+    #[test]
+    fn truncate_to_depth() {
+        let mut book = OpeningBook::new();
+        let mut positions = Vec::new();
+        for start in 0..Position::WIDTH {
+            let mut pos = Position::new();
+            for j in 0..Position::KEY3_MAX_MOVES {
+                let col = (start + j * 3 + j / 5) % Position::WIDTH;
+                if !pos.can_play(col) || pos.is_winning_move(col) {
+                    break;
+                }
+                pos.play_col(col);
+                assert_eq!(key3_moves(pos.key3()), u32::from(pos.nb_moves()));
+                book.put(&pos, isize::from(j));
+                positions.push(pos.clone());
+            }
+        }
+        assert!(positions.iter().any(|pos| pos.nb_moves() > 8));
+        book.truncate_to_depth(8);
+        assert!(book.is_valid());
+        for pos in &positions {
+            assert_eq!(book.contains(pos), pos.nb_moves() <= 8);
+        }
+        book.truncate_to_depth(0);
+        assert_eq!(book.num_entries(), 0);
+    }
+
+    #[test]
+    fn store_load_weak_book() {
+        let mut book = OpeningBook::with_kind(BookKind::Weak);
+        let mut strong_book = OpeningBook::new();
+        let mut pos = Position::new();
+        for j in 0..20 {
+            pos.play_col(j * 5 % Position::WIDTH);
+            let score = (isize::from(j) % 5 - 2) * 7;
+            book.put(&pos, score);
+            strong_book.put(&pos, score);
+        }
+        let book_path = &std::env::temp_dir().join("connect4_test_weak_book.book");
+        let strong_book_path = &std::env::temp_dir().join("connect4_test_strong_book.book");
+        book.store(book_path).unwrap();
+        strong_book.store(strong_book_path).unwrap();
+        let size = std::fs::metadata(book_path).unwrap().len();
+        let strong_size = std::fs::metadata(strong_book_path).unwrap().len();
+        let loaded = OpeningBook::load(book_path).unwrap();
+        std::fs::remove_file(book_path).unwrap();
+        std::fs::remove_file(strong_book_path).unwrap();
+        assert!(size < strong_size);
+
+        assert_eq!(loaded.kind(), BookKind::Weak);
+        assert_eq!(loaded.num_entries(), strong_book.num_entries());
+        let mut pos = Position::new();
+        for j in 0..20 {
+            pos.play_col(j * 5 % Position::WIDTH);
+            let score = strong_book.get(&pos).unwrap();
+            assert_eq!(loaded.get(&pos), Some(score.signum()));
+        }
+    }
+
+    #[test]
+    fn load_book_without_header() {
+        let book_path = &std::env::temp_dir().join("connect4_test_old_book.book");
+        std::fs::write(book_path, "0 1\n2 2\n5 -1\n").unwrap();
+        let book = OpeningBook::load(book_path).unwrap();
+        std::fs::remove_file(book_path).unwrap();
+        assert_eq!(book.kind(), BookKind::Strong);
+        assert_eq!(book.num_entries(), 3);
+        assert_eq!(book.get(&Position::new()), Some(1));
+    }
+
+    #[test]
+    fn load_errors() {
+        let missing = &std::env::temp_dir().join("connect4_missing_test_book.book");
+        assert!(matches!(
+            OpeningBook::load(missing),
+            Err(Connect4Error::Io(_))
+        ));
+
+        let book_path = &std::env::temp_dir().join("connect4_corrupt_test_book.book");
+        std::fs::write(book_path, "123 4\nnot a book entry\n").unwrap();
+        let result = OpeningBook::load(book_path);
+        std::fs::remove_file(book_path).unwrap();
+        assert!(matches!(
+            result,
+            Err(Connect4Error::Parse(ParseBookEntryError::NumValues))
+        ));
+    }
+
+    #[test]
+    fn key_format_versions() {
+        let book_path = &std::env::temp_dir().join("connect4_key_format_test_book.book");
+        let mut book = OpeningBook::new();
+        book.put(&Position::new(), 1);
+        book.store(book_path).unwrap();
+        let header = std::fs::read_to_string(book_path).unwrap();
+        assert_eq!(
+            header.lines().next(),
+            Some(
+                format!(
+                    "#connect4-book strong key-format {}",
+                    Position::KEY_FORMAT_VERSION
+                )
+                .as_str()
+            )
+        );
+        assert_eq!(
+            OpeningBook::load(book_path).unwrap().get(&Position::new()),
+            Some(1)
+        );
+
+        // Books from before the version was stored use the first version.
+        std::fs::write(book_path, "#connect4-book strong\n0 1\n").unwrap();
+        assert_eq!(
+            OpeningBook::load(book_path).unwrap().get(&Position::new()),
+            Some(1)
+        );
+
+        std::fs::write(book_path, "#connect4-book strong key-format 2\n0 1\n").unwrap();
+        let result = OpeningBook::load(book_path);
+        assert!(matches!(
+            result,
+            Err(Connect4Error::Parse(ParseBookEntryError::KeyFormat(2)))
+        ));
+        std::fs::write(book_path, "#connect4-book weak key-format\n").unwrap();
+        let result = OpeningBook::load(book_path);
+        std::fs::remove_file(book_path).unwrap();
+        assert!(matches!(
+            result,
+            Err(Connect4Error::Parse(ParseBookEntryError::Header))
+        ));
+    }
 }
@@ -46,6 +46,11 @@
 // non-ambigous representation of the position.
 //
 
+#[cfg(feature = "std")]
+use crate::logging::error;
+use crate::solver::Outcome;
+use alloc::{collections::BTreeSet, string::String, vec, vec::Vec};
+
 pub type Bitboard = u64;
 pub type Column = u8;
 ///
@@ -63,6 +68,11 @@ pub struct Position {
     mask: Bitboard,
     /// number of moves played since the beginning of the game.
     moves: u8,
+    /// the spots where the current player would make an alignment, including the
+    /// spots that are already played. Updated in `play` to avoid recomputing it.
+    current_threats: Bitboard,
+    /// the same as `current_threats`, but for the opponent.
+    opponent_threats: Bitboard,
 }
 /// Handle errors when playing a sequence of moves
 pub enum PlayResult {
@@ -72,21 +82,119 @@ pub enum PlayResult {
     Unplayable(Column),
     AlreadyWinning(Column),
 }
+/// How the game ends, see `Position::is_terminal_after`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum GameStatus {
+    /// The player who made the move won.
+    Won,
+    /// The board is full without any alignment.
+    Drawn,
+}
+
+/// How `Position::to_ascii_art` draws a position.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RenderOpts {
+    /// The stones of the player who started the game.
+    pub first: char,
+    /// The stones of the other player.
+    pub second: char,
+    /// The empty cells.
+    pub empty: char,
+    /// Draw a frame around the board, with a space between the cells.
+    pub border: bool,
+    /// Show the 1-based column numbers below the board.
+    pub labels: bool,
+}
+
+impl Default for RenderOpts {
+    /// The look of `Position::display_position`: the bare grid of cells.
+    fn default() -> Self {
+        Self {
+            first: 'o',
+            second: 'x',
+            empty: '.',
+            border: false,
+            labels: false,
+        }
+    }
+}
+/// Errors that can occur when parsing a position from a string of moves.
+#[derive(Debug, PartialEq, Eq)]
+pub enum PositionParseError {
+    /// The 1-based column is not on the board.
+    InvalidColumn(Column),
+    /// The 1-based column is already full.
+    Unplayable(Column),
+    /// Playing the 1-based column leads to an already won position.
+    AlreadyWinning(Column),
+    /// The character is not a column number.
+    InvalidChar(char),
+    /// A grid doesn't have `HEIGHT` rows of `WIDTH` cells.
+    GridSize,
+    /// The character is not a cell of a grid.
+    InvalidCell(char),
+    /// The 1-based column of a grid has a stone above an empty cell.
+    FloatingStone(Column),
+    /// The first player of a grid doesn't have as many stones as the second player, or one more.
+    StoneCount,
+    /// A grid already contains an alignment, so the game is over.
+    Alignment,
+    /// The stones of a grid can't be played in any order allowed by the rules.
+    Unreachable,
+}
+
+impl core::fmt::Display for PositionParseError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::InvalidColumn(col) => write!(
+                f,
+                "Column {col} should be between 1 and {}",
+                Position::WIDTH
+            ),
+            Self::Unplayable(col) => write!(f, "Column {col} is already full"),
+            Self::AlreadyWinning(col) => {
+                write!(f, "Playing column {col} leads to an already won position")
+            }
+            Self::InvalidChar(c) => write!(f, "Expected a column number, got {c:?}"),
+            Self::GridSize => write!(
+                f,
+                "The grid should have {} rows of {} cells",
+                Position::HEIGHT,
+                Position::WIDTH
+            ),
+            Self::InvalidCell(c) => write!(f, "Expected 'o', 'x', '.' or a digit, got {c:?}"),
+            Self::FloatingStone(col) => {
+                write!(f, "Column {col} has a stone above an empty cell")
+            }
+            Self::StoneCount => write!(
+                f,
+                "'o' should have as many stones as 'x', or one more, since 'o' starts"
+            ),
+            Self::Alignment => write!(f, "The grid already contains {} in a row", Position::WIN),
+            Self::Unreachable => write!(f, "The grid can't be reached in a game"),
+        }
+    }
+}
+
+impl core::error::Error for PositionParseError {}
+
 /// Handle the enum type, and print appropriate error messages
+/// (logged at the error level with the `log` feature).
+#[cfg(feature = "std")]
 #[must_use]
 #[allow(clippy::needless_pass_by_value)]
 pub fn play_result_ok(result: PlayResult) -> bool {
     match result {
         PlayResult::Ok => true,
         PlayResult::TooSmall => {
-            eprintln!(
+            error!(
                 "Input column was too small should be between 1 and {}",
                 Position::WIDTH
             );
             false
         }
         PlayResult::TooBig(col) => {
-            eprintln!(
+            error!(
                 "Input column ({}) was too big should be between 1 and {}",
                 col + 1,
                 Position::WIDTH
@@ -94,7 +202,7 @@ pub fn play_result_ok(result: PlayResult) -> bool {
             false
         }
         PlayResult::Unplayable(col) => {
-            eprintln!(
+            error!(
                 "Input column ({}) is already full (board height: {})",
                 col + 1,
                 Position::HEIGHT
@@ -102,26 +210,90 @@ pub fn play_result_ok(result: PlayResult) -> bool {
             false
         }
         PlayResult::AlreadyWinning(col) => {
-            eprintln!("Playing column {col} leads to an already won position");
+            error!("Playing column {col} leads to an already won position");
             false
         }
     }
 }
 
+impl Default for Position {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Positions are equal if they have the same stones on the board and the same player
+/// to move, no matter in which order the moves were played. This is the equality the
+/// solver cares about: transpositions are the same position. Use
+/// `GameRecord::same_game()` to also compare the order of the moves.
+impl PartialEq for Position {
+    fn eq(&self, other: &Self) -> bool {
+        self.current_position == other.current_position && self.mask == other.mask
+    }
+}
+
+impl Eq for Position {}
+
+// Checked at compile time, so changing the board size to one that doesn't fit fails
+// the build instead of silently mixing up the columns.
+const _: () = Position::assert_board_fits(Position::WIDTH, Position::HEIGHT);
+
 impl Position {
+    /// Panics if a board of `width` x `height` doesn't fit in a bitboard. Every column
+    /// takes `height + 1` bits, the extra row is what stops the lines found by shifting
+    /// from wrapping around, see `alignment_spots_of`.
+    ///
+    /// It is called in a constant to check `WIDTH` and `HEIGHT`, so a board size that
+    /// doesn't fit fails the build with the message of the panic:
+    /// ```compile_fail,E0080
+    /// use connect_4::position::Position;
+    /// // error: The board doesn't fit in a bitboard: WIDTH * (HEIGHT + 1) must be at most 64
+    /// const _: () = Position::assert_board_fits(8, 8);
+    /// ```
+    /// ```
+    /// use connect_4::position::Position;
+    /// const _: () = Position::assert_board_fits(8, 7);
+    /// ```
+    pub const fn assert_board_fits(width: Column, height: Column) {
+        assert!(
+            width as u32 * (height as u32 + 1) <= Bitboard::BITS,
+            "The board doesn't fit in a bitboard: WIDTH * (HEIGHT + 1) must be at most 64"
+        );
+    }
+
     /// Width of the board
     pub const WIDTH: Column = 7;
     /// Height of the board
     pub const HEIGHT: Column = 6;
+    /// The number of stones in a row needed to win.
+    pub const WIN: usize = 4;
     /// For width and height of 7x6 min score is -18
-    pub const MIN_SCORE: isize = -((Self::WIDTH * Self::HEIGHT) as isize) / 2 + 3;
+    pub const MIN_SCORE: isize =
+        -((Self::WIDTH * Self::HEIGHT) as isize) / 2 + Self::WIN as isize - 1;
     /// For width and height of 7x6 max score is 18
-    pub const MAX_SCORE: isize = ((Self::WIDTH * Self::HEIGHT + 1) as isize) / 2 - 3;
+    pub const MAX_SCORE: isize =
+        ((Self::WIDTH * Self::HEIGHT + 1) as isize) / 2 - (Self::WIN as isize - 1);
+    /// The maximal number of moves for which `key3()` fits in a `u64`,
+    /// i.e. the largest n such that 3^(n + WIDTH) < 2^64. This is 33 for 7 columns.
+    pub const KEY3_MAX_MOVES: u8 = Self::key3_max_moves(3u64.pow(Self::WIDTH as u32));
+    /// The version of the values of `key()` and `key3()`. It is stored in opening book
+    /// and tablebase files, which are only loaded if the version matches. This has to be
+    /// increased whenever a change to the bitboards changes the keys.
+    pub const KEY_FORMAT_VERSION: u32 = 1;
+    /// The number of values returned by `feature_planes()`.
+    pub const FEATURE_LEN: usize = 2 * (Self::WIDTH * Self::HEIGHT) as usize + 1;
     // Masks used for calculating possible moves.
     const BOTTOM_MASK: Bitboard = Self::bottom(Self::WIDTH, Self::HEIGHT);
     const BOARD_MASK: Bitboard = Self::BOTTOM_MASK * ((1u64 << Self::HEIGHT) - 1);
     const COLUMN_MASKS: [Bitboard; Self::WIDTH as usize] =
         Self::column_masks(0, [0; Self::WIDTH as usize]);
+    /// The shifts that move a bit to the next cell in the horizontal direction and
+    /// the two diagonal directions. Since each column has an extra bit on top, shifting
+    /// by `HEIGHT` goes one row down in the next column, and `HEIGHT + 2` one row up.
+    const LINE_SHIFTS: [Column; 3] = [Self::HEIGHT + 1, Self::HEIGHT, Self::HEIGHT + 2];
+    /// The cells in the rows 0, 2, 4, ... (counting from the bottom).
+    const EVEN_ROWS_MASK: Bitboard =
+        Self::BOTTOM_MASK * (0x5555_5555_5555_5555 & ((1 << Self::HEIGHT) - 1));
 }
 
 impl Position {
@@ -131,6 +303,10 @@ impl Position {
     ///       only one bit of the bitboard should be set to 1
     ///       the move should be a valid possible move for the current player
     pub fn play(&mut self, bmove: Bitboard) {
+        // Only the threats of the player making the move change.
+        let threats = self.threats_after(bmove);
+        self.current_threats = self.opponent_threats;
+        self.opponent_threats = threats;
         self.current_position ^= self.mask;
         self.mask |= bmove;
         self.moves += 1;
@@ -142,42 +318,252 @@ impl Position {
     /// You can check if the move sequence was valid by calling `play_result_ok()` on the
     /// returned value.
     pub fn play_sequence(&mut self, seq: &[Column]) -> PlayResult {
-        for col_1_based in seq {
+        self.play_sequence_partial(seq).1
+    }
+
+    /// Same as `play_sequence()`, but also returns the number of moves that were played.
+    /// If a move is invalid, the moves before it stay played, and the number of played
+    /// moves is the index of the invalid move in `seq`.
+    ///
+    /// Example:
+    /// ```
+    /// use connect_4::position::*;
+    /// let mut pos = Position::new();
+    /// let (played, result) = pos.play_sequence_partial(&[4, 4, 8, 3]);
+    /// assert_eq!(played, 2);
+    /// assert!(!play_result_ok(result));
+    /// assert_eq!(pos.nb_moves(), 2);
+    /// ```
+    pub fn play_sequence_partial(&mut self, seq: &[Column]) -> (usize, PlayResult) {
+        for (i, col_1_based) in seq.iter().enumerate() {
             if let Some(col) = col_1_based.checked_sub(1) {
                 if col >= Position::WIDTH {
-                    return PlayResult::TooBig(col);
+                    return (i, PlayResult::TooBig(col));
                 } else if !self.can_play(col) {
-                    return PlayResult::Unplayable(col);
+                    return (i, PlayResult::Unplayable(col));
                 } else if self.is_winning_move(col) {
-                    return PlayResult::AlreadyWinning(col); // invalid move
+                    return (i, PlayResult::AlreadyWinning(col)); // invalid move
                 }
                 self.play_col(col);
             } else {
-                return PlayResult::TooSmall; // invalid move
+                return (i, PlayResult::TooSmall); // invalid move
             }
         }
-        PlayResult::Ok
+        (seq.len(), PlayResult::Ok)
     }
 
     /// Create a position from a string of moves with no spaces in between
-    /// If something went wrong with parsing `None` is returned.
-    #[must_use]
-    pub fn from_string(position_str: &str) -> Option<Self> {
+    /// If one of the characters is not a digit, or one of the moves could not be played,
+    /// an `Err` is returned.
+    pub fn from_string(position_str: &str) -> Result<Self, PositionParseError> {
         let mut pos = Position::new();
         let seq = position_str
             .chars()
             .map(|m| {
-                m.to_digit(10).unwrap_or_else(|| {
-                    println!("Invalid char {m}, set to column 1 as default");
-                    1
-                }) as Column
+                m.to_digit(10)
+                    .map(|col| col as Column)
+                    .ok_or(PositionParseError::InvalidChar(m))
             })
-            .collect::<Vec<Column>>();
-        if play_result_ok(pos.play_sequence(&seq)) {
-            Some(pos)
+            .collect::<Result<Vec<Column>, _>>()?;
+        match pos.play_sequence(&seq) {
+            PlayResult::Ok => Ok(pos),
+            PlayResult::TooSmall => Err(PositionParseError::InvalidColumn(0)),
+            PlayResult::TooBig(col) => Err(PositionParseError::InvalidColumn(col + 1)),
+            PlayResult::Unplayable(col) => Err(PositionParseError::Unplayable(col + 1)),
+            PlayResult::AlreadyWinning(col) => Err(PositionParseError::AlreadyWinning(col + 1)),
+        }
+    }
+
+    /// Parse a position in the notation of Pascal Pons' solver and benchmark files, where
+    /// every character is a 1-based column, played in order from the starting position.
+    ///
+    /// Like the reference solver, the moves are checked one by one, and the first move
+    /// that isn't a column on the board, is in a full column or wins the game is an error.
+    /// `from_string()` first checks that every character is a digit, so for `"1111111x"`
+    /// it reports the `'x'`, while this reports the full column.
+    ///
+    /// Example:
+    /// ```
+    /// use connect_4::position::*;
+    /// assert_eq!(Position::parse_pons("4453").unwrap().nb_moves(), 4);
+    /// assert_eq!(Position::parse_pons("").unwrap().nb_moves(), 0);
+    /// assert_eq!(
+    ///     Position::parse_pons("1111111x").err(),
+    ///     Some(PositionParseError::Unplayable(1))
+    /// );
+    /// ```
+    pub fn parse_pons(s: &str) -> Result<Self, PositionParseError> {
+        let mut pos = Position::new();
+        for c in s.chars() {
+            let col_1_based = c.to_digit(10).ok_or(PositionParseError::InvalidChar(c))? as Column;
+            let Some(col) = col_1_based
+                .checked_sub(1)
+                .filter(|&col| col < Position::WIDTH)
+            else {
+                return Err(PositionParseError::InvalidColumn(col_1_based));
+            };
+            if !pos.can_play(col) {
+                return Err(PositionParseError::Unplayable(col_1_based));
+            } else if pos.is_winning_move(col) {
+                return Err(PositionParseError::AlreadyWinning(col_1_based));
+            }
+            pos.play_col(col);
+        }
+        Ok(pos)
+    }
+
+    /// Parse a grid of cells, like `display_position()` prints: the rows from the top down,
+    /// separated by newlines or `'/'`, with `'o'` for the stones of the first player, `'x'`
+    /// for the second player and `'.'` for empty cells. Like in FEN, a digit stands for that
+    /// many empty cells. Upper case letters are accepted as well.
+    ///
+    /// The grid has to come from a game that isn't over yet. Only the stones are known, not
+    /// the order in which they were played, see `GameRecord::from` to keep track of the moves.
+    ///
+    /// Example:
+    /// ```
+    /// use connect_4::position::*;
+    /// let pos = Position::from_grid_string("7/7/7/7/3x3/2oox2").unwrap();
+    /// assert_eq!(pos.key(), Position::from_string("4435").unwrap().key());
+    /// assert_eq!(
+    ///     Position::from_grid_string("7/7/7/7/7/xxo4").err(),
+    ///     Some(PositionParseError::StoneCount)
+    /// );
+    /// ```
+    pub fn from_grid_string(grid: &str) -> Result<Self, PositionParseError> {
+        let rows = grid
+            .trim()
+            .trim_end_matches('/')
+            .split(['/', '\n'])
+            .map(str::trim)
+            .collect::<Vec<_>>();
+        if rows.len() != Self::HEIGHT as usize {
+            return Err(PositionParseError::GridSize);
+        }
+        // The stones of the first and the second player.
+        let (mut first, mut second) = (0, 0);
+        for (i, row) in rows.into_iter().enumerate() {
+            let row_index = Self::HEIGHT - 1 - i as Column;
+            let mut col = 0;
+            for c in row.chars() {
+                let cells = match c.to_ascii_lowercase() {
+                    '.' | 'o' | 'x' => 1,
+                    '1'..='9' => c.to_digit(10).unwrap() as Column,
+                    _ => return Err(PositionParseError::InvalidCell(c)),
+                };
+                if col + cells > Self::WIDTH {
+                    return Err(PositionParseError::GridSize);
+                }
+                let cell = Self::bottom_mask_col(col) << row_index;
+                match c.to_ascii_lowercase() {
+                    'o' => first |= cell,
+                    'x' => second |= cell,
+                    _ => {}
+                }
+                col += cells;
+            }
+            if col != Self::WIDTH {
+                return Err(PositionParseError::GridSize);
+            }
+        }
+        let mask = first | second;
+        for col in 0..Self::WIDTH {
+            let stones = (mask & Self::column_mask(col)) >> (col * (Self::HEIGHT + 1));
+            if stones & (stones + 1) != 0 {
+                return Err(PositionParseError::FloatingStone(col + 1));
+            }
+        }
+        if !(0..=1).contains(&(first.count_ones() as isize - second.count_ones() as isize)) {
+            return Err(PositionParseError::StoneCount);
+        }
+        if Self::has_alignment(first) || Self::has_alignment(second) {
+            return Err(PositionParseError::Alignment);
+        }
+        Self::from_stones(first, second).ok_or(PositionParseError::Unreachable)
+    }
+
+    /// Get the position with the stones of the two players swapped, so the same board is
+    /// seen from the other side, e.g. to set up a puzzle or to test that an evaluation is
+    /// symmetric. The player to move gets the stones of the opponent.
+    ///
+    /// Returns `None` after an odd number of moves: the second player is to move and has
+    /// one stone less, so after swapping the player to move would have one stone more,
+    /// which can't happen in a game. Also returns `None` if no order of the moves leads to
+    /// the swapped stones. Like for `from_grid_string()`, only the stones are known, not
+    /// the order in which they were played.
+    ///
+    /// Example:
+    /// ```
+    /// use connect_4::position::*;
+    /// let pos = Position::from_string("12").unwrap();
+    /// let swapped = pos.swap_to_move().unwrap();
+    /// assert_eq!(swapped.key(), Position::from_string("21").unwrap().key());
+    /// // The second player's stone would have to be played first.
+    /// assert!(Position::from_string("44").unwrap().swap_to_move().is_none());
+    /// assert!(Position::from_string("4").unwrap().swap_to_move().is_none());
+    /// ```
+    #[must_use]
+    pub fn swap_to_move(&self) -> Option<Position> {
+        if !self.moves.is_multiple_of(2) {
+            return None;
+        }
+        // The first player is to move, so their stones are `current_position`.
+        let first = self.current_position ^ self.mask;
+        let second = self.current_position;
+        Self::from_stones(first, second)
+    }
+
+    /// The position with the stones `first` of the first player and `second` of the second
+    /// player, which have to be stacked, without an alignment, and the first player has
+    /// as many stones as the second or one more. Returns `None` if no game reaches it.
+    fn from_stones(first: Bitboard, second: Bitboard) -> Option<Position> {
+        let mask = first | second;
+        let moves = mask.count_ones() as u8;
+        let current_position = if moves.is_multiple_of(2) {
+            first
         } else {
-            None
+            second
+        };
+        Self::reachable(0, first, second, &mut BTreeSet::new()).then_some(Position {
+            current_position,
+            mask,
+            moves,
+            current_threats: Self::alignment_spots(current_position),
+            opponent_threats: Self::alignment_spots(current_position ^ mask),
+        })
+    }
+
+    /// Returns true if the stones `first` of the first player and `second` of the second
+    /// player can be played, in some order allowed by the rules, after the stones in
+    /// `mask`. `dead` holds the masks from which they can't, so each of them is only
+    /// tried once.
+    ///
+    /// Since the stones contain no alignment, none of the moves wins.
+    fn reachable(
+        mask: Bitboard,
+        first: Bitboard,
+        second: Bitboard,
+        dead: &mut BTreeSet<Bitboard>,
+    ) -> bool {
+        if mask == first | second {
+            return true;
+        }
+        if dead.contains(&mask) {
+            return false;
         }
+        let mine = if mask.count_ones().is_multiple_of(2) {
+            first
+        } else {
+            second
+        };
+        for col in 0..Self::WIDTH {
+            let next = (mask + Self::bottom_mask_col(col)) & Self::column_mask(col);
+            if next & mine != 0 && Self::reachable(mask | next, first, second, dead) {
+                return true;
+            }
+        }
+        dead.insert(mask);
+        false
     }
 
     /// return true if the current player can win next move.
@@ -186,12 +572,61 @@ impl Position {
         (self.winning_position() & self.possible()) != 0
     }
 
+    /// Check that the internal representation describes a position that can be reached
+    /// in a game: the stones of the current player are on the board, every stone lies
+    /// on top of another one, the number of stones is the number of moves, nobody made
+    /// an alignment, and the cached threats are up to date.
+    ///
+    /// Every position created with the public API should be valid, this is meant for
+    /// testing, e.g. by fuzzing the parsers.
+    #[must_use]
+    pub fn is_valid(&self) -> bool {
+        let columns_stacked = (0..Self::WIDTH).all(|col| {
+            let column = (self.mask & Self::column_mask(col)) >> (col * (Self::HEIGHT + 1));
+            column & (column + 1) == 0
+        });
+        self.current_position & !self.mask == 0
+            && self.mask & !Self::BOARD_MASK == 0
+            && columns_stacked
+            && u32::from(self.moves) == self.mask.count_ones()
+            && !Self::has_alignment(self.current_position)
+            && !Self::has_alignment(self.current_position ^ self.mask)
+            && self.current_threats == Self::alignment_spots(self.current_position)
+            && self.opponent_threats == Self::alignment_spots(self.current_position ^ self.mask)
+    }
+
     /// return the number of moves played since the beginning of the game.
     #[must_use]
     pub fn nb_moves(&self) -> u8 {
         self.moves
     }
 
+    /// The number of stones on the board, which is the same as `nb_moves()`.
+    #[must_use]
+    pub fn num_stones(&self) -> u8 {
+        Self::popcount(self.mask)
+    }
+
+    /// The number of stones of the first player ('o') and of the second player ('x').
+    ///
+    /// Unlike the bitboards, which store the stones of the player to move, this doesn't
+    /// depend on whose turn it is:
+    /// ```
+    /// use connect_4::position::*;
+    /// let pos = Position::from_string("445").unwrap();
+    /// assert_eq!(pos.stones_per_player(), (2, 1));
+    /// ```
+    #[must_use]
+    pub fn stones_per_player(&self) -> (u8, u8) {
+        let current = Self::popcount(self.current_position);
+        let opponent = Self::popcount(self.current_position ^ self.mask);
+        if self.moves.is_multiple_of(2) {
+            (current, opponent)
+        } else {
+            (opponent, current)
+        }
+    }
+
     /// returns a compact representation of a position on WIDTH*(HEIGHT+1) bits.
     #[must_use]
     pub fn key(&self) -> Bitboard {
@@ -243,6 +678,14 @@ impl Position {
         (self.current_position & Self::mirror(self.current_position ^ self.mask)) == 0
     }
 
+    /// Check if the two positions are the same, or if one is the mirror image of the other.
+    /// This is the symmetry relation used by the opening book and the solver.
+    #[must_use]
+    pub fn equivalent_under_symmetry(&self, other: &Position) -> bool {
+        // Don't use `key3()`, which is only valid for positions with few moves.
+        self.key() == other.key() || self.key() == other.mirrored_key()
+    }
+
     /// Build a symmetric base 3 key. Two symmetric positions will have the same key.
     ///
     /// This key is a base 3 representation of the sequence of played moves column per column,
@@ -256,8 +699,18 @@ impl Position {
     ///
     /// as the last digit is always 0, we omit it and a base 3 key
     /// uses N = (nbMoves + nbColums - 1) base 3 digits or N*log2(3) bits.
+    ///
+    /// WARNING: the key only fits in a `u64` if at most `KEY3_MAX_MOVES` moves were played.
     #[must_use]
     pub fn key3(&self) -> u64 {
+        let (key_forward, key_reverse) = self.key3_pair();
+        // take the smallest key and divide per 3 as the last base3 digit is always 0
+        (key_forward.min(key_reverse) / 3) as u64
+    }
+
+    /// Compute the base 3 keys iterating the columns from left to right and from
+    /// right to left, see `key3()`. A `u128` can hold the keys of all positions.
+    fn key3_pair(&self) -> (u128, u128) {
         let mut key_forward = 0;
         for i in 0..Position::WIDTH {
             // compute key in increasing order of columns
@@ -268,12 +721,34 @@ impl Position {
         for i in (0..Position::WIDTH).rev() {
             self.partial_key3(&mut key_reverse, i);
         }
-        // take the smallest key and divide per 3 as the last base3 digit is always 0
-        if key_forward < key_reverse {
-            key_forward / 3
-        } else {
-            key_reverse / 3
+        (key_forward, key_reverse)
+    }
+
+    /// Mirror the position if the mirror image has the smaller base 3 key, so that
+    /// two positions that are each other's mirror image become the same. This is the
+    /// orientation whose key `key3()` uses, so `key3()` doesn't change. Returns
+    /// whether the position was mirrored, which isn't done for symmetric positions.
+    ///
+    /// Unlike `key3()`, this works for any number of moves.
+    ///
+    /// Example:
+    /// ```
+    /// use connect_4::position::*;
+    /// let mut pos = Position::from_string("13").unwrap();
+    /// assert!(pos.canonicalize());
+    /// assert!(pos == Position::from_string("75").unwrap());
+    /// assert!(!pos.canonicalize());
+    /// ```
+    pub fn canonicalize(&mut self) -> bool {
+        let (key_forward, key_reverse) = self.key3_pair();
+        if key_forward <= key_reverse {
+            return false;
         }
+        self.current_position = Self::mirror(self.current_position);
+        self.mask = Self::mirror(self.mask);
+        self.current_threats = Self::alignment_spots(self.current_position);
+        self.opponent_threats = Self::alignment_spots(self.current_position ^ self.mask);
+        true
     }
 
     /// Return a bitboard of all the possible next moves the do not lose in one turn.
@@ -285,6 +760,11 @@ impl Position {
     #[must_use]
     pub fn possible_non_losing_moves(&self) -> Bitboard {
         debug_assert!(!self.can_win_next());
+        self.non_losing_moves()
+    }
+
+    /// `possible_non_losing_moves()` without the check that we can't win directly.
+    fn non_losing_moves(&self) -> Bitboard {
         let possible_mask = self.possible();
         let opponent_win = self.opponent_winning_position();
         let forced_moves = possible_mask & opponent_win;
@@ -306,10 +786,73 @@ impl Position {
     /// the current player has after playing the move.
     #[must_use]
     pub fn move_score(&self, bmove: Bitboard) -> u8 {
-        Self::popcount(Self::compute_winning_position(
-            self.current_position | bmove,
-            self.mask,
-        ))
+        Self::popcount(self.threats_after(bmove) & (Self::BOARD_MASK ^ self.mask))
+    }
+
+    /// The spots where the current player would make an alignment after playing `bmove`,
+    /// whether they are free or not. `play` stores them as the threats of the player who
+    /// moved, so they are only computed once per move.
+    #[must_use]
+    fn threats_after(&self, bmove: Bitboard) -> Bitboard {
+        Self::alignment_spots(self.current_position | bmove)
+    }
+
+    /// The number of spots where the current player can make an alignment,
+    /// minus the number of such spots for the opponent.
+    ///
+    /// This is a cheap heuristic of who has the better position.
+    #[must_use]
+    pub fn threat_difference(&self) -> isize {
+        isize::from(Self::popcount(self.winning_position()))
+            - isize::from(Self::popcount(self.opponent_winning_position()))
+    }
+
+    /// Count the threats of the current player and of the opponent in each row, where
+    /// row 0 is the bottom row. A threat is a free cell where the player would make an
+    /// alignment, whether it can be played right away or not.
+    ///
+    /// In the endgame, whether the threats are on odd or even rows often decides the game.
+    /// With rows counted from 1 like in the literature, the first player wants threats on
+    /// odd rows (even values of `row` here) and the second player on even rows.
+    #[must_use]
+    pub fn threat_row_counts(&self) -> ([u8; Self::HEIGHT as usize], [u8; Self::HEIGHT as usize]) {
+        let count = |threats: Bitboard| {
+            core::array::from_fn(|row| Self::popcount(threats & (Self::BOTTOM_MASK << row)))
+        };
+        (
+            count(self.winning_position()),
+            count(self.opponent_winning_position()),
+        )
+    }
+
+    /// Encode the position as input for a neural network: two planes of `HEIGHT` rows
+    /// of `WIDTH` cells, followed by a turn indicator, for a total of `FEATURE_LEN` values.
+    ///
+    /// The first plane has a 1.0 for each stone of the current player, the second plane
+    /// for each stone of the opponent. Rows go from the bottom to the top, so the value
+    /// of the cell in row `row` and column `col` of plane `p` is at index
+    /// `p * WIDTH * HEIGHT + row * WIDTH + col`. The turn indicator is 1.0 if the
+    /// first player is to move, and 0.0 otherwise.
+    #[must_use]
+    pub fn feature_planes(&self) -> Vec<f32> {
+        let cells = (Self::WIDTH * Self::HEIGHT) as usize;
+        let mut features = vec![0.0; Self::FEATURE_LEN];
+        let opponent_position = self.current_position ^ self.mask;
+        for row in 0..Self::HEIGHT {
+            for col in 0..Self::WIDTH {
+                let cell = 1 << (row + col * (Self::HEIGHT + 1));
+                let index = (row * Self::WIDTH + col) as usize;
+                if self.current_position & cell != 0 {
+                    features[index] = 1.0;
+                } else if opponent_position & cell != 0 {
+                    features[cells + index] = 1.0;
+                }
+            }
+        }
+        if self.moves.is_multiple_of(2) {
+            features[2 * cells] = 1.0;
+        }
+        features
     }
 
     /// Default constructor, build an empty position.
@@ -319,6 +862,8 @@ impl Position {
             current_position: 0,
             mask: 0,
             moves: 0,
+            current_threats: 0,
+            opponent_threats: 0,
         }
     }
 
@@ -328,6 +873,26 @@ impl Position {
         ((Position::WIDTH * Position::HEIGHT) as isize + addend - self.nb_moves() as isize) / 2
     }
 
+    /// The lowest and highest score the current position can have, as `(min, max)`.
+    ///
+    /// The lowest score is a loss to the next stone of the opponent, the fastest way to
+    /// lose, and the highest is a win with the next stone of the current player, the
+    /// fastest way to win. Every solved score of the position is within these bounds, so
+    /// they are the widest useful search window.
+    ///
+    /// ```
+    /// use connect_4::position::*;
+    ///
+    /// assert_eq!(Position::new().score_bounds(), (-21, 21));
+    /// let pos = Position::from_string("445").unwrap();
+    /// assert_eq!(pos.score_bounds(), (-19, 20));
+    /// ```
+    #[inline]
+    #[must_use]
+    pub fn score_bounds(&self) -> (isize, isize) {
+        (-self.num_stones_left(0), self.num_stones_left(1))
+    }
+
     /// Indicates whether a column is playable.
     /// `col` is a 0-based index of the column to play
     /// returns `true` if the column is playable, `false` if the column is already full.
@@ -336,6 +901,60 @@ impl Position {
         (self.mask & Self::top_mask_col(col)) == 0
     }
 
+    /// Get the 0-based row (counted from the bottom) where a stone played in `col`
+    /// would land, or `None` if the column is full. Useful for animating a move
+    /// before it is played.
+    #[must_use]
+    pub fn drop_row(&self, col: Column) -> Option<Column> {
+        if self.can_play(col) {
+            Some((self.mask & Self::column_mask(col)).count_ones() as Column)
+        } else {
+            None
+        }
+    }
+
+    /// Indicates for every column whether it is playable, see `can_play()`.
+    /// Useful for user interfaces, which want to disable the full columns.
+    #[must_use]
+    pub fn playable_mask(&self) -> [bool; Self::WIDTH as usize] {
+        core::array::from_fn(|col| self.can_play(col as Column))
+    }
+
+    /// Indicates for every column whether playing it doesn't lose directly, i.e. whether it
+    /// wins directly or doesn't allow the opponent to win in one move. If every move loses
+    /// directly, all the values are `false`.
+    ///
+    /// Unlike `possible_non_losing_moves()`, this can be used when the current player can win.
+    #[must_use]
+    pub fn non_losing_mask(&self) -> [bool; Self::WIDTH as usize] {
+        let possible = self.non_losing_moves();
+        core::array::from_fn(|col| {
+            let col = col as Column;
+            self.can_play(col)
+                && (self.is_winning_move(col) || possible & Self::column_mask(col) != 0)
+        })
+    }
+
+    /// The columns where the current player creates a double threat: after the move there are
+    /// two or more columns in which the current player would make an alignment right away.
+    /// The opponent can only block one of them, so these moves win, unless the opponent can
+    /// win first. Moves that let the opponent win directly are not included.
+    ///
+    /// `col` values are 0-based indices, in increasing order.
+    pub fn double_threat_moves(&self) -> impl Iterator<Item = Column> + '_ {
+        let moves = self.non_losing_moves() & !self.winning_position();
+        (0..Self::WIDTH).filter(move |&col| {
+            let bmove = moves & Self::column_mask(col);
+            if bmove == 0 {
+                return false;
+            }
+            let mask = self.mask | bmove;
+            let possible = (mask + Self::BOTTOM_MASK) & Self::BOARD_MASK;
+            let threats = Self::compute_winning_position(self.current_position | bmove, mask);
+            Self::popcount(threats & possible) >= 2
+        })
+    }
+
     /// Plays a playable column.
     /// This function should not be called on a non-playable column or a column making an alignment.
     ///
@@ -344,6 +963,21 @@ impl Position {
         self.play((self.mask + Self::bottom_mask_col(col)) & Self::column_mask(col));
     }
 
+    /// Get the position after playing `col`, without changing this position.
+    /// Returns `None` if the column is full, or if the move makes an alignment,
+    /// since positions with an alignment are not supported.
+    ///
+    /// `col` is a 0-based index of a column.
+    #[must_use]
+    pub fn with_move(&self, col: Column) -> Option<Position> {
+        if !self.can_play(col) || self.is_winning_move(col) {
+            return None;
+        }
+        let mut next = self.clone();
+        next.play_col(col);
+        Some(next)
+    }
+
     /// Indicates whether the current player wins by playing a given column.
     /// This function should never be called on a non-playable column.
     /// `col` is a 0-based index of a playable column.
@@ -353,7 +987,173 @@ impl Position {
         (self.winning_position() & self.possible() & Self::column_mask(col)) != 0
     }
 
+    /// Try to find the outcome of the game without searching, using the "claimeven" rule
+    /// from Victor Allis' thesis. Returns `None` if this is inconclusive, which is the case
+    /// for most positions. A returned outcome is always correct, and a full board is a draw.
+    ///
+    /// If every column has an even number of empty cells, the opponent can answer every
+    /// move by playing on top of it. Then the current player gets all the empty cells in
+    /// the rows 0, 2, 4, ... and the opponent all the empty cells in the rows 1, 3, 5, ...
+    /// If the current player can't make an alignment with these cells, but the opponent
+    /// can, the current player loses.
+    #[must_use]
+    pub fn strategic_hint(&self) -> Option<Outcome> {
+        if self.nb_moves() == Self::WIDTH * Self::HEIGHT {
+            return Some(Outcome::Draw);
+        }
+        if self.can_win_next() {
+            return Some(Outcome::Win);
+        }
+        if self.possible_non_losing_moves() == 0 {
+            return Some(Outcome::Loss);
+        }
+        if self.possible() & !Self::EVEN_ROWS_MASK != 0 {
+            // Some column has an odd number of empty cells.
+            return None;
+        }
+        let empty = Self::BOARD_MASK & !self.mask;
+        let current = self.current_position | (empty & Self::EVEN_ROWS_MASK);
+        let opponent = (self.current_position ^ self.mask) | (empty & !Self::EVEN_ROWS_MASK);
+        if !Self::has_alignment(current) && Self::has_alignment(opponent) {
+            Some(Outcome::Loss)
+        } else {
+            None
+        }
+    }
+
+    /// Check whether the stones in `position` contain an alignment of `WIN` stones.
+    fn has_alignment(position: Bitboard) -> bool {
+        Self::has_alignment_of::<{ Self::WIN }>(position)
+    }
+
+    /// Check whether the stones in `position` contain an alignment of `WIN` stones.
+    fn has_alignment_of<const WIN: usize>(position: Bitboard) -> bool {
+        [1, Self::HEIGHT + 1, Self::HEIGHT, Self::HEIGHT + 2]
+            .into_iter()
+            .any(|shift| {
+                // The spots that start a line of `i` stones, for increasing `i`.
+                (1..WIN).fold(position, |m, i| m & (position >> (i * shift as usize))) != 0
+            })
+    }
+
+    /// Check whether playing the 0-based column `col` ends the game, without playing it.
+    /// Returns `None` if the game continues after the move.
+    /// This function should never be called on a non-playable column.
+    #[must_use]
+    pub fn is_terminal_after(&self, col: Column) -> Option<GameStatus> {
+        debug_assert!(self.can_play(col));
+        if self.is_winning_move(col) {
+            Some(GameStatus::Won)
+        } else if self.moves + 1 == Self::WIDTH * Self::HEIGHT {
+            Some(GameStatus::Drawn)
+        } else {
+            None
+        }
+    }
+
+    /// Count the move sequences of length `depth` from this position, a standard check of
+    /// the move generation. A winning move ends the game, so the sequences that continue
+    /// after it aren't counted.
+    ///
+    /// Example:
+    /// ```
+    /// use connect_4::position::*;
+    /// assert_eq!(Position::new().perft(3), 7 * 7 * 7);
+    /// ```
+    #[must_use]
+    pub fn perft(&self, depth: usize) -> u64 {
+        if depth == 0 {
+            return 1;
+        }
+        (0..Self::WIDTH)
+            .filter(|&col| self.can_play(col))
+            .map(|col| self.perft_after(col, depth))
+            .sum()
+    }
+
+    /// The counts of `perft()` for each playable column, as `(column, count)`.
+    /// When `perft()` disagrees with a reference, this shows which first move is wrong.
+    /// Returns an empty `Vec` if `depth` is 0.
+    #[must_use]
+    pub fn perft_divide(&self, depth: usize) -> Vec<(Column, u64)> {
+        if depth == 0 {
+            return Vec::new();
+        }
+        (0..Self::WIDTH)
+            .filter(|&col| self.can_play(col))
+            .map(|col| (col, self.perft_after(col, depth)))
+            .collect()
+    }
+
+    /// `perft()` of the sequences starting with the playable column `col`.
+    fn perft_after(&self, col: Column, depth: usize) -> u64 {
+        if self.is_winning_move(col) {
+            return u64::from(depth == 1);
+        }
+        let mut pos = self.clone();
+        pos.play_col(col);
+        pos.perft(depth - 1)
+    }
+
+    /// Enumerate all the positions with exactly `ply` moves that can be reached without
+    /// an alignment, depth first. A position and its mirror image are only generated once,
+    /// since they have the same `key3()`, as are positions reached by different orders of
+    /// the same moves.
+    ///
+    /// The positions are generated lazily, and nothing is kept besides the positions that
+    /// are left to visit: each position is only visited from its first parent, see
+    /// `is_first_parent()`, and only the mirror image with the smaller base 3 key is
+    /// generated.
+    ///
+    /// Panics if `ply` is larger than `KEY3_MAX_MOVES`.
+    pub fn all_positions_at_ply(ply: u8) -> impl Iterator<Item = Position> {
+        assert!(
+            ply <= Self::KEY3_MAX_MOVES,
+            "Can only enumerate positions with at most {} moves",
+            Self::KEY3_MAX_MOVES
+        );
+        let mut stack = vec![Position::new()];
+        core::iter::from_fn(move || {
+            while let Some(pos) = stack.pop() {
+                if pos.nb_moves() == ply {
+                    let (key_forward, key_reverse) = pos.key3_pair();
+                    if key_forward <= key_reverse {
+                        return Some(pos);
+                    }
+                    continue;
+                }
+                // Push the children in reverse, so they are visited from left to right.
+                stack.extend((0..Self::WIDTH).rev().filter_map(|col| {
+                    pos.with_move(col)
+                        .filter(|child| Self::is_first_parent(child, col))
+                }));
+            }
+            None
+        })
+    }
+
+    /// Returns true if `col` is the leftmost column of `child` whose top stone could have
+    /// been played last, that is the stone is the last player's and a game reaches the
+    /// position without it. The parent without that stone is the first parent of `child`.
+    fn is_first_parent(child: &Position, col: Column) -> bool {
+        let last = child.current_position ^ child.mask;
+        (0..col).all(|c| {
+            let stones = child.mask & Self::column_mask(c);
+            let top = stones & !(stones >> 1);
+            if top & last == 0 {
+                return true;
+            }
+            let (first, second) = if child.nb_moves().is_multiple_of(2) {
+                (child.current_position, last ^ top)
+            } else {
+                (last ^ top, child.current_position)
+            };
+            !Self::reachable(0, first, second, &mut BTreeSet::new())
+        })
+    }
+
     /// Displays the bitboard, usefull for debugging
+    #[cfg(feature = "std")]
     pub fn display_bitboard(bb: Bitboard) {
         for col in (0..Self::HEIGHT).rev() {
             for row in 0..(Self::WIDTH) {
@@ -378,32 +1178,80 @@ impl Position {
     }
 
     /// Prints the current position to `std_out()`.
+    #[cfg(feature = "std")]
     pub fn display_position(&self) {
-        let (us, them) = match self.moves % 2 {
-            0 => ("x", "o"),
-            _ => ("o", "x"),
+        print!("{}", self.to_ascii_art(RenderOpts::default()));
+    }
+
+    /// Draws the board, one line per row from the top, each ending in a newline.
+    /// See `RenderOpts` for the options.
+    /// ```
+    /// use connect_4::position::{Position, RenderOpts};
+    /// let pos = Position::from_string("4435").unwrap();
+    /// let opts = RenderOpts { border: true, labels: true, ..RenderOpts::default() };
+    /// assert!(pos.to_ascii_art(opts).ends_with(
+    ///     "| . . . x . . . |\n| . . o o x . . |\n+---------------+\n  1 2 3 4 5 6 7\n"
+    /// ));
+    /// ```
+    #[must_use]
+    pub fn to_ascii_art(&self, opts: RenderOpts) -> String {
+        let mut art = String::new();
+        let edge = |art: &mut String| {
+            art.push('+');
+            art.extend(core::iter::repeat_n('-', 2 * Self::WIDTH as usize + 1));
+            art.push_str("+\n");
         };
-        for col in (0..Self::HEIGHT).rev() {
-            for row in 0..(Self::WIDTH) {
-                if (self.mask & (1u64 << (col + row * (Self::HEIGHT + 1)))) == 0 {
-                    print!(".");
+        if opts.border {
+            edge(&mut art);
+        }
+        // The stones of the player to move are in `current_position`.
+        let (mine, theirs) = if self.moves.is_multiple_of(2) {
+            (opts.first, opts.second)
+        } else {
+            (opts.second, opts.first)
+        };
+        for row in (0..Self::HEIGHT).rev() {
+            if opts.border {
+                art.push('|');
+            }
+            for col in 0..Self::WIDTH {
+                let cell = 1 << (row + col * (Self::HEIGHT + 1));
+                if opts.border {
+                    art.push(' ');
+                }
+                art.push(if self.mask & cell == 0 {
+                    opts.empty
+                } else if self.current_position & cell != 0 {
+                    mine
                 } else {
-                    match self.current_position & (1u64 << (col + row * (Self::HEIGHT + 1))) {
-                        0 => {
-                            print!("{us}");
-                        }
-                        _ => {
-                            print!("{them}");
-                        }
-                    }
+                    theirs
+                });
+            }
+            if opts.border {
+                art.push_str(" |");
+            }
+            art.push('\n');
+        }
+        if opts.border {
+            edge(&mut art);
+        }
+        if opts.labels {
+            if opts.border {
+                art.push(' ');
+            }
+            for col in 1..=Self::WIDTH {
+                if opts.border {
+                    art.push(' ');
                 }
+                art.push(char::from_digit(u32::from(col % 10), 10).unwrap());
             }
-            println!();
+            art.push('\n');
         }
+        art
     }
 
     /// Compute a partial base 3 key for a given column
-    fn partial_key3(&self, key: &mut u64, col: Column) {
+    fn partial_key3(&self, key: &mut u128, col: Column) {
         let mut pos = 1 << (col * (Position::HEIGHT + 1));
         while (pos & self.mask) != 0 {
             *key *= 3;
@@ -420,19 +1268,19 @@ impl Position {
     /// Return a bitboard of the possible winning positions for the current player
     #[must_use]
     fn winning_position(&self) -> Bitboard {
-        Self::compute_winning_position(self.current_position, self.mask)
+        self.current_threats & (Self::BOARD_MASK ^ self.mask)
     }
 
     /// Return a bitboard of the possible winning positions for the opponent
     #[must_use]
     fn opponent_winning_position(&self) -> Bitboard {
-        Self::compute_winning_position(self.current_position ^ self.mask, self.mask)
+        self.opponent_threats & (Self::BOARD_MASK ^ self.mask)
     }
 
     /// Bitboard of the next possible valid moves for the current player
     /// including losing moves.
     #[must_use]
-    fn possible(&self) -> Bitboard {
+    pub(crate) fn possible(&self) -> Bitboard {
         (self.mask + Self::BOTTOM_MASK) & Self::BOARD_MASK
     }
 
@@ -451,34 +1299,46 @@ impl Position {
     /// position `position`. The `mask` has the bits set where a spot was already played.
     #[must_use]
     fn compute_winning_position(position: Bitboard, mask: Bitboard) -> Bitboard {
-        // vertical;
-        let mut r = (position << 1) & (position << 2) & (position << 3);
+        Self::alignment_spots(position) & (Self::BOARD_MASK ^ mask)
+    }
 
-        //horizontal
-        let mut p = (position << (Self::HEIGHT + 1)) & (position << (2 * (Self::HEIGHT + 1)));
-        r |= p & (position << (3 * (Self::HEIGHT + 1)));
-        r |= p & (position >> (Self::HEIGHT + 1));
-        p = (position >> (Self::HEIGHT + 1)) & (position >> (2 * (Self::HEIGHT + 1)));
-        r |= p & (position << (Self::HEIGHT + 1));
-        r |= p & (position >> (3 * (Self::HEIGHT + 1)));
+    /// Returns a bitboard of all the spots on the board making an alignment with the
+    /// stones in `position`, whether they are free or not.
+    #[must_use]
+    fn alignment_spots(position: Bitboard) -> Bitboard {
+        Self::alignment_spots_of::<{ Self::WIN }>(position)
+    }
 
-        //diagonal 1
-        p = (position << Self::HEIGHT) & (position << (2 * Self::HEIGHT));
-        r |= p & (position << (3 * Self::HEIGHT));
-        r |= p & (position >> Self::HEIGHT);
-        p = (position >> Self::HEIGHT) & (position >> (2 * Self::HEIGHT));
-        r |= p & (position << Self::HEIGHT);
-        r |= p & (position >> (3 * Self::HEIGHT));
+    /// `alignment_spots()` for alignments of `WIN` stones.
+    ///
+    /// The shifts don't stop at the edges of a column: a line that leaves the board through
+    /// the top or the bottom of a column continues in the next column. It always crosses
+    /// the extra row above each column first, which never has stones, so such a line is
+    /// only found if its spot is in that extra row, and `BOARD_MASK` removes those. Past
+    /// the right edge of the board are bits without stones, and past the left edge the
+    /// bits are shifted out.
+    #[must_use]
+    fn alignment_spots_of<const WIN: usize>(position: Bitboard) -> Bitboard {
+        // vertical: the only free spot is on top of the stones.
+        let mut r = (1..WIN).fold(!0, |r, i| r & (position << i));
 
-        //diagonal 2
-        p = (position << (Self::HEIGHT + 2)) & (position << (2 * (Self::HEIGHT + 2)));
-        r |= p & (position << (3 * (Self::HEIGHT + 2)));
-        r |= p & (position >> (Self::HEIGHT + 2));
-        p = (position >> (Self::HEIGHT + 2)) & (position >> (2 * (Self::HEIGHT + 2)));
-        r |= p & (position << (Self::HEIGHT + 2));
-        r |= p & (position >> (3 * (Self::HEIGHT + 2)));
+        // horizontal, diagonal 1 and diagonal 2. The shifts and `WIN` are constants,
+        // so the loops get unrolled by the compiler.
+        for shift in Self::LINE_SHIFTS {
+            let shift = shift as usize;
+            // The spot is the `k`-th of the `WIN` spots of a line, the others have stones.
+            for k in 0..WIN {
+                r |= (0..WIN).filter(|&j| j != k).fold(!0, |p, j| {
+                    if j < k {
+                        p & (position << ((k - j) * shift))
+                    } else {
+                        p & (position >> ((j - k) * shift))
+                    }
+                });
+            }
+        }
 
-        r & (Self::BOARD_MASK ^ mask)
+        r & Self::BOARD_MASK
     }
 
     #[must_use]
@@ -503,6 +1363,15 @@ impl Position {
         }
     }
 
+    /// The number of times `power` can be multiplied by 3 without overflowing a `u64`.
+    #[must_use]
+    const fn key3_max_moves(power: u64) -> u8 {
+        match power.checked_mul(3) {
+            Some(next) => Self::key3_max_moves(next) + 1,
+            None => 0,
+        }
+    }
+
     // return a bitboard containg a single 1 corresponding to the top cel of a given column
     #[must_use]
     fn top_mask_col(col: Column) -> Bitboard {
@@ -526,7 +1395,12 @@ impl Position {
 mod tests {
     use crate::position;
 
-    use super::{play_result_ok, Position};
+    use super::{
+        play_result_ok, Bitboard, Column, GameStatus, PlayResult, Position, PositionParseError,
+        RenderOpts,
+    };
+    use crate::solver::Outcome;
+    use alloc::collections::BTreeSet;
     #[test]
     fn simple_moves() {
         let mut pos = Position::new();
@@ -565,6 +1439,796 @@ mod tests {
         assert_eq!(0u64, pos.possible_non_losing_moves());
     }
 
+    #[test]
+    fn from_string_errors() {
+        assert_eq!(Position::from_string("4455").unwrap().nb_moves(), 4);
+        assert_eq!(
+            Position::from_string("48").err(),
+            Some(PositionParseError::InvalidColumn(8))
+        );
+        assert_eq!(
+            Position::from_string("1111111").err(),
+            Some(PositionParseError::Unplayable(1))
+        );
+        assert_eq!(
+            Position::from_string("1212121").err(),
+            Some(PositionParseError::AlreadyWinning(1))
+        );
+        // Invalid characters used to be played as column 1.
+        assert_eq!(
+            Position::from_string("4x4").err(),
+            Some(PositionParseError::InvalidChar('x'))
+        );
+        assert_eq!(
+            Position::from_string("44 55").err(),
+            Some(PositionParseError::InvalidChar(' '))
+        );
+    }
+
+    #[test]
+    fn threat_rows() {
+        let (current, opponent) = Position::new().threat_row_counts();
+        assert_eq!(current, [0; Position::HEIGHT as usize]);
+        assert_eq!(opponent, [0; Position::HEIGHT as usize]);
+        // 'o' has three stones in the bottom row and can complete it on both sides,
+        // 'x' has three stones in the second row.
+        let pos = Position::from_string("445566").unwrap();
+        let (current, opponent) = pos.threat_row_counts();
+        assert_eq!(current[0], 2);
+        assert_eq!(current[1..].iter().sum::<u8>(), 0);
+        assert_eq!(opponent[1], 2);
+        // After 'o' plays in column 1, the threats are counted for the other side.
+        let mut pos2 = pos.clone();
+        pos2.play_col(0);
+        assert_eq!(pos2.threat_row_counts(), (opponent, current));
+        assert_eq!(
+            current.iter().sum::<u8>(),
+            Position::popcount(pos.winning_position())
+        );
+    }
+
+    #[test]
+    fn from_grid_string() {
+        for moves in ["", "4", "4435", "65617356615221567642233737747314144542532"] {
+            let pos = Position::from_string(moves).unwrap();
+            for slashes in [false, true] {
+                let grid = pos.to_ascii_art(RenderOpts::default());
+                let grid = if slashes {
+                    grid.replace('\n', "/")
+                } else {
+                    grid
+                };
+                let parsed = Position::from_grid_string(&grid).unwrap();
+                assert!(parsed.is_valid());
+                assert_eq!(parsed.key(), pos.key(), "{grid}");
+                assert_eq!(parsed.nb_moves(), pos.nb_moves());
+            }
+        }
+        let errors = [
+            ("7/7/7/7/7", PositionParseError::GridSize),
+            ("7/7/7/7/7/8", PositionParseError::GridSize),
+            ("7/7/7/7/7/o5", PositionParseError::GridSize),
+            ("7/7/7/7/7/o5?", PositionParseError::InvalidCell('?')),
+            ("7/7/7/7/o6/.x5", PositionParseError::FloatingStone(1)),
+            ("7/7/7/7/7/xx5", PositionParseError::StoneCount),
+            ("7/7/7/x6/x6/x6/", PositionParseError::StoneCount),
+            ("7/7/o6/o6/o6/o6xxx", PositionParseError::GridSize),
+            ("7/7/o6/o6/o6/oxxx3", PositionParseError::Alignment),
+            ("7/7/7/7/o6/x6", PositionParseError::Unreachable),
+        ];
+        for (grid, error) in errors {
+            assert_eq!(
+                Position::from_grid_string(grid).err(),
+                Some(error),
+                "{grid}"
+            );
+        }
+        // Upper case letters work as well.
+        let grid = "7/7/7/O6/O6/OX2X2";
+        let pos = Position::from_grid_string(grid).unwrap();
+        assert_eq!(pos.key(), Position::from_string("12151").unwrap().key());
+    }
+
+    #[test]
+    fn ascii_art() {
+        let pos = Position::from_string("4435").unwrap();
+        let empty = ".......\n".repeat(4);
+        assert_eq!(
+            pos.to_ascii_art(RenderOpts::default()),
+            format!("{empty}...x...\n..oox..\n")
+        );
+        let opts = RenderOpts {
+            first: 'X',
+            second: 'O',
+            empty: ' ',
+            labels: true,
+            ..RenderOpts::default()
+        };
+        let empty = "       \n".repeat(4);
+        assert_eq!(
+            pos.to_ascii_art(opts),
+            format!("{empty}   O   \n  XXO  \n1234567\n")
+        );
+        let lines = pos
+            .to_ascii_art(RenderOpts {
+                border: true,
+                ..opts
+            })
+            .lines()
+            .map(str::len)
+            .collect::<Vec<_>>();
+        assert_eq!(lines.len(), Position::HEIGHT as usize + 3);
+        assert!(lines
+            .iter()
+            .all(|&len| len == lines[0] || len == lines[0] - 2));
+    }
+
+    #[test]
+    fn all_positions_at_ply() {
+        // The number of positions, see OEIS A212693, which from ply 7 on also counts
+        // the positions with an alignment.
+        let known = [1, 7, 49, 238, 1120, 4263, 16422];
+        // Counting mirror images once.
+        let counts = [1, 4, 25, 121, 568, 2144, 8231, 27109, 91295];
+        for (ply, &count) in counts.iter().enumerate() {
+            let mut keys = BTreeSet::new();
+            let mut positions = 0;
+            for pos in Position::all_positions_at_ply(ply as u8) {
+                assert_eq!(pos.nb_moves() as usize, ply);
+                assert!(pos.is_valid());
+                assert!(keys.insert(pos.key3()));
+                // A position that isn't symmetric stands for itself and its mirror image.
+                positions += if pos.key() == pos.mirrored_key() {
+                    1
+                } else {
+                    2
+                };
+            }
+            assert_eq!(keys.len(), count, "at ply {ply}");
+            if let Some(&known) = known.get(ply) {
+                assert_eq!(positions, known, "at ply {ply}");
+            }
+        }
+        // Of a position and its mirror image, the one with the smaller base 3 key is
+        // generated, so "11" comes as "77".
+        let mut positions = Position::all_positions_at_ply(2);
+        assert!(positions.next().unwrap() == Position::from_string("17").unwrap());
+        assert!(positions.next().unwrap() == Position::from_string("26").unwrap());
+        assert!(Position::all_positions_at_ply(2)
+            .any(|pos| pos == Position::from_string("77").unwrap()));
+    }
+
+    #[test]
+    fn perft() {
+        fn mirror_moves(moves: &str) -> String {
+            moves
+                .bytes()
+                .map(|c| char::from(b'1' + Position::WIDTH - (c - b'0')))
+                .collect()
+        }
+        let pos = Position::new();
+        for depth in 0..=6 {
+            assert_eq!(pos.perft(depth), 7u64.pow(depth as u32));
+        }
+        // Seven stones can't go in the same column.
+        assert_eq!(pos.perft(7), 7u64.pow(7) - 7);
+        assert!(pos.perft_divide(0).is_empty());
+
+        for moves in [
+            "",
+            "4455",
+            "2252576253462244111563365343671351441",
+            "121212",
+        ] {
+            let pos = Position::from_string(moves).unwrap();
+            let mirrored = Position::from_string(&mirror_moves(moves)).unwrap();
+            for depth in 1..=5 {
+                let divide = pos.perft_divide(depth);
+                assert_eq!(divide.iter().map(|(_, n)| n).sum::<u64>(), pos.perft(depth));
+                // The mirrored position has the same counts, in the mirrored columns.
+                let mut mirrored_divide = mirrored
+                    .perft_divide(depth)
+                    .into_iter()
+                    .map(|(col, n)| (Position::WIDTH - 1 - col, n))
+                    .collect::<Vec<_>>();
+                mirrored_divide.reverse();
+                assert_eq!(divide, mirrored_divide, "{moves} {depth}");
+            }
+        }
+        // Only the winning move of "121212" counts at depth 1 and not after.
+        let pos = Position::from_string("121212").unwrap();
+        assert_eq!(pos.perft_divide(2)[0], (0, 0));
+        assert_eq!(pos.perft_divide(1)[0], (0, 1));
+    }
+
+    fn bit(col: Column, row: Column) -> Bitboard {
+        1 << (row + col * (Position::HEIGHT + 1))
+    }
+
+    /// The spots making a line of `win` with the stones, checked one by one.
+    fn brute_force_spots(position: Bitboard, win: isize) -> Bitboard {
+        let has_stone = |col: isize, row: isize| {
+            (0..Position::WIDTH as isize).contains(&col)
+                && (0..Position::HEIGHT as isize).contains(&row)
+                && position & bit(col as Column, row as Column) != 0
+        };
+        let mut spots = 0;
+        for col in 0..Position::WIDTH as isize {
+            for row in 0..Position::HEIGHT as isize {
+                // Vertically only the spot on top of the stones counts, the spots
+                // below them are always taken.
+                let makes_line = [(1, 0), (0, 1), (1, 1), (1, -1)].iter().any(|&(dc, dr)| {
+                    (0..win).filter(|&k| dc != 0 || k == win - 1).any(|k| {
+                        (0..win)
+                            .filter(|&j| j != k)
+                            .all(|j| has_stone(col + (j - k) * dc, row + (j - k) * dr))
+                    })
+                });
+                if makes_line {
+                    spots |= bit(col as Column, row as Column);
+                }
+            }
+        }
+        spots
+    }
+
+    #[test]
+    fn no_wrapped_alignments() {
+        // Every window of four spots the shifts look at, including the windows that go
+        // past the top or the bottom of a column, or past the edges of the board.
+        for shift in [
+            1,
+            Position::HEIGHT + 1,
+            Position::HEIGHT,
+            Position::HEIGHT + 2,
+        ] {
+            for start in 0..Bitboard::BITS - 3 * u32::from(shift) {
+                let window = (0..4).map(|j| 1 << (start + j * u32::from(shift)));
+                for k in 0..4 {
+                    let stones = window
+                        .clone()
+                        .enumerate()
+                        .filter(|&(j, _)| j != k)
+                        .fold(0, |stones, (_, spot)| stones | spot);
+                    if stones & !Position::BOARD_MASK != 0 {
+                        continue;
+                    }
+                    let spots = brute_force_spots(stones, 4);
+                    assert_eq!(
+                        Position::alignment_spots(stones),
+                        spots,
+                        "{shift} {start} {k}"
+                    );
+                    assert_eq!(
+                        Position::compute_winning_position(stones, stones),
+                        spots & !stones
+                    );
+                    assert_eq!(
+                        Position::has_alignment(stones),
+                        brute_force_spots(stones, 4) & stones != 0
+                    );
+                }
+            }
+        }
+        // Stones at the top of one column and the bottom of the next one follow each other
+        // in the bitboard, but don't make a line.
+        let stones = bit(0, 4) | bit(0, 5) | bit(1, 0) | bit(1, 1);
+        assert!(!Position::has_alignment(stones));
+        assert_eq!(Position::alignment_spots(stones), 0);
+        // The same for the diagonals going through the extra row above each column.
+        let stones = bit(0, 1) | bit(0, 0) | bit(1, 5);
+        assert_eq!(Position::alignment_spots(stones), 0);
+        let stones = bit(0, 4) | bit(0, 5) | bit(1, 0);
+        assert_eq!(Position::alignment_spots(stones), 0);
+    }
+
+    #[test]
+    fn other_win_lengths() {
+        // Two stones next to each other make a line of three on both sides.
+        let pos = Position::from_string("3343").unwrap();
+        let stones = pos.current_position;
+        let spots = Position::alignment_spots_of::<3>(stones);
+        assert_eq!(spots, bit(1, 0) | bit(4, 0));
+        assert!(!Position::has_alignment_of::<3>(stones));
+        assert!(Position::has_alignment_of::<3>(stones | bit(4, 0)));
+        assert!(!Position::has_alignment_of::<3>(stones | bit(5, 0)));
+
+        for file in ["end_easy", "middle_easy"] {
+            let path = format!("./benchmark_files/{file}");
+            for line in std::fs::read_to_string(path).unwrap().lines().take(50) {
+                let pos = Position::parse_pons(line.split(' ').next().unwrap()).unwrap();
+                for stones in [pos.current_position, pos.current_position ^ pos.mask] {
+                    for win in 3..=5 {
+                        let spots = match win {
+                            3 => Position::alignment_spots_of::<3>(stones),
+                            4 => Position::alignment_spots_of::<4>(stones),
+                            _ => Position::alignment_spots_of::<5>(stones),
+                        };
+                        assert_eq!(spots, brute_force_spots(stones, win), "{line} {win}");
+                    }
+                    assert_eq!(
+                        Position::has_alignment_of::<3>(stones),
+                        brute_force_spots(stones, 3) & stones != 0
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn non_losing_moves_brute_force() {
+        const W: usize = Position::WIDTH as usize;
+        const H: usize = Position::HEIGHT as usize;
+        // A plain grid, indexed by column and row, with 0 for an empty cell
+        // and 1 or 2 for the stones of the players.
+        type Grid = [[u8; H]; W];
+
+        fn height(grid: &Grid, col: usize) -> usize {
+            grid[col].iter().take_while(|&&cell| cell != 0).count()
+        }
+
+        // Whether `player` makes an alignment by playing `col`.
+        fn wins_with(grid: &Grid, col: usize, player: u8) -> bool {
+            let row = height(grid, col);
+            if row == H {
+                return false;
+            }
+            let owns = |c: isize, r: isize| {
+                (0..W as isize).contains(&c)
+                    && (0..H as isize).contains(&r)
+                    && grid[c as usize][r as usize] == player
+            };
+            [(1, 0), (0, 1), (1, 1), (1, -1)].iter().any(|&(dc, dr)| {
+                let count = |sign: isize| {
+                    (1..4)
+                        .take_while(|&i| {
+                            owns(col as isize + sign * i * dc, row as isize + sign * i * dr)
+                        })
+                        .count()
+                };
+                1 + count(1) + count(-1) >= 4
+            })
+        }
+
+        let mut seed = 0;
+        let mut random = || {
+            seed += 1;
+            crate::solver::splitmix64(seed)
+        };
+        let mut checked = 0;
+        for _ in 0..2_000 {
+            let mut grid: Grid = [[0; H]; W];
+            let mut pos = Position::new();
+            let mut player = 1;
+            loop {
+                let can_win = (0..W).any(|col| wins_with(&grid, col, player));
+                assert_eq!(pos.can_win_next(), can_win);
+                if !can_win {
+                    // A move doesn't lose if the opponent can't make an alignment after it.
+                    let expected = (0..W)
+                        .filter(|&col| {
+                            if height(&grid, col) == H {
+                                return false;
+                            }
+                            let mut next = grid;
+                            next[col][height(&grid, col)] = player;
+                            !(0..W).any(|c| wins_with(&next, c, 3 - player))
+                        })
+                        .collect::<Vec<_>>();
+                    let possible = pos.possible_non_losing_moves();
+                    let actual = (0..W)
+                        .filter(|&col| {
+                            possible & Position::column_mask(col as position::Column) != 0
+                        })
+                        .collect::<Vec<_>>();
+                    assert_eq!(
+                        actual,
+                        expected,
+                        "in position\n{}",
+                        pos.to_ascii_art(RenderOpts::default())
+                    );
+                    checked += 1;
+                }
+                let moves = (0..W)
+                    .filter(|&col| height(&grid, col) < H && !wins_with(&grid, col, player))
+                    .collect::<Vec<_>>();
+                if moves.is_empty() {
+                    break;
+                }
+                let col = moves[(random() % moves.len() as u64) as usize];
+                grid[col][height(&grid, col)] = player;
+                pos.play_col(col as position::Column);
+                player = 3 - player;
+            }
+        }
+        assert!(checked > 10_000);
+    }
+
+    #[test]
+    fn parse_pons_notation() {
+        assert_eq!(
+            Position::parse_pons("4455").unwrap().key(),
+            Position::from_string("4455").unwrap().key()
+        );
+        for (moves, err) in [
+            ("40", PositionParseError::InvalidColumn(0)),
+            ("48", PositionParseError::InvalidColumn(8)),
+            ("4x4", PositionParseError::InvalidChar('x')),
+            ("4455\n", PositionParseError::InvalidChar('\n')),
+            ("1111111", PositionParseError::Unplayable(1)),
+            ("1212121", PositionParseError::AlreadyWinning(1)),
+            // The first invalid move is reported, even if a later character isn't a digit.
+            ("1111111x", PositionParseError::Unplayable(1)),
+            ("12121218", PositionParseError::AlreadyWinning(1)),
+        ] {
+            assert_eq!(Position::parse_pons(moves).err(), Some(err), "{moves:?}");
+        }
+        for file in ["begin_easy", "end_easy", "middle_easy", "middle_medium"] {
+            let path = format!("./benchmark_files/{file}");
+            for line in std::fs::read_to_string(path).unwrap().lines() {
+                let moves = line.split(' ').next().unwrap();
+                let pos = Position::parse_pons(moves).unwrap();
+                assert_eq!(pos.nb_moves() as usize, moves.len());
+            }
+        }
+    }
+
+    #[test]
+    fn from_string_invariants() {
+        // The same checks as the fuzz target, on random strings of columns.
+        let mut seed = 0;
+        let mut random = || {
+            seed += 1;
+            crate::solver::splitmix64(seed)
+        };
+        for _ in 0..10_000 {
+            let len = random() % 50;
+            let position_str = (0..len)
+                .map(|_| char::from(b'1' + (random() % u64::from(Position::WIDTH)) as u8))
+                .collect::<String>();
+            if let Ok(pos) = Position::from_string(&position_str) {
+                assert!(pos.is_valid(), "{position_str}");
+                assert_eq!(pos.nb_moves() as usize, position_str.len());
+            }
+        }
+        assert!(Position::new().is_valid());
+        let mut pos = Position::from_string("4455").unwrap();
+        pos.mask |= 1 << 5;
+        assert!(!pos.is_valid());
+    }
+
+    #[test]
+    fn cached_threats() {
+        // Check the threats that are updated in `play` in every position of a search tree.
+        fn check_tree(pos: &Position, depth: usize) -> usize {
+            assert!(pos.is_valid());
+            assert_eq!(
+                pos.winning_position(),
+                Position::compute_winning_position(pos.current_position, pos.mask)
+            );
+            assert_eq!(
+                pos.opponent_winning_position(),
+                Position::compute_winning_position(pos.current_position ^ pos.mask, pos.mask)
+            );
+            if depth == 0 || pos.can_win_next() {
+                return 1;
+            }
+            let mut nodes = 1;
+            for col in 0..Position::WIDTH {
+                if pos.can_play(col) {
+                    let mut pos2 = pos.clone();
+                    pos2.play_col(col);
+                    nodes += check_tree(&pos2, depth - 1);
+                }
+            }
+            nodes
+        }
+        for position_str in [
+            "",
+            "4455",
+            "52753311433677442422121",
+            "2252576253462244111563",
+        ] {
+            let pos = Position::from_string(position_str).unwrap();
+            assert!(check_tree(&pos, 5) > 100);
+        }
+        // The positions that are not built by playing moves also get their threats.
+        let mut pos = Position::from_string("1233").unwrap();
+        assert!(pos.canonicalize());
+        assert!(pos.is_valid());
+        let grid = Position::from_grid_string(&pos.to_ascii_art(RenderOpts::default())).unwrap();
+        assert!(grid.is_valid());
+        assert!(pos.swap_to_move().unwrap().is_valid());
+    }
+
+    #[test]
+    fn canonicalize() {
+        for position_str in [
+            "",
+            "4",
+            "1",
+            "7",
+            "4455",
+            "13",
+            "75",
+            "52753311433677442422121",
+            // More moves than `key3()` supports.
+            "2252576253462244111563365343671351441",
+        ] {
+            let pos = Position::from_string(position_str).unwrap();
+            let mirrored_str = position_str
+                .bytes()
+                .map(|c| char::from(b'1' + b'7' - c))
+                .collect::<String>();
+            let mirrored = Position::from_string(&mirrored_str).unwrap();
+            let (mut canonical, mut canonical_mirrored) = (pos.clone(), mirrored.clone());
+            let changed = canonical.canonicalize();
+            let changed_mirrored = canonical_mirrored.canonicalize();
+            // Only one orientation is changed, unless the position is symmetric.
+            assert!(!(changed && changed_mirrored));
+            assert_eq!(changed || changed_mirrored, pos.key() != mirrored.key());
+            assert_eq!(canonical.key(), canonical_mirrored.key());
+            assert!(canonical == canonical_mirrored);
+            if pos.nb_moves() <= Position::KEY3_MAX_MOVES {
+                assert_eq!(canonical.key3(), pos.key3());
+            }
+            assert!(canonical.is_valid());
+            assert!(canonical.equivalent_under_symmetry(&pos));
+            // Canonicalizing twice doesn't change anything.
+            assert!(!canonical.canonicalize());
+        }
+    }
+
+    #[test]
+    fn swap_to_move() {
+        for moves in ["", "1234", "12344321", "21436576"] {
+            let pos = Position::from_string(moves).unwrap();
+            let swapped = pos.swap_to_move().unwrap();
+            assert!(swapped.is_valid());
+            assert_eq!(swapped.nb_moves(), pos.nb_moves());
+            assert_eq!(swapped.threat_difference(), -pos.threat_difference());
+            assert!(swapped.swap_to_move().unwrap() == pos, "{moves}");
+            // The swapped stones are the same as those of the swapped grid.
+            let grid = pos.to_ascii_art(RenderOpts::default());
+            let swapped_grid = grid.replace('o', "O").replace('x', "o").replace('O', "x");
+            assert!(Position::from_grid_string(&swapped_grid).unwrap() == swapped);
+        }
+        assert!(Position::from_string("123")
+            .unwrap()
+            .swap_to_move()
+            .is_none());
+    }
+
+    #[test]
+    fn double_threat_moves() {
+        // 'o' makes an open three on the bottom row.
+        let pos = Position::from_string("2233").unwrap();
+        assert_eq!(pos.double_threat_moves().collect::<Vec<_>>(), vec![3]);
+        // 'x' can't stop both threats, so every move loses.
+        let pos = Position::from_string("22334").unwrap();
+        assert_eq!(pos.double_threat_moves().count(), 0);
+        // Filling either gap of 'o' on the bottom row threatens both ends.
+        let pos = Position::from_string("226644").unwrap();
+        assert_eq!(pos.double_threat_moves().collect::<Vec<_>>(), vec![2, 4]);
+        // 'x' has to block the threat of 'o' in column 5.
+        let pos = Position::from_string("2266447").unwrap();
+        assert_eq!(pos.double_threat_moves().count(), 0);
+        assert_eq!(Position::new().double_threat_moves().count(), 0);
+    }
+
+    #[test]
+    fn with_move() {
+        let pos = Position::from_string("4445555556").unwrap();
+        let next = pos.with_move(3).unwrap();
+        assert_eq!(next.nb_moves(), pos.nb_moves() + 1);
+        assert_eq!(next.drop_row(3), Some(4));
+        // The position itself is unchanged.
+        assert_eq!(pos.drop_row(3), Some(3));
+        // Full column.
+        assert!(pos.with_move(4).is_none());
+        // Winning move.
+        let pos = Position::from_string("445566").unwrap();
+        assert!(pos.with_move(6).is_none());
+        assert!(pos.with_move(2).is_none());
+        assert!(pos.with_move(0).is_some());
+    }
+
+    #[test]
+    fn drop_rows() {
+        let pos = Position::from_string("4445555556").unwrap();
+        // Empty column.
+        assert_eq!(pos.drop_row(0), Some(0));
+        // Partially filled columns.
+        assert_eq!(pos.drop_row(3), Some(3));
+        assert_eq!(pos.drop_row(5), Some(1));
+        // Full column.
+        assert_eq!(pos.drop_row(4), None);
+        let mut pos = pos;
+        pos.play_col(3);
+        assert_eq!(pos.drop_row(3), Some(4));
+    }
+
+    #[test]
+    fn column_masks() {
+        let pos = Position::new();
+        assert_eq!(pos.playable_mask(), [true; Position::WIDTH as usize]);
+        assert_eq!(pos.non_losing_mask(), [true; Position::WIDTH as usize]);
+        // The first column is full.
+        let pos = Position::from_string("2252576253462244111563365343671351441").unwrap();
+        let playable = pos.playable_mask();
+        assert!(!playable[0]);
+        for col in 1..Position::WIDTH {
+            assert_eq!(playable[col as usize], pos.can_play(col));
+        }
+        // 'o' has to block column 3, but can also win in column 4.
+        let pos = Position::from_string("434343").unwrap();
+        let non_losing = pos.non_losing_mask();
+        assert!(non_losing[2] && non_losing[3]);
+        assert_eq!(non_losing.iter().filter(|&&b| b).count(), 2);
+        // 'o' threatens to win in columns 3 and 7.
+        let pos = Position::from_string("44556").unwrap();
+        assert_eq!(pos.non_losing_mask(), [false; Position::WIDTH as usize]);
+    }
+
+    #[test]
+    fn stones_per_player() {
+        // A game that fills the whole board without an alignment.
+        let line = "656173566152215676422337377473141445425321";
+        let mut pos = Position::new();
+        assert_eq!(pos.stones_per_player(), (0, 0));
+        for c in line.bytes() {
+            assert!(!pos.is_winning_move(c - b'1'));
+            pos.play_col(c - b'1');
+            let (first, second) = pos.stones_per_player();
+            assert_eq!(first + second, pos.nb_moves());
+            assert_eq!(pos.num_stones(), pos.nb_moves());
+            assert_eq!(first, pos.nb_moves().div_ceil(2));
+            assert_eq!(second, pos.nb_moves() / 2);
+        }
+        let half = Position::WIDTH * Position::HEIGHT / 2;
+        assert_eq!(pos.stones_per_player(), (half, half));
+    }
+
+    #[test]
+    fn equality() {
+        let pos = Position::from_string("4455").unwrap();
+        // A transposition is the same position, but not the same game.
+        let transposed = Position::from_string("5544").unwrap();
+        assert!(pos == transposed);
+        // The same cells filled, but with the stones of the players swapped.
+        assert!(pos != Position::from_string("5454").unwrap());
+        assert!(pos != Position::from_string("4456").unwrap());
+        // A parsed grid, whose moves are unknown, is equal to the position it shows.
+        let grid = Position::from_grid_string(&pos.to_ascii_art(RenderOpts::default())).unwrap();
+        assert!(grid == pos);
+        assert!(Position::new() == Position::default());
+    }
+
+    #[test]
+    fn feature_planes() {
+        let cells = (Position::WIDTH * Position::HEIGHT) as usize;
+        let features = Position::new().feature_planes();
+        assert_eq!(features.len(), Position::FEATURE_LEN);
+        assert!(features[..2 * cells].iter().all(|&f| f == 0.0));
+        assert_eq!(features[2 * cells], 1.0);
+
+        // 'o' in the bottom of column 4, 'x' on top of it and 'o' in the bottom of column 1.
+        let pos = Position::from_string("441").unwrap();
+        let features = pos.feature_planes();
+        // It's the turn of 'x', so the first plane has the stone of 'x'.
+        assert_eq!(features[Position::WIDTH as usize + 3], 1.0);
+        assert_eq!(features[cells + 3], 1.0);
+        assert_eq!(features[cells], 1.0);
+        assert_eq!(features[2 * cells], 0.0);
+        assert_eq!(features.iter().filter(|&&f| f == 1.0).count(), 3);
+    }
+
+    #[test]
+    fn partial_sequences() {
+        let mut pos = Position::new();
+        assert!(matches!(
+            pos.play_sequence_partial(&[1, 1, 1, 1, 1, 1, 1, 2]),
+            (6, PlayResult::Unplayable(0))
+        ));
+        assert_eq!(pos.nb_moves(), 6);
+        assert!(matches!(
+            pos.play_sequence_partial(&[0]),
+            (0, PlayResult::TooSmall)
+        ));
+        assert!(matches!(
+            pos.play_sequence_partial(&[2, 3]),
+            (2, PlayResult::Ok)
+        ));
+        assert_eq!(pos.nb_moves(), 8);
+        assert!(matches!(
+            pos.play_sequence_partial(&[]),
+            (0, PlayResult::Ok)
+        ));
+    }
+
+    #[test]
+    fn strategic_hints() {
+        assert_eq!(Position::new().strategic_hint(), None);
+        let pos = Position::from_string("445533").unwrap();
+        assert_eq!(pos.strategic_hint(), Some(Outcome::Win));
+        // A full board without an alignment is a draw, although there is no move left.
+        let pos = Position::from_string("656173566152215676422337377473141445425321").unwrap();
+        assert_eq!(pos.nb_moves(), Position::WIDTH * Position::HEIGHT);
+        assert_eq!(pos.strategic_hint(), Some(Outcome::Draw));
+        // The hint never contradicts the scores in the benchmark files.
+        let mut claimeven_losses = 0;
+        for file in ["end_easy", "middle_easy", "middle_medium"] {
+            let path = format!("./benchmark_files/{file}");
+            for line in std::fs::read_to_string(path).unwrap().lines() {
+                let mut parts = line.split(' ');
+                let pos = Position::from_string(parts.next().unwrap()).unwrap();
+                let score = parts.next().unwrap().parse::<isize>().unwrap();
+                if let Some(outcome) = pos.strategic_hint() {
+                    assert_eq!(outcome, Outcome::from_score(score), "{line}");
+                    if !pos.can_win_next() && pos.possible_non_losing_moves() != 0 {
+                        claimeven_losses += 1;
+                    }
+                }
+            }
+        }
+        assert!(claimeven_losses > 0);
+    }
+
+    #[test]
+    fn terminal_moves() {
+        let pos = Position::from_string("445533").unwrap();
+        assert_eq!(pos.is_terminal_after(1), Some(GameStatus::Won));
+        assert_eq!(pos.is_terminal_after(0), None);
+        // The last cell of a board without alignments.
+        let pos = Position::from_string("65617356615221567642233737747314144542532").unwrap();
+        assert_eq!(pos.nb_moves(), Position::WIDTH * Position::HEIGHT - 1);
+        assert_eq!(pos.is_terminal_after(0), Some(GameStatus::Drawn));
+    }
+
+    #[test]
+    fn symmetry_equivalence() {
+        let empty = Position::new();
+        assert!(empty.equivalent_under_symmetry(&Position::new()));
+        let pos = Position::from_string("4453212").unwrap();
+        let mirrored = Position::from_string("4435676").unwrap();
+        assert!(pos.equivalent_under_symmetry(&mirrored));
+        assert!(mirrored.equivalent_under_symmetry(&pos));
+        // Same stones but a different position.
+        let other = Position::from_string("4453213").unwrap();
+        assert!(!pos.equivalent_under_symmetry(&other));
+        assert!(!pos.equivalent_under_symmetry(&empty));
+    }
+
+    #[test]
+    fn key3_max_moves() {
+        let digits = u32::from(Position::KEY3_MAX_MOVES + Position::WIDTH);
+        assert!(3u64.checked_pow(digits).is_some());
+        assert!(3u64.checked_pow(digits + 1).is_none());
+        assert_eq!(Position::KEY3_MAX_MOVES, 33);
+    }
+
+    #[test]
+    fn key_values() {
+        // Stored books and tablebases depend on these values, see `KEY_FORMAT_VERSION`.
+        for (position_str, key, key3) in [
+            ("", 0, 0),
+            ("4", 2_097_152, 54),
+            ("44", 8_388_608, 135),
+            ("4455", 1_082_130_432, 1260),
+            ("1234567", 4_467_038_634_241, 1_135_955),
+            (
+                "52753311433677442422121",
+                48_448_630_250_513,
+                60_340_293_063_781,
+            ),
+        ] {
+            let pos = Position::from_string(position_str).unwrap();
+            assert_eq!(pos.key(), key, "key of {position_str:?}");
+            assert_eq!(pos.key3(), key3, "key3 of {position_str:?}");
+        }
+        assert_eq!(Position::KEY_FORMAT_VERSION, 1);
+    }
+
     #[test]
     fn mirror() {
         for p in [3, 5, 11, 37, 53, 137] {
@@ -1,15 +1,22 @@
-use std::sync::{
-    atomic::{AtomicBool, AtomicIsize, AtomicU64, Ordering},
-    Arc,
-};
-use std::time::Instant;
+use alloc::collections::VecDeque;
+#[cfg(not(feature = "std"))]
+use alloc::collections::{BTreeMap as HashMap, BTreeSet as HashSet};
+use alloc::{format, string::String, sync::Arc, vec, vec::Vec};
+use core::sync::atomic::{AtomicBool, AtomicIsize, AtomicU64, AtomicU8, Ordering};
+use core::time::Duration;
 
+use crate::compat::{Instant, Mutex};
+use crate::error::Connect4Error;
+use crate::logging::{debug, info};
 use crate::move_sorter;
-use crate::opening_book::OpeningBook;
+use crate::opening_book::{BookKind, OpeningBook};
 use crate::position;
-use crate::transposition_table::TranspositionTable;
+use crate::tablebase::Tablebase;
+use crate::transposition_table::{PosInfo, TranspositionTable};
 use move_sorter::MoveSorter;
-use position::{Column, Position};
+use position::{Bitboard, Column, Position};
+#[cfg(feature = "std")]
+use std::collections::{HashMap, HashSet};
 
 struct Nodes(Arc<AtomicU64>);
 
@@ -19,14 +26,16 @@ impl Clone for Nodes {
     }
 }
 
-#[derive(Clone)]
+#[derive(Clone, Default)]
 struct NodeCounter {
     node_counters: Vec<Option<Arc<AtomicU64>>>,
 }
 
 impl NodeCounter {
     fn initialize_node_counters(&mut self, threads: usize) {
-        self.node_counters = vec![None; threads];
+        // Reuse the allocation of the previous search.
+        self.node_counters.clear();
+        self.node_counters.resize(threads, None);
     }
 
     fn add_node_counter(&mut self, thread: usize, node_counter: Arc<AtomicU64>) {
@@ -42,16 +51,89 @@ impl NodeCounter {
     }
 }
 
+/// A handle to the progress of the search of a `Solver`, which can be polled
+/// from another thread. See `Solver::progress`.
+#[derive(Clone, Default)]
+pub struct SearchProgress {
+    percentage: Arc<AtomicU8>,
+    /// The node counters of the threads of the current (or last) search.
+    nodes: Arc<Mutex<NodeCounter>>,
+}
+
+impl SearchProgress {
+    /// Get the estimated progress of the current (or last) search, as a percentage.
+    ///
+    /// This is estimated from how much the search window has been narrowed, compared
+    /// to the initial window. It is not proportional to the time spent searching.
+    #[must_use]
+    pub fn percentage(&self) -> u8 {
+        self.percentage.load(Ordering::Relaxed)
+    }
+
+    /// Get the number of nodes searched so far by all the threads of the current
+    /// (or last) search. Positions found in the opening book don't count.
+    #[must_use]
+    pub fn nodes(&self) -> u64 {
+        self.nodes.lock().unwrap().get_node_count()
+    }
+
+    fn set(&self, percentage: u8) {
+        self.percentage.store(percentage, Ordering::Relaxed);
+    }
+
+    fn set_node_counter(&self, node_counter: NodeCounter) {
+        *self.nodes.lock().unwrap() = node_counter;
+    }
+
+    /// Estimate the progress of the binary search when `[min, max]` is left of the
+    /// window `initial_range` we started with.
+    fn estimate(min: isize, max: isize, initial_range: isize) -> u8 {
+        if initial_range <= 0 {
+            return 100;
+        }
+        (100 - (100 * (max - min)) / initial_range).clamp(0, 100) as u8
+    }
+}
+
 #[derive(Clone)]
 struct SharedContext {
     table: Arc<TranspositionTable>,
     abort_search: Arc<AtomicBool>,
+    /// Set from outside the search to stop it, see `Solver::interrupt_handle`.
+    interrupt: Arc<AtomicBool>,
     score: Arc<AtomicIsize>,
+    progress: SearchProgress,
+    settings: SearchSettings,
+}
+
+/// The settings of the solver that change how the search works, see `Solver::config`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct SearchSettings {
+    /// Positions with fewer moves also store their result under the mirrored key.
+    mirror_store_ply: u8,
+    /// Look up the mirrored key when a position isn't in the transposition table.
+    mirror_probe: bool,
+    /// The search checks whether it should stop when the number of nodes is a multiple
+    /// of the check interval, which is a power of two, so this is the interval minus one.
+    abort_check_mask: u64,
+    /// Keep the keys of the positions along the current line, see `Solver::set_detect_repetitions`.
+    detect_repetitions: bool,
+}
+
+impl Default for SearchSettings {
+    fn default() -> Self {
+        Self {
+            mirror_store_ply: Solver::DEFAULT_MIRROR_STORE_PLY,
+            mirror_probe: false,
+            abort_check_mask: Solver::DEFAULT_ABORT_CHECK_INTERVAL - 1,
+            detect_repetitions: false,
+        }
+    }
 }
 
 impl SharedContext {
     fn abort_search(&self) -> bool {
-        self.abort_search.load(Ordering::SeqCst)
+        self.abort_search.load(Ordering::SeqCst) || self.interrupt.load(Ordering::SeqCst)
     }
 
     fn abort_now(&self) {
@@ -64,6 +146,83 @@ struct LocalContext {
     abort: bool,
     nodes: Nodes,
     tt_hits: u64,
+    tt_misses: u64,
+    windows: u64,
+    /// The keys of the positions from the root to the current node, if the search
+    /// detects repetitions.
+    line: HashSet<Bitboard>,
+}
+
+/// Statistics of the search done by a single thread.
+#[derive(Default)]
+struct ThreadStats {
+    nodes: u64,
+    tt_hits: u64,
+    tt_misses: u64,
+    windows: u64,
+}
+
+/// How much information a solve prints to `std_out`.
+///
+/// With the `log` feature, the messages are logged instead: the summary at the info
+/// level, and the steps of the search at the debug level.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Verbosity {
+    /// Print nothing.
+    #[default]
+    Silent,
+    /// Print a single line with the score, the number of nodes and the time it took.
+    Summary,
+    /// Print the search window, statistics and principal variation after every step of the search.
+    Full,
+}
+
+/// The result of `Solver::solve_full`, with statistics about the search.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SolveReport {
+    /// The score of the position, see `Solver::solve`.
+    pub score: isize,
+    /// The total number of nodes searched by all the threads.
+    pub nodes: u64,
+    /// The number of times a position was found in the transposition table.
+    pub tt_hits: u64,
+    /// The number of times a position was not found in the transposition table.
+    pub tt_misses: u64,
+    /// The time it took to solve the position.
+    pub elapsed: Duration,
+    /// The principal variation, as 0-based columns, read from the transposition table.
+    /// It can be incomplete, since entries can be overwritten.
+    pub pv: Vec<Column>,
+    /// The number of search windows the main thread searched.
+    pub windows_searched: u64,
+    /// Whether the search was stopped with `Solver::interrupt_handle`.
+    /// In that case the score and the principal variation are meaningless.
+    pub interrupted: bool,
+}
+
+/// The current settings of a `Solver`, see `Solver::config`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SolverConfig {
+    /// Whether an opening book is loaded.
+    pub has_book: bool,
+    /// The base 2 logarithm of the number of entries in the transposition table.
+    pub tt_log_size: usize,
+    /// The number of positions in the opening book, or 0 if there is none.
+    pub book_entries: usize,
+    /// See `Solver::set_mirror_store_ply`.
+    pub mirror_store_ply: u8,
+    /// See `Solver::set_mirror_probe`.
+    pub mirror_probe: bool,
+    /// See `Solver::set_abort_check_interval`.
+    pub abort_check_interval: u64,
+    /// See `Solver::set_cache_capacity`.
+    pub cache_capacity: usize,
+    /// See `Solver::set_detect_repetitions`.
+    pub detect_repetitions: bool,
+    /// See `Solver::set_verbosity`.
+    pub verbosity: Verbosity,
+    /// The number of positions in the tablebase, or 0 if there is none.
+    pub tablebase_entries: usize,
 }
 
 impl LocalContext {
@@ -78,6 +237,15 @@ impl LocalContext {
     pub fn nodes(&self) -> u64 {
         self.nodes.0.load(Ordering::Relaxed)
     }
+
+    fn stats(&self) -> ThreadStats {
+        ThreadStats {
+            nodes: self.nodes(),
+            tt_hits: self.tt_hits,
+            tt_misses: self.tt_misses,
+            windows: self.windows,
+        }
+    }
 }
 
 struct Searcher {
@@ -89,6 +257,106 @@ struct Searcher {
 pub struct Solver {
     trans_table: Arc<TranspositionTable>,
     book: Option<OpeningBook>,
+    tablebase: Option<Tablebase>,
+    progress: SearchProgress,
+    interrupt: Arc<AtomicBool>,
+    settings: SearchSettings,
+    /// Reused by every search, so solving many easy positions doesn't allocate a new
+    /// searcher each time.
+    searcher: Searcher,
+    cache: ResultCache,
+    /// The nodes searched since the solver was created, see `get_node_count()`.
+    total_nodes: u64,
+    /// See `set_verbosity()`.
+    verbosity: Verbosity,
+}
+
+/// The results of the last searches, see `Solver::set_cache_capacity`.
+/// When it is full, the least recently used result is removed.
+#[derive(Default)]
+struct ResultCache {
+    capacity: usize,
+    entries: HashMap<CacheKey, CachedResult>,
+    /// The keys in the order they were used, the least recently used first. A key is
+    /// pushed again every time it is used, so only the copy with the `last_used` of its
+    /// entry counts, the others are skipped when they reach the front.
+    order: VecDeque<(CacheKey, u64)>,
+    /// Counts the lookups and insertions, to know which entry was used last.
+    clock: u64,
+}
+
+/// The key of the position, whether the solve was weak, and whether the search
+/// detected repetitions, which can change the score.
+type CacheKey = (Bitboard, bool, bool);
+
+struct CachedResult {
+    score: isize,
+    pv: Vec<Column>,
+    last_used: u64,
+}
+
+impl ResultCache {
+    fn get(&mut self, key: CacheKey) -> Option<(isize, Vec<Column>)> {
+        let entry = self.entries.get_mut(&key)?;
+        self.clock += 1;
+        entry.last_used = self.clock;
+        let result = (entry.score, entry.pv.clone());
+        self.order.push_back((key, self.clock));
+        self.compact();
+        Some(result)
+    }
+
+    fn insert(&mut self, key: CacheKey, score: isize, pv: Vec<Column>) {
+        if self.capacity == 0 {
+            return;
+        }
+        self.clock += 1;
+        let last_used = self.clock;
+        self.entries.insert(
+            key,
+            CachedResult {
+                score,
+                pv,
+                last_used,
+            },
+        );
+        self.order.push_back((key, last_used));
+        self.shrink_to(self.capacity);
+        self.compact();
+    }
+
+    /// Remove the least recently used entries until at most `len` are left.
+    fn shrink_to(&mut self, len: usize) {
+        while self.entries.len() > len {
+            let (key, used) = self.order.pop_front().unwrap();
+            if self
+                .entries
+                .get(&key)
+                .is_some_and(|entry| entry.last_used == used)
+            {
+                self.entries.remove(&key);
+            }
+        }
+    }
+
+    /// Drop the keys in `order` that were used again later, once they are more than
+    /// half of it. This takes time proportional to the number of entries, but only
+    /// after as many lookups, so each lookup still takes constant time on average.
+    fn compact(&mut self) {
+        if self.order.len() > 2 * self.entries.len() + 16 {
+            let entries = &self.entries;
+            self.order.retain(|(key, used)| {
+                entries
+                    .get(key)
+                    .is_some_and(|entry| entry.last_used == *used)
+            });
+        }
+    }
+
+    fn clear(&mut self) {
+        self.entries.clear();
+        self.order.clear();
+    }
 }
 
 impl Default for Solver {
@@ -98,13 +366,176 @@ impl Default for Solver {
 }
 
 impl Solver {
+    /// The default for `set_mirror_store_ply`.
+    pub const DEFAULT_MIRROR_STORE_PLY: u8 = 24;
+
+    /// The default for `set_abort_check_interval`.
+    pub const DEFAULT_ABORT_CHECK_INTERVAL: u64 = 1024;
+
     /// Initializes the solver with a transposition table. A book can be
     /// added with the `set_book` method.
     #[must_use]
     pub fn new(book: Option<OpeningBook>) -> Self {
+        Self::with_table(Arc::new(TranspositionTable::new()), book)
+    }
+
+    /// Initializes the solver with the given transposition table, which can be shared
+    /// with other solvers. A book can be added with the `set_book` method.
+    ///
+    /// Solvers sharing a table profit from each other's searches, also when they are
+    /// used from different threads at the same time: the table is thread safe, and
+    /// an entry is always valid for the position it was stored for. Keep in mind that
+    /// `reset_transposition_table` clears the table for all of them.
+    #[must_use]
+    pub fn with_table(table: Arc<TranspositionTable>, book: Option<OpeningBook>) -> Self {
+        let progress = SearchProgress::default();
+        let interrupt = Arc::new(AtomicBool::new(false));
+        let settings = SearchSettings::default();
         Solver {
-            trans_table: Arc::new(TranspositionTable::new()),
+            searcher: Searcher::new(table.clone(), progress.clone(), interrupt.clone(), settings),
+            trans_table: table,
             book,
+            tablebase: None,
+            progress,
+            interrupt,
+            settings,
+            cache: ResultCache::default(),
+            total_nodes: 0,
+            verbosity: Verbosity::Full,
+        }
+    }
+
+    /// Set the number of moves below which the results of the search are also stored for
+    /// the mirrored position. With few moves played, the mirrored position is likely to
+    /// be reached in another branch, but later the extra entries mostly push out useful ones.
+    /// This only applies to positions that can still become symmetric.
+    ///
+    /// The default of `DEFAULT_MIRROR_STORE_PLY` was chosen by solving the openings 444,
+    /// 4444 and 444444: it searches 11% fewer nodes than a limit of 10 moves, while 20 and
+    /// 32 moves search more. Most benchmark positions can't become symmetric, so there
+    /// the difference is less than 0.1%.
+    pub fn set_mirror_store_ply(&mut self, ply: u8) {
+        self.settings.mirror_store_ply = ply;
+    }
+
+    /// Set whether the search looks up the mirrored position in the transposition table,
+    /// when the position itself isn't in it. This finds the entries of mirrored positions
+    /// that weren't stored under both keys, but costs an extra lookup on every miss.
+    /// This only applies to positions that can still become symmetric.
+    ///
+    /// It is off by default: in the openings above it searches 13% fewer nodes, but the
+    /// extra lookups make the nodes slower, and the difference in time was within the noise.
+    pub fn set_mirror_probe(&mut self, probe: bool) {
+        self.settings.mirror_probe = probe;
+    }
+
+    /// Set after how many nodes the search checks whether it was interrupted, see
+    /// `interrupt_handle()`. A search can run for up to this many nodes after the
+    /// interrupt, so a smaller interval stops sooner, while a larger interval spends
+    /// less time checking. The interval must be a power of two.
+    ///
+    /// The default is `DEFAULT_ABORT_CHECK_INTERVAL`.
+    pub fn set_abort_check_interval(&mut self, interval: u64) {
+        assert!(
+            interval.is_power_of_two(),
+            "The abort check interval must be a power of two, not {interval}"
+        );
+        self.settings.abort_check_mask = interval - 1;
+    }
+
+    /// Set whether the search keeps the keys of the positions along the line it is
+    /// searching, and scores a position that repeats one of them as a draw.
+    ///
+    /// In Connect 4 stones are only added, so a position can't repeat and this only
+    /// costs time. It is meant for rule variants where stones can be removed, and to
+    /// check that the moves are applied correctly. A draw by repetition depends on the
+    /// line that led to the position, so while this is on the search doesn't use the
+    /// transposition table, which makes it a lot slower. It is off by default.
+    pub fn set_detect_repetitions(&mut self, detect: bool) {
+        self.settings.detect_repetitions = detect;
+    }
+
+    /// Set what `analyze()` and the book generation print about their searches, e.g.
+    /// `Verbosity::Silent` to keep the output clean when the solver is used by another
+    /// program. The methods that take a `Verbosity`, like `solve()`, don't use this.
+    /// The default is `Verbosity::Full`.
+    pub fn set_verbosity(&mut self, verbosity: Verbosity) {
+        self.verbosity = verbosity;
+    }
+
+    /// Keep the results of up to `capacity` searches, so solving the same position again
+    /// returns the result without searching, e.g. in a server that gets the same requests
+    /// over and over. When the cache is full, the least recently used result is removed.
+    ///
+    /// Unlike the transposition table, the cache only holds the final results of `solve()`
+    /// and its variants, which are never overwritten by other searches. A result is only
+    /// used for a solve with the same `weak` and `set_detect_repetitions()`. Interrupted
+    /// searches are not cached. The default capacity is 0, which disables the cache.
+    pub fn set_cache_capacity(&mut self, capacity: usize) {
+        self.cache.capacity = capacity;
+        self.cache.shrink_to(capacity);
+    }
+
+    /// The number of results the cache can hold, see `set_cache_capacity()`.
+    #[must_use]
+    pub fn cache_capacity(&self) -> usize {
+        self.cache.capacity
+    }
+
+    /// Remove all the results from the cache, see `set_cache_capacity()`.
+    pub fn clear_cache(&mut self) {
+        self.cache.clear();
+    }
+
+    /// The total number of nodes searched by `solve()`, `analyze()`, `solve_window()`
+    /// and the other exact searches since the solver was created, or since the last
+    /// `reset_node_count()`. The approximate searches of `analyze_to_depth()` are not
+    /// counted.
+    #[must_use]
+    pub fn get_node_count(&self) -> u64 {
+        self.total_nodes
+    }
+
+    /// Start counting the nodes of `get_node_count()` from zero again, e.g. to count the
+    /// nodes of the next solve only. Unlike `reset_transposition_table()`, this keeps the
+    /// results of earlier searches.
+    pub fn reset_node_count(&mut self) {
+        self.total_nodes = 0;
+    }
+
+    /// Get a handle to interrupt the searches of this solver, e.g. from a signal handler.
+    ///
+    /// Setting the flag to `true` stops `solve()` and `analyze()` as soon as possible,
+    /// after which the search threads have been joined and `SolveReport::interrupted`
+    /// is set. The solver does not reset the flag: every search returns immediately until
+    /// it is set back to `false`. The transposition table can contain wrong entries after
+    /// an interrupted search, so it should be cleared before solving again.
+    #[must_use]
+    pub fn interrupt_handle(&self) -> Arc<AtomicBool> {
+        self.interrupt.clone()
+    }
+
+    /// Get a handle to the progress of the searches done by this solver.
+    /// The handle can be sent to another thread to poll the progress of a long solve.
+    #[must_use]
+    pub fn progress(&self) -> SearchProgress {
+        self.progress.clone()
+    }
+
+    /// Get the current settings of the solver, e.g. to log them.
+    #[must_use]
+    pub fn config(&self) -> SolverConfig {
+        SolverConfig {
+            has_book: self.book.is_some(),
+            tt_log_size: TranspositionTable::LOG_SIZE,
+            book_entries: self.book.as_ref().map_or(0, OpeningBook::num_entries),
+            mirror_store_ply: self.settings.mirror_store_ply,
+            mirror_probe: self.settings.mirror_probe,
+            abort_check_interval: self.settings.abort_check_mask + 1,
+            cache_capacity: self.cache.capacity,
+            detect_repetitions: self.settings.detect_repetitions,
+            verbosity: self.verbosity,
+            tablebase_entries: self.tablebase.as_ref().map_or(0, Tablebase::num_entries),
         }
     }
 
@@ -125,41 +556,143 @@ impl Solver {
         self.trans_table.reset();
     }
 
+    /// Get the entry of the transposition table for the position, decoded like the search
+    /// does. Returns `None` if the position has no entry, for example because it wasn't
+    /// searched yet, or because its entry was overwritten. Entries stored under the
+    /// mirrored key aren't looked up.
+    #[must_use]
+    pub fn probe_table(&self, pos: &Position) -> Option<TableEntry> {
+        self.trans_table.get(pos.key()).map(|posinfo| TableEntry {
+            bound: TableBound::from_posinfo(&posinfo),
+            column: posinfo.column(),
+        })
+    }
+
     pub fn set_book(&mut self, book: OpeningBook) {
         self.book = Some(book)
     }
 
+    /// Use the tablebase for the positions near the end of the game that are in it.
+    pub fn set_tablebase(&mut self, tablebase: Tablebase) {
+        self.tablebase = Some(tablebase);
+    }
+
+    /// Remove the opening book from the solver and return it, e.g. to solve positions
+    /// without using the book. It can be put back with `set_book`.
+    pub fn take_book(&mut self) -> Option<OpeningBook> {
+        self.book.take()
+    }
+
     /// Generate an opening book by adding all the positions up to a certain depth.
     /// This function does not store the opening book in a file.
-    pub fn generate_book(&mut self, pos: &Position, depth: usize) {
-        match &self.book {
-            None => {
-                self.book = Some(OpeningBook::new());
-            }
-            Some(book) => {
-                if book.get(pos).is_some() {
-                    return;
-                }
+    ///
+    /// If the solver has no book yet, a weak book is created if `weak` is true and a
+    /// strong book otherwise. The positions are solved according to the kind of the book.
+    /// If the search is interrupted, the book only contains the positions solved so far.
+    ///
+    /// The positions are visited depth first, with an explicit stack instead of recursion,
+    /// so large depths can't overflow the stack. A position that is already in the book
+    /// is skipped, together with the positions after it.
+    pub fn generate_book(&mut self, pos: &Position, depth: usize, weak: bool) {
+        // Without a file nothing can fail.
+        let _ = self.add_book_positions(pos, depth, weak, |_, _| Ok(()));
+    }
+
+    /// Generate an opening book like `generate_book()`, but write each entry to the file
+    /// at `path` as soon as the position is solved, so the work isn't lost if the
+    /// generation is stopped. The file starts with the entries that were already in the
+    /// solver's book, and is overwritten if it exists.
+    ///
+    /// The solver's book also gets the new entries, so at the end the file contains the
+    /// same entries as the solver's book, only not sorted (`OpeningBook::load` sorts them).
+    #[cfg(feature = "std")]
+    pub fn generate_book_streaming(
+        &mut self,
+        pos: &Position,
+        depth: usize,
+        weak: bool,
+        path: &std::path::Path,
+    ) -> Result<(), Connect4Error> {
+        let mut writer = self.book_or_new(weak).store_and_append(path)?;
+        self.add_book_positions(pos, depth, weak, |pos, score| writer.append(pos, score))
+    }
+
+    /// Add the positions up to `depth` to the book, see `generate_book()`.
+    /// `added` is called with every position added to the book and its score,
+    /// and the generation stops at the first error it returns.
+    fn add_book_positions(
+        &mut self,
+        pos: &Position,
+        depth: usize,
+        weak: bool,
+        mut added: impl FnMut(&Position, isize) -> Result<(), Connect4Error>,
+    ) -> Result<(), Connect4Error> {
+        let weak = self.book_or_new(weak).kind() == BookKind::Weak;
+        // Inserting the entries one by one in the sorted book shifts all the entries after
+        // them, which is slow for large books, so the new entries are only added at the end.
+        let mut new_entries = HashMap::new();
+        let result = self.collect_book_positions(pos, depth, weak, &mut added, &mut new_entries);
+        self.book.as_mut().unwrap().put_all_by_key(new_entries);
+        result
+    }
+
+    /// Solve the positions for `add_book_positions()`, and put them in `new_entries` by
+    /// their `Position::key3`.
+    fn collect_book_positions(
+        &mut self,
+        pos: &Position,
+        depth: usize,
+        weak: bool,
+        added: &mut impl FnMut(&Position, isize) -> Result<(), Connect4Error>,
+        new_entries: &mut HashMap<u64, isize>,
+    ) -> Result<(), Connect4Error> {
+        let mut stack = vec![pos.clone()];
+        while let Some(pos) = stack.pop() {
+            if pos.nb_moves() as usize > depth
+                || self.get_book().get(&pos).is_some()
+                || new_entries.contains_key(&pos.key3())
+            {
+                continue;
             }
-        };
-        if pos.nb_moves() as usize > depth {
-            return;
-        }
-        println!("\nAdding position to opening book...");
-        pos.display_position();
-        let (score, _) = self.solve(pos, false, true, 1);
-        let book = self.book.as_mut().unwrap();
-        println!("Added position with score {score}");
-        book.put(pos, score);
-        for col in 0..Position::WIDTH {
-            let col = Searcher::COLUMN_ORDER1[col as usize];
-            if !pos.can_play(col) || pos.is_winning_move(col) {
+            let verbosity = self.verbosity;
+            if verbosity != Verbosity::Silent {
+                info!("\nAdding position to opening book...");
+                let board = pos.to_ascii_art(position::RenderOpts::default());
+                info!("{}", board.trim_end());
+            }
+            let report = self.solve_full(&pos, weak, verbosity, 1);
+            if report.interrupted {
+                return Ok(());
+            }
+            let score = report.score;
+            if verbosity != Verbosity::Silent {
+                info!("Added position with score {score}");
+            }
+            new_entries.insert(pos.key3(), score);
+            added(&pos, score)?;
+            if pos.nb_moves() as usize == depth {
                 continue;
             }
-            let mut p2 = pos.clone();
-            p2.play_col(col);
-            self.generate_book(&p2, depth);
+            // Push the children in reverse, so they are visited in the column order.
+            for &col in Searcher::COLUMN_ORDER1.iter().rev() {
+                if let Some(p2) = pos.with_move(col) {
+                    stack.push(p2);
+                }
+            }
         }
+        Ok(())
+    }
+
+    /// The solver's book, or a new empty book if it has none yet. The new book is weak
+    /// if `weak` is true and strong otherwise.
+    fn book_or_new(&mut self, weak: bool) -> &mut OpeningBook {
+        self.book.get_or_insert_with(|| {
+            OpeningBook::with_kind(if weak {
+                BookKind::Weak
+            } else {
+                BookKind::Strong
+            })
+        })
     }
 
     /// Gets the solver's opening book. Panics if it has no book.
@@ -169,7 +702,9 @@ impl Solver {
 
     /// Get a score for the current position, if `weak` is true, then only a weak solve
     /// is done, i.e. we only check if it is a win a draw or a loss, but without a score.
-    /// Prints search info to `std_out` if `output` is set to `true`.
+    /// How much search info is printed to `std_out` is controlled by `verbosity`.
+    /// The search runs on `num_threads` threads, including the calling thread, so 0 and
+    /// 1 both search on the calling thread only.
     ///
     /// A positive score means it's winning for the current player and a negative score means
     /// that it's losing. A score of zero means it's a draw with best play. A score of 1 means
@@ -178,111 +713,1149 @@ impl Solver {
         &mut self,
         pos: &Position,
         weak: bool,
-        output: bool,
+        verbosity: Verbosity,
         num_threads: u8,
     ) -> (isize, u64) {
+        let report = self.solve_full(pos, weak, verbosity, num_threads);
+        (report.score, report.nodes)
+    }
+
+    /// Same as `solve()`, but returns a `SolveReport` with statistics about the search.
+    pub fn solve_full(
+        &mut self,
+        pos: &Position,
+        weak: bool,
+        verbosity: Verbosity,
+        num_threads: u8,
+    ) -> SolveReport {
+        let report = self.solve_report(pos, weak, verbosity, num_threads);
+        if verbosity == Verbosity::Summary {
+            info!(
+                "Score: {}, nodes: {}, took: {:?}",
+                report.score, report.nodes, report.elapsed
+            );
+        }
+        report
+    }
+
+    /// Solve the position and give the result as the `Outcome` for the player to move,
+    /// and the number of plies (moves of either player) from now until the game is
+    /// decided: the winning stone, or the last stone for a draw. With `weak` only the
+    /// outcome is known, so the number of plies is `None`.
+    ///
+    /// This is the score of `solve()` converted with `score_to_moves_to_win()`. The search
+    /// uses a single thread and prints nothing.
+    ///
+    /// Example:
+    /// ```
+    /// use connect_4::position::Position;
+    /// use connect_4::solver::{Outcome, Solver};
+    /// let mut solver = Solver::new(None);
+    /// // 'o' wins with the next stone.
+    /// let pos = Position::from_string("445566").unwrap();
+    /// assert_eq!(solver.solve_in_moves(&pos, false), (Outcome::Win, Some(1)));
+    /// // 'x' can't stop both threats, so 'o' wins after the next move of 'x'.
+    /// let pos = Position::from_string("22334").unwrap();
+    /// assert_eq!(solver.solve_in_moves(&pos, false), (Outcome::Loss, Some(2)));
+    /// assert_eq!(solver.solve_in_moves(&pos, true), (Outcome::Loss, None));
+    /// ```
+    pub fn solve_in_moves(&mut self, pos: &Position, weak: bool) -> (Outcome, Option<u8>) {
+        let (score, _) = self.solve(pos, weak, Verbosity::Silent, 1);
+        let outcome = Outcome::from_score(score);
+        if weak {
+            return (outcome, None);
+        }
+        let moves = Solver::score_to_moves_to_win(pos, score) as u8;
+        let plies = match outcome {
+            // The current player plays at the odd plies.
+            Outcome::Win => 2 * moves - 1,
+            Outcome::Loss => 2 * moves,
+            Outcome::Draw => Position::WIDTH * Position::HEIGHT - pos.nb_moves(),
+        };
+        (outcome, Some(plies))
+    }
+
+    fn solve_report(
+        &mut self,
+        pos: &Position,
+        weak: bool,
+        verbosity: Verbosity,
+        num_threads: u8,
+    ) -> SolveReport {
+        let start = Instant::now();
+        let mut report = SolveReport {
+            score: 0,
+            nodes: 0,
+            tt_hits: 0,
+            tt_misses: 0,
+            elapsed: Duration::ZERO,
+            pv: Vec::new(),
+            windows_searched: 0,
+            interrupted: false,
+        };
         // Check if we can win in one move as the negamax function does not support this case.
         if pos.can_win_next() {
-            return (pos.num_stones_left(1), 0);
+            self.progress.set(100);
+            report.score = pos.num_stones_left(1);
+            report.elapsed = start.elapsed();
+            return report;
         }
 
-        // Check if the position is in the opening book.
+        // Check if the position is in the opening book. A weak book can't be used for a strong solve.
         if let Some(book) = &self.book {
-            if let Some(score) = book.get(pos) {
-                if output {
-                    println!("Position in opening book");
+            if let Some(score) = book
+                .get(pos)
+                .filter(|_| weak || book.kind() == BookKind::Strong)
+            {
+                if verbosity == Verbosity::Full {
+                    info!("Position in opening book");
                 }
-                return (score, 0);
+                self.progress.set(100);
+                report.score = score;
+                report.elapsed = start.elapsed();
+                return report;
+            }
+        }
+
+        // Positions near the end of the game can be in the tablebase, which has exact scores.
+        // A weak solve only gives the sign of the score, like a weak search.
+        if let Some(score) = self.tablebase.as_ref().and_then(|tb| tb.probe(pos)) {
+            let score = if weak { score.signum() } else { score };
+            if verbosity == Verbosity::Full {
+                info!("Position in tablebase");
+            }
+            self.progress.set(100);
+            report.score = score;
+            report.elapsed = start.elapsed();
+            return report;
+        }
+
+        let cache_key = (pos.key(), weak, self.settings.detect_repetitions);
+        if let Some((score, pv)) = self.cache.get(cache_key) {
+            if verbosity == Verbosity::Full {
+                info!("Position in result cache");
             }
+            self.progress.set(100);
+            report.score = score;
+            report.pv = pv;
+            report.elapsed = start.elapsed();
+            return report;
+        }
+        self.searcher.reset(self.settings);
+        let (score, stats) = self.searcher.search(num_threads, verbosity, pos, weak);
+        report.interrupted = score.is_none();
+        report.score = score.unwrap_or(0);
+        report.nodes = stats.nodes;
+        self.total_nodes += stats.nodes;
+        report.tt_hits = stats.tt_hits;
+        report.tt_misses = stats.tt_misses;
+        report.windows_searched = stats.windows;
+        report.pv = Searcher::principal_variation(&self.trans_table, pos);
+        if !report.interrupted {
+            self.cache
+                .insert(cache_key, report.score, report.pv.clone());
         }
-        let mut searcher = Searcher::new(self.trans_table.clone());
-        searcher.search(num_threads, output, pos, weak)
+        report.elapsed = start.elapsed();
+        report
     }
 
-    /// Get a score for all the columns that can be played by calling `solve()`.
-    pub fn analyze(&mut self, pos: &Position, weak: bool) -> Vec<isize> {
-        let mut scores = vec![Searcher::INVALID_MOVE; Position::WIDTH as usize];
+    /// Get a score for all the columns by calling `solve()`.
+    /// The score of a column that can't be played is `None`.
+    ///
+    /// If the search is interrupted, see `interrupt_handle()`, every score is `None`,
+    /// so the columns solved so far can't be mistaken for a complete analysis.
+    pub fn analyze(&mut self, pos: &Position, weak: bool) -> Vec<Option<isize>> {
+        let mut scores = vec![None; Position::WIDTH as usize];
         for col in 0..Position::WIDTH {
             if pos.can_play(col) {
                 if pos.is_winning_move(col) {
-                    scores[col as usize] = pos.num_stones_left(1);
+                    scores[col as usize] = Some(pos.num_stones_left(1));
                 } else {
                     let mut pos2 = pos.clone();
                     pos2.play_col(col);
-                    let (score, nodes) = self.solve(&pos2, weak, true, 1);
-                    println!("Solved with {nodes} nodes.");
-                    scores[col as usize] = -score;
+                    let report = self.solve_full(&pos2, weak, self.verbosity, 1);
+                    if report.interrupted {
+                        return vec![None; Position::WIDTH as usize];
+                    }
+                    if self.verbosity != Verbosity::Silent {
+                        info!("Solved with {} nodes.", report.nodes);
+                    }
+                    scores[col as usize] = Some(-report.score);
                 }
             }
         }
         scores
     }
-}
 
-impl Searcher {
-    const INVALID_MOVE: isize = -1000;
-    const COLUMN_ORDER1: [Column; Position::WIDTH as usize] =
-        Self::column_order1(0, [0; Position::WIDTH as usize]);
-    const COLUMN_ORDER2: [Column; Position::WIDTH as usize] =
-        Self::column_order2(0, [0; Position::WIDTH as usize]);
-    const fn column_order1(
-        i: u8,
-        mut temp_order: [Column; Position::WIDTH as usize],
-    ) -> [Column; Position::WIDTH as usize] {
-        if i == Position::WIDTH {
-            return temp_order;
+    /// Like `analyze()`, but stop as soon as a winning column is found, for when any
+    /// winning move will do. The columns are tried from the center outwards, the order
+    /// in which the search tries them, after checking for an immediate win.
+    ///
+    /// The result is then partial: the columns that weren't solved are
+    /// `ColumnScore::Skipped`, and the winning column isn't always the one that wins
+    /// fastest. If the search is interrupted, the remaining columns are skipped too.
+    pub fn analyze_until_win(&mut self, pos: &Position, weak: bool) -> Vec<ColumnScore> {
+        let mut scores = (0..Position::WIDTH)
+            .map(|col| {
+                if pos.can_play(col) {
+                    ColumnScore::Skipped
+                } else {
+                    ColumnScore::Unplayable
+                }
+            })
+            .collect::<Vec<_>>();
+        if let Some(col) =
+            (0..Position::WIDTH).find(|&col| pos.can_play(col) && pos.is_winning_move(col))
+        {
+            scores[col as usize] = ColumnScore::Score(pos.num_stones_left(1));
+            return scores;
         }
-        // initialize the column exploration order, starting with center columns
-        // example for WIDTH=7: column_order = {3, 2, 4, 1, 5, 0, 6}
-        temp_order[i as usize] = (Position::WIDTH as isize / 2
-            + (1 - 2 * (i % 2) as isize) * (i as isize + 1) / 2)
-            as Column;
-        Self::column_order1(i + 1, temp_order)
+        for col in Searcher::COLUMN_ORDER1 {
+            if !pos.can_play(col) {
+                continue;
+            }
+            let Some(score) = self.evaluate_move(pos, col, weak) else {
+                break;
+            };
+            scores[col as usize] = ColumnScore::Score(score);
+            if score > 0 {
+                break;
+            }
+        }
+        scores
     }
-    const fn column_order2(
-        i: u8,
-        mut temp_order: [Column; Position::WIDTH as usize],
-    ) -> [Column; Position::WIDTH as usize] {
-        if i == Position::WIDTH {
-            return temp_order;
+
+    /// Get the score of playing `col`, for the current player of `pos`, like `analyze()`
+    /// does for all the columns. Returns `None` if the column can't be played, or if the
+    /// search is interrupted, see `interrupt_handle()`.
+    pub fn evaluate_move(&mut self, pos: &Position, col: Column, weak: bool) -> Option<isize> {
+        if !pos.can_play(col) {
+            return None;
         }
-        // initialize the column exploration order, starting with center columns
-        // example for WIDTH=7: column_order = {3, 4, 2, 5, 1, 6, 0}
-        temp_order[i as usize] = (Position::WIDTH as isize / 2
-            - (1 - 2 * (i % 2) as isize) * (i as isize + 1) / 2)
-            as Column;
-        Self::column_order2(i + 1, temp_order)
+        if pos.is_winning_move(col) {
+            return Some(pos.num_stones_left(1));
+        }
+        let mut pos2 = pos.clone();
+        pos2.play_col(col);
+        let report = self.solve_full(&pos2, weak, Verbosity::Silent, 1);
+        (!report.interrupted).then_some(-report.score)
     }
-}
 
-impl Searcher {
+    /// Quickly get approximate scores for each column, by searching only `depth` moves ahead.
+    /// Returns `None` for columns that can't be played.
+    ///
+    /// Wins and losses found within `depth` moves get their exact score, see
+    /// `ApproxScore::decided()`, otherwise the position is scored by
+    /// `Position::threat_difference`. These scores are only an indication, use `analyze`
+    /// to get the exact scores.
     #[must_use]
-    pub fn new(table: Arc<TranspositionTable>) -> Self {
-        Self {
-            shared_context: SharedContext {
-                table,
-                abort_search: Arc::new(AtomicBool::new(false)),
-                score: Arc::new(AtomicIsize::new(0)),
-            },
-            local_context: LocalContext {
-                abort: false,
-                nodes: Nodes(Arc::new(AtomicU64::new(0))),
-                tt_hits: 0,
-            },
-            node_counter: NodeCounter {
-                node_counters: Vec::new(),
-            },
-        }
+    pub fn analyze_to_depth(&self, pos: &Position, depth: usize) -> Vec<Option<ApproxScore>> {
+        (0..Position::WIDTH)
+            .map(|col| {
+                if !pos.can_play(col) {
+                    None
+                } else if pos.is_winning_move(col) {
+                    Some(ApproxScore::exact(pos.num_stones_left(1)))
+                } else {
+                    let mut pos2 = pos.clone();
+                    pos2.play_col(col);
+                    let score = Searcher::approximate(&pos2, depth, -isize::MAX, isize::MAX, None);
+                    Some(ApproxScore(-score))
+                }
+            })
+            .collect()
     }
 
-    /// Main alpha-beta search function.
-    fn negamax(
-        local_context: &mut LocalContext,
-        shared_context: &SharedContext,
+    /// Search deeper and deeper, starting with 1 move ahead, and report the best move
+    /// and its approximate score after each depth to `report`, e.g. to show a player
+    /// that the engine is thinking. The search at each depth is the same as
+    /// `analyze_to_depth()`, so the scores are only an indication.
+    ///
+    /// The best move of every position searched is remembered, and tried first at the
+    /// next depth, which makes the deeper searches faster. It doesn't change the scores,
+    /// and when the best move of the last depth is still one of the best, it is kept.
+    ///
+    /// This stops after `max_depth`, when a deeper search can't find anything new because
+    /// the board would be full, or before starting a depth when `time_limit` has passed or
+    /// the search was interrupted, see `interrupt_handle()`. The last completed depth is
+    /// returned, or `None` if no move can be played.
+    pub fn analyze_deepening(
+        &self,
         pos: &Position,
-        mut alpha: isize,
-        mut beta: isize,
-        can_be_symmetric: bool,
+        max_depth: usize,
+        time_limit: Option<Duration>,
+        report: &mut impl FnMut(DepthResult),
+    ) -> Option<DepthResult> {
+        let start = Instant::now();
+        let mut table = MoveTable::new();
+        let mut last = None;
+        let cells_left = usize::from(Position::WIDTH * Position::HEIGHT - pos.nb_moves());
+        for depth in 1..=max_depth.min(cells_left) {
+            if time_limit.is_some_and(|limit| start.elapsed() >= limit)
+                || self.interrupt.load(Ordering::SeqCst)
+            {
+                break;
+            }
+            let (score, best_move) = Searcher::approximate_root(pos, depth, &mut table)?;
+            let result = DepthResult {
+                depth,
+                best_move,
+                score: ApproxScore(score),
+            };
+            report(result);
+            last = Some(result);
+        }
+        last
+    }
+
+    /// Estimate how hard it is to strongly solve the position, without searching.
+    ///
+    /// This is only a heuristic based on the number of moves played, the number of
+    /// moves that don't lose directly, and whether the position is in a strong opening
+    /// book. It can be used to decide whether to warn the user before a long solve, but
+    /// there is no guarantee about the actual time the solve takes.
+    #[must_use]
+    pub fn estimate_difficulty(&self, pos: &Position) -> Difficulty {
+        if pos.can_win_next() {
+            return Difficulty::Easy;
+        }
+        if let Some(book) = &self.book {
+            if book.kind() == BookKind::Strong && book.get(pos).is_some() {
+                return Difficulty::Easy;
+            }
+        }
+        let non_losing_moves = pos.possible_non_losing_moves().count_ones();
+        // Same boundaries as the benchmark files: "end" positions have more than 28 moves,
+        // "middle" positions more than 14.
+        let difficulty = match pos.nb_moves() {
+            29.. => Difficulty::Easy,
+            15..=28 => Difficulty::Medium,
+            _ => Difficulty::Hard,
+        };
+        if non_losing_moves <= 1 {
+            // The move is forced (or every move loses).
+            Difficulty::Easy
+        } else if non_losing_moves <= 2 {
+            difficulty.min(Difficulty::Medium)
+        } else {
+            difficulty
+        }
+    }
+
+    /// Estimate the number of nodes a strong solve of the position searches, with a single
+    /// thread and an empty transposition table, e.g. to show a progress bar.
+    ///
+    /// This interpolates typical node counts by the number of stones left to play. The node
+    /// counts of positions with the same number of stones differ by orders of magnitude, so
+    /// the real number of nodes can be much higher or lower.
+    ///
+    /// Only available with the `std` feature, which has the floating point functions.
+    #[cfg(feature = "std")]
+    #[must_use]
+    pub fn estimate_nodes(pos: &Position) -> u64 {
+        let stones_left = f64::from(Position::WIDTH * Position::HEIGHT - pos.nb_moves());
+        let segment = TYPICAL_NODES
+            .windows(2)
+            .find(|segment| stones_left <= segment[1].0)
+            // Bigger boards than the default one.
+            .unwrap_or(&TYPICAL_NODES[TYPICAL_NODES.len() - 2..]);
+        let ((x0, y0), (x1, y1)) = (segment[0], segment[1]);
+        let log_nodes = y0 + (stones_left - x0) * (y1 - y0) / (x1 - x0);
+        10f64.powf(log_nodes).max(1.0) as u64
+    }
+
+    /// Search the position once with the window `[alpha, beta]`, using a single thread.
+    /// Returns the result of the search and the number of nodes searched.
+    ///
+    /// If the result is at most `alpha`, it's an upper bound on the score. If it's at least
+    /// `beta`, it's a lower bound on the score. Otherwise it's the exact score.
+    /// `solve()` narrows down the score by repeatedly calling this with a window of size 1.
+    ///
+    /// The search can be interrupted, see `interrupt_handle()`, after which the result
+    /// is meaningless.
+    ///
+    /// Panics if `alpha >= beta`.
+    pub fn solve_window(&mut self, pos: &Position, alpha: isize, beta: isize) -> (isize, u64) {
+        assert!(alpha < beta, "The search window [{alpha}, {beta}] is empty");
+        if pos.can_win_next() {
+            // The negamax function does not support this case.
+            return (pos.num_stones_left(1), 0);
+        }
+        let mut searcher = Searcher::new(
+            self.trans_table.clone(),
+            self.progress.clone(),
+            self.interrupt.clone(),
+            self.settings,
+        );
+        let (score, nodes) = searcher.search_window(pos, alpha, beta);
+        self.total_nodes += nodes;
+        (score, nodes)
+    }
+
+    /// Solve the position, and report increasingly tight bounds on the score to the `observer`.
+    ///
+    /// First a weak solve is done, which is usually fast, and gives the `Outcome`.
+    /// Then the exact score is searched for, and the observer is called after each step of
+    /// the search. Each reported bound is at least as tight as the previous one, and the
+    /// last reported bound is the exact score, which is also returned.
+    pub fn solve_incremental(
+        &mut self,
+        pos: &Position,
+        observer: &mut impl SolveObserver,
+    ) -> isize {
+        if pos.can_win_next() {
+            let score = pos.num_stones_left(1);
+            observer.update(ScoreBounds::exact(score));
+            return score;
+        }
+        if let Some(book) = &self.book {
+            if book.kind() == BookKind::Strong {
+                if let Some(score) = book.get(pos) {
+                    observer.update(ScoreBounds::exact(score));
+                    return score;
+                }
+            }
+        }
+        let (weak_score, _) = self.solve(pos, true, Verbosity::Silent, 1);
+        let mut bounds = match Outcome::from_score(weak_score) {
+            Outcome::Win => ScoreBounds {
+                min: 1,
+                max: pos.num_stones_left(1),
+            },
+            Outcome::Draw => ScoreBounds::exact(0),
+            Outcome::Loss => ScoreBounds {
+                min: -pos.num_stones_left(0),
+                max: -1,
+            },
+        };
+        observer.update(bounds);
+        while bounds.min < bounds.max {
+            let med = Searcher::window_middle(bounds.min, bounds.max);
+            let (r, _) = self.solve_window(pos, med, med + 1);
+            if r <= med {
+                bounds.max = r;
+            } else {
+                bounds.min = r;
+            }
+            observer.update(bounds);
+        }
+        bounds.min
+    }
+
+    /// Get the scores of all the columns from the opening book, without searching.
+    /// The score of a column that can't be played is `None`, like in `analyze()`.
+    ///
+    /// Returns `None` if there is no book, if the book can't be used for this kind of
+    /// solve, or if one of the positions after a playable column is not in the book.
+    #[must_use]
+    pub fn book_scores(&self, pos: &Position, weak: bool) -> Option<Vec<Option<isize>>> {
+        let book = self
+            .book
+            .as_ref()
+            .filter(|book| weak || book.kind() == BookKind::Strong)?;
+        (0..Position::WIDTH)
+            .map(|col| {
+                if !pos.can_play(col) {
+                    Some(None)
+                } else if pos.is_winning_move(col) {
+                    Some(Some(pos.num_stones_left(1)))
+                } else {
+                    let mut pos2 = pos.clone();
+                    pos2.play_col(col);
+                    book.get(&pos2).map(|score| Some(-score))
+                }
+            })
+            .collect()
+    }
+
+    /// Get the best column to play in the current position, by calling `analyze()`.
+    /// If several columns share the best score, `tie_break` decides which one is returned.
+    /// Returns `None` if there are no playable columns.
+    ///
+    /// If all the positions after the playable columns are in the opening book,
+    /// no search is done, see `book_scores()`.
+    pub fn best_move(&mut self, pos: &Position, weak: bool, tie_break: TieBreak) -> Option<Column> {
+        let scores = match self.book_scores(pos, weak) {
+            Some(scores) => scores,
+            None => self.analyze(pos, weak),
+        };
+        tie_break.select(&scores)
+    }
+
+    /// Get all the columns with the best score, from left to right, using the same scores
+    /// as `best_move()`. Returns an empty `Vec` if there are no playable columns.
+    ///
+    /// In a lost position these are the moves that lose as late as possible, which is
+    /// what perfect play does. With `weak` only the outcome of each move is known, so
+    /// every move with the best `Outcome` is returned, even though an immediate win
+    /// still gets an exact score.
+    pub fn best_moves(&mut self, pos: &Position, weak: bool) -> Vec<Column> {
+        let mut scores = match self.book_scores(pos, weak) {
+            Some(scores) => scores,
+            None => self.analyze(pos, weak),
+        };
+        if weak {
+            for score in scores.iter_mut().flatten() {
+                *score = score.signum();
+            }
+        }
+        let Some(&max) = scores.iter().flatten().max() else {
+            return Vec::new();
+        };
+        (0..Position::WIDTH)
+            .filter(|&col| scores[col as usize] == Some(max))
+            .collect()
+    }
+
+    /// Solve every position of a game, played with the 0-based columns `moves` from
+    /// `start` (`Position::new()` for a whole game), and grade each move by comparing
+    /// it with the best move, see `MoveTag`.
+    ///
+    /// All the moves of every position are solved like in `analyze()`, which takes long
+    /// at the start of the game unless they are in the opening book. The transposition
+    /// table is kept between the positions, so each solve profits from the previous ones.
+    ///
+    /// The review stops after a move that makes an alignment, before a move that can't be
+    /// played, and when the search is interrupted, so it can have fewer reviews than
+    /// there are moves.
+    pub fn review_game(&mut self, start: &Position, moves: &[Column]) -> Vec<MoveReview> {
+        let mut reviews = Vec::with_capacity(moves.len());
+        let mut pos = start.clone();
+        for (ply, &played) in moves.iter().enumerate() {
+            if !pos.can_play(played) {
+                break;
+            }
+            let scores = match self.book_scores(&pos, false) {
+                Some(scores) => scores,
+                None => {
+                    let mut scores = vec![None; Position::WIDTH as usize];
+                    for col in (0..Position::WIDTH).filter(|&col| pos.can_play(col)) {
+                        let Some(score) = self.evaluate_move(&pos, col, false) else {
+                            return reviews;
+                        };
+                        scores[col as usize] = Some(score);
+                    }
+                    scores
+                }
+            };
+            // The played column can be played, so there is a best column.
+            let best = TieBreak::CenterFirst.select(&scores).unwrap();
+            let eval_before = scores[best as usize].unwrap();
+            let eval_after = scores[played as usize].unwrap();
+            reviews.push(MoveReview {
+                ply,
+                first_player: pos.nb_moves().is_multiple_of(2),
+                played,
+                best,
+                eval_before,
+                eval_after,
+                tag: MoveTag::classify(eval_before, eval_after),
+            });
+            if pos.is_winning_move(played) {
+                break;
+            }
+            pos.play_col(played);
+        }
+        reviews
+    }
+
+    /// Explain the best move of `best_move()` in a few sentences: its result, what it does
+    /// tactically, the threats it leaves and the main line of play. Columns and rows are
+    /// numbered from 1, rows from the bottom. Returns `None` if there are no playable
+    /// columns, or if the search was interrupted.
+    ///
+    /// The threats are split by row parity: the first player wants threats on odd rows and
+    /// the second player on even rows, since those are the cells they get at the end of
+    /// the game, see `Position::threat_row_counts`.
+    pub fn explain_best_move(&mut self, pos: &Position) -> Option<String> {
+        let col = self.best_move(pos, false, TieBreak::CenterFirst)?;
+        let (opponent, player) = pos.current_player();
+        if pos.is_winning_move(col) {
+            return Some(format!(
+                "Column {} wins for '{player}' right away with four in a row.",
+                col + 1
+            ));
+        }
+        let next = pos.with_move(col)?;
+        let report = self.solve_full(&next, false, Verbosity::Silent, 1);
+        if report.interrupted {
+            return None;
+        }
+        let score = -report.score;
+        let moves = Solver::score_to_moves_to_win(pos, score);
+        let mut text = match Outcome::from_score(score) {
+            Outcome::Win => format!("Column {} wins for '{player}' in {moves} move(s).", col + 1),
+            Outcome::Draw => format!("Column {} leads to a draw.", col + 1),
+            Outcome::Loss => format!(
+                "Every move loses, column {} holds out the longest: '{opponent}' wins in {moves} move(s).",
+                col + 1
+            ),
+        };
+
+        if pos.double_threat_moves().any(|c| c == col) {
+            text.push_str(" It creates two threats at once, which can't both be blocked.");
+        } else if pos.non_losing_mask().iter().filter(|&&ok| ok).count() == 1 {
+            text.push_str(&format!(
+                " It is the only move that doesn't let '{opponent}' win right away."
+            ));
+        }
+
+        // After the move, the player who played it is the opponent of `next`.
+        let (_, threats) = next.threat_row_counts();
+        let rows = (0..Position::HEIGHT as usize)
+            .filter(|&row| threats[row] > 0)
+            .collect::<Vec<_>>();
+        if rows.is_empty() {
+            text.push_str(&format!(" Afterwards '{player}' has no threats."));
+        } else {
+            // Row 0 is row 1 in the literature, which is odd.
+            let plays_first = pos.nb_moves().is_multiple_of(2);
+            let total = threats.iter().sum::<u8>();
+            let good = (0..Position::HEIGHT as usize)
+                .filter(|&row| (row % 2 == 0) == plays_first)
+                .map(|row| threats[row])
+                .sum::<u8>();
+            let names = rows
+                .iter()
+                .map(|row| format!("{}", row + 1))
+                .collect::<Vec<_>>()
+                .join(", ");
+            text.push_str(&format!(
+                " Afterwards '{player}' has {total} threat(s), on row(s) {names}. {good} of them are on {} rows, which favour '{player}'.",
+                if plays_first { "odd" } else { "even" }
+            ));
+        }
+
+        let line = core::iter::once(col)
+            .chain(report.pv)
+            .map(|c| format!("{}", c + 1))
+            .collect::<Vec<_>>()
+            .join(" ");
+        text.push_str(&format!(" The main line is {line}."));
+        Some(text)
+    }
+}
+
+/// An approximate score of a position, see `Solver::analyze_to_depth`.
+///
+/// Unlike the scores of `Solver::solve`, this is not exact: a positive score only
+/// means the position looks good for the player to move. Wins and losses found within
+/// the searched depth are offset by `DECIDED`, so they are in a separate band from the
+/// heuristic and can't be confused with it, see `decided()`. A draw scores 0.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct ApproxScore(pub isize);
+
+impl ApproxScore {
+    /// The offset of the scores of won and lost positions. The heuristic is at most the
+    /// number of empty cells, so it is always smaller.
+    pub const DECIDED: isize = (Position::WIDTH * Position::HEIGHT) as isize;
+
+    /// The approximate score of a position with the exact `score` of `Solver::solve`.
+    #[must_use]
+    pub fn exact(score: isize) -> Self {
+        Self(score + score.signum() * Self::DECIDED)
+    }
+
+    /// The exact score, like `Solver::solve` gives, if a win or a loss was found within
+    /// the searched depth, or `None` if the score comes from the heuristic or is a draw.
+    #[must_use]
+    pub fn decided(self) -> Option<isize> {
+        (self.0.abs() > Self::DECIDED).then(|| self.0 - self.0.signum() * Self::DECIDED)
+    }
+}
+
+/// The best move found at one depth of `Solver::analyze_deepening`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct DepthResult {
+    /// The number of moves searched ahead, including the best move.
+    pub depth: usize,
+    /// The 0-based column of the best move.
+    pub best_move: Column,
+    /// The approximate score of the best move.
+    pub score: ApproxScore,
+}
+
+/// The best column found for each position by `Solver::analyze_deepening`, so the next
+/// depth can try it first. Like in the transposition table, a position overwrites the
+/// entry of any other position with the same index.
+struct MoveTable {
+    entries: Vec<(Bitboard, Column)>,
+}
+
+impl MoveTable {
+    const LOG_SIZE: u32 = 16;
+
+    fn new() -> Self {
+        Self {
+            // The empty position has key 0, so it finds column 0 before it is stored.
+            // That only changes the order of the moves.
+            entries: vec![(0, 0); 1 << Self::LOG_SIZE],
+        }
+    }
+
+    fn index(key: Bitboard) -> usize {
+        (key % (1 << Self::LOG_SIZE)) as usize
+    }
+
+    fn get(&self, key: Bitboard) -> Option<Column> {
+        let (entry_key, col) = self.entries[Self::index(key)];
+        (entry_key == key).then_some(col)
+    }
+
+    fn put(&mut self, key: Bitboard, col: Column) {
+        self.entries[Self::index(key)] = (key, col);
+    }
+}
+
+/// How good a move was, see `Solver::review_game`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MoveTag {
+    /// The move has the best score.
+    Best,
+    /// The move keeps the outcome of the game, but wins later or loses sooner than the
+    /// best move.
+    Inaccuracy,
+    /// The move gives away a win or a draw.
+    Blunder,
+}
+
+impl MoveTag {
+    /// Classify a move with the score `played`, when the best move has the score `best`.
+    /// The scores are for the player who moves.
+    #[must_use]
+    pub fn classify(best: isize, played: isize) -> Self {
+        if played >= best {
+            Self::Best
+        } else if Outcome::from_score(played) == Outcome::from_score(best) {
+            Self::Inaccuracy
+        } else {
+            Self::Blunder
+        }
+    }
+}
+
+/// The review of a move of a game, see `Solver::review_game`. The scores are for the
+/// player who played the move.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct MoveReview {
+    /// The index of the move in the reviewed moves.
+    pub ply: usize,
+    /// Whether the move was played by the first player ('o').
+    pub first_player: bool,
+    /// The 0-based column that was played.
+    pub played: Column,
+    /// A best column, the one closest to the center if there are several.
+    pub best: Column,
+    /// The score of the position before the move, which is the score of the best move.
+    pub eval_before: isize,
+    /// The score of the played move.
+    pub eval_after: isize,
+    pub tag: MoveTag,
+}
+
+/// The result of a game with perfect play, from the point of view of the player to move.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Outcome {
+    Win,
+    Draw,
+    Loss,
+}
+
+impl Outcome {
+    /// Get the outcome corresponding to a score of `Solver::solve`.
+    /// This also works for scores of a weak solve.
+    #[must_use]
+    pub fn from_score(score: isize) -> Self {
+        match score.cmp(&0) {
+            core::cmp::Ordering::Greater => Self::Win,
+            core::cmp::Ordering::Equal => Self::Draw,
+            core::cmp::Ordering::Less => Self::Loss,
+        }
+    }
+}
+
+/// The result for a single column of `Solver::analyze_until_win`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ColumnScore {
+    /// The column is full.
+    Unplayable,
+    /// The column wasn't solved, because another column wins.
+    Skipped,
+    /// The score of playing the column, like in `Solver::analyze`.
+    Score(isize),
+}
+
+/// What an entry of the transposition table says about the score of a position,
+/// see `Solver::probe_table`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TableBound {
+    /// The score is known exactly.
+    Exact(isize),
+    /// The score is at least this.
+    Lower(isize),
+    /// The score is at most this.
+    Upper(isize),
+}
+
+impl TableBound {
+    /// Decode the score of an entry. Lower bounds are stored above
+    /// `MAX_SCORE - MIN_SCORE + 1`, upper bounds and exact scores below it.
+    #[inline]
+    fn from_posinfo(posinfo: &PosInfo) -> Self {
+        let val = posinfo.score();
+        if posinfo.is_exact() {
+            Self::Exact(val + Position::MIN_SCORE - 1)
+        } else if val > Position::MAX_SCORE - Position::MIN_SCORE + 1 {
+            Self::Lower(val + 2 * Position::MIN_SCORE - Position::MAX_SCORE - 2)
+        } else {
+            Self::Upper(val + Position::MIN_SCORE - 1)
+        }
+    }
+}
+
+/// An entry of the transposition table, see `Solver::probe_table`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TableEntry {
+    pub bound: TableBound,
+    /// The best column found by the search that stored the entry.
+    pub column: Column,
+}
+
+/// The score of a position is known to be in `[min, max]`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ScoreBounds {
+    pub min: isize,
+    pub max: isize,
+}
+
+impl ScoreBounds {
+    /// Bounds for a score which is known exactly.
+    #[must_use]
+    pub fn exact(score: isize) -> Self {
+        Self {
+            min: score,
+            max: score,
+        }
+    }
+
+    /// Returns `true` if the score is known exactly.
+    #[must_use]
+    pub fn is_exact(&self) -> bool {
+        self.min == self.max
+    }
+
+    /// Returns the outcome if it is already determined by the bounds.
+    #[must_use]
+    pub fn outcome(&self) -> Option<Outcome> {
+        let outcome = Outcome::from_score(self.min);
+        (outcome == Outcome::from_score(self.max)).then_some(outcome)
+    }
+}
+
+/// Gets notified of the progress of `Solver::solve_incremental`.
+pub trait SolveObserver {
+    /// Called every time tighter bounds on the score are known.
+    fn update(&mut self, bounds: ScoreBounds);
+}
+
+impl<F: FnMut(ScoreBounds)> SolveObserver for F {
+    fn update(&mut self, bounds: ScoreBounds) {
+        self(bounds);
+    }
+}
+
+/// Typical numbers of nodes of a strong solve with a single thread, by the number of
+/// stones left to play, as `(stones left, log10(nodes))`. These roughly follow the
+/// averages of the benchmark files.
+#[cfg(feature = "std")]
+const TYPICAL_NODES: [(f64, f64); 6] = [
+    (0.0, 0.0),
+    (7.0, 1.3),
+    (14.0, 3.0),
+    (20.0, 4.6),
+    (32.0, 6.0),
+    (42.0, 9.0),
+];
+
+/// A rough estimate of how long it takes to solve a position, see `Solver::estimate_difficulty`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Difficulty {
+    /// Usually solved instantly.
+    Easy,
+    /// Usually solved within seconds.
+    Medium,
+    /// Can take a long time to solve.
+    Hard,
+}
+
+/// How to choose between columns that share the best score.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum TieBreak {
+    /// Prefer the columns closest to the center, in the same order as the search.
+    #[default]
+    CenterFirst,
+    /// Prefer the leftmost column.
+    LeftFirst,
+    /// Prefer the rightmost column.
+    RightFirst,
+    /// Pick one of the best columns pseudo-randomly, using the given seed.
+    Random(u64),
+}
+
+impl TieBreak {
+    /// Select a column with the highest score. Unplayable columns should have
+    /// the score `None`.
+    pub(crate) fn select(self, scores: &[Option<isize>]) -> Option<Column> {
+        let max = Some(*scores.iter().flatten().max()?);
+        let mut best = (0..Position::WIDTH).filter(|&col| scores[col as usize] == max);
+        match self {
+            Self::CenterFirst => Searcher::COLUMN_ORDER1
+                .into_iter()
+                .find(|&col| scores[col as usize] == max),
+            Self::LeftFirst => best.next(),
+            Self::RightFirst => best.next_back(),
+            Self::Random(seed) => {
+                let best = best.collect::<Vec<Column>>();
+                Some(best[(splitmix64(seed) % best.len() as u64) as usize])
+            }
+        }
+    }
+}
+
+/// Splitmix64, good enough to spread seeds over a small number of candidates.
+/// Consecutive seeds give unrelated results.
+pub(crate) fn splitmix64(seed: u64) -> u64 {
+    let mut z = seed.wrapping_add(0x9E37_79B9_7F4A_7C15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^ (z >> 31)
+}
+
+impl Searcher {
+    const COLUMN_ORDER1: [Column; Position::WIDTH as usize] =
+        Self::column_order1(0, [0; Position::WIDTH as usize]);
+    const COLUMN_ORDER2: [Column; Position::WIDTH as usize] =
+        Self::column_order2(0, [0; Position::WIDTH as usize]);
+    const fn column_order1(
+        i: u8,
+        mut temp_order: [Column; Position::WIDTH as usize],
+    ) -> [Column; Position::WIDTH as usize] {
+        if i == Position::WIDTH {
+            return temp_order;
+        }
+        // initialize the column exploration order, starting with center columns
+        // example for WIDTH=7: column_order = {3, 2, 4, 1, 5, 0, 6}
+        temp_order[i as usize] = (Position::WIDTH as isize / 2
+            + (1 - 2 * (i % 2) as isize) * (i as isize + 1) / 2)
+            as Column;
+        Self::column_order1(i + 1, temp_order)
+    }
+    const fn column_order2(
+        i: u8,
+        mut temp_order: [Column; Position::WIDTH as usize],
+    ) -> [Column; Position::WIDTH as usize] {
+        if i == Position::WIDTH {
+            return temp_order;
+        }
+        // initialize the column exploration order, starting with center columns
+        // example for WIDTH=7: column_order = {3, 4, 2, 5, 1, 6, 0}
+        temp_order[i as usize] = (Position::WIDTH as isize / 2
+            - (1 - 2 * (i % 2) as isize) * (i as isize + 1) / 2)
+            as Column;
+        Self::column_order2(i + 1, temp_order)
+    }
+}
+
+impl Searcher {
+    #[must_use]
+    pub fn new(
+        table: Arc<TranspositionTable>,
+        progress: SearchProgress,
+        interrupt: Arc<AtomicBool>,
+        settings: SearchSettings,
+    ) -> Self {
+        Self {
+            shared_context: SharedContext {
+                table,
+                abort_search: Arc::new(AtomicBool::new(false)),
+                interrupt,
+                score: Arc::new(AtomicIsize::new(0)),
+                progress,
+                settings,
+            },
+            local_context: LocalContext {
+                abort: false,
+                nodes: Nodes(Arc::new(AtomicU64::new(0))),
+                tt_hits: 0,
+                tt_misses: 0,
+                windows: 0,
+                line: HashSet::new(),
+            },
+            node_counter: NodeCounter {
+                node_counters: Vec::new(),
+            },
+        }
+    }
+
+    /// Prepare the searcher for a new search, with the given settings.
+    /// The search threads of the previous search have all been joined.
+    fn reset(&mut self, settings: SearchSettings) {
+        self.shared_context
+            .abort_search
+            .store(false, Ordering::SeqCst);
+        self.shared_context.score.store(0, Ordering::SeqCst);
+        self.shared_context.settings = settings;
+    }
+
+    /// Depth limited negamax used by `Solver::analyze_to_depth`, returning the value of
+    /// an `ApproxScore`. The positions at the maximal depth are scored with a heuristic.
+    ///
+    /// With a `table`, the best column of each position is tried first, and then stored.
+    fn approximate(
+        pos: &Position,
+        depth: usize,
+        mut alpha: isize,
+        beta: isize,
+        mut table: Option<&mut MoveTable>,
+    ) -> isize {
+        if pos.can_win_next() {
+            return ApproxScore::exact(pos.num_stones_left(1)).0;
+        }
+        let possible = pos.possible_non_losing_moves();
+        if possible == 0 {
+            return ApproxScore::exact(-pos.num_stones_left(0)).0;
+        }
+        if pos.nb_moves() >= Position::WIDTH * Position::HEIGHT - 2 {
+            return 0;
+        }
+        if depth == 0 {
+            return pos.threat_difference();
+        }
+        let first = table.as_deref().and_then(|table| table.get(pos.key()));
+        let mut best = None;
+        for col in first.into_iter().chain(
+            Self::COLUMN_ORDER1
+                .into_iter()
+                .filter(|&col| Some(col) != first),
+        ) {
+            let bmove = possible & Position::column_mask(col);
+            if bmove == 0 {
+                continue;
+            }
+            let mut pos2 = pos.clone();
+            pos2.play(bmove);
+            let score = -Self::approximate(&pos2, depth - 1, -beta, -alpha, table.as_deref_mut());
+            if score >= beta {
+                alpha = score;
+                best = Some(col);
+                break;
+            }
+            if score > alpha || best.is_none() {
+                best = Some(col);
+            }
+            alpha = alpha.max(score);
+        }
+        if let (Some(table), Some(best)) = (table, best) {
+            table.put(pos.key(), best);
+        }
+        alpha
+    }
+
+    /// Search `depth` moves ahead like `approximate()`, and return the score of the best
+    /// column and the column. Returns `None` if no column can be played.
+    fn approximate_root(
+        pos: &Position,
+        depth: usize,
+        table: &mut MoveTable,
+    ) -> Option<(isize, Column)> {
+        if let Some(col) = (0..Position::WIDTH).find(|&col| pos.is_winning_move(col)) {
+            return Some((ApproxScore::exact(pos.num_stones_left(1)).0, col));
+        }
+        let first = table.get(pos.key());
+        let mut best: Option<(isize, Column)> = None;
+        for col in first.into_iter().chain(
+            Self::COLUMN_ORDER1
+                .into_iter()
+                .filter(|&col| Some(col) != first),
+        ) {
+            if !pos.can_play(col) {
+                continue;
+            }
+            let alpha = best.map_or(-isize::MAX, |(score, _)| score);
+            let mut pos2 = pos.clone();
+            pos2.play_col(col);
+            let score = -Self::approximate(&pos2, depth - 1, -isize::MAX, -alpha, Some(table));
+            if best.is_none_or(|(best_score, _)| score > best_score) {
+                best = Some((score, col));
+            }
+        }
+        let (_, col) = best?;
+        table.put(pos.key(), col);
+        best
+    }
+
+    /// The score of a position where only one column isn't full, so the players take
+    /// turns in that column until one of them wins or the board is full.
+    fn single_column_score(pos: &Position) -> isize {
+        let mut pos = pos.clone();
+        let mut sign = 1;
+        while pos.nb_moves() < Position::WIDTH * Position::HEIGHT {
+            if pos.can_win_next() {
+                return sign * pos.num_stones_left(1);
+            }
+            pos.play(pos.possible());
+            sign = -sign;
+        }
+        0
+    }
+
+    /// Main alpha-beta search function.
+    fn negamax(
+        local_context: &mut LocalContext,
+        shared_context: &SharedContext,
+        pos: &Position,
+        alpha: isize,
+        beta: isize,
+        can_be_symmetric: bool,
+        thread_id: u8,
+    ) -> isize {
+        if !shared_context.settings.detect_repetitions {
+            return Self::negamax_node(
+                local_context,
+                shared_context,
+                pos,
+                alpha,
+                beta,
+                can_be_symmetric,
+                thread_id,
+            );
+        }
+        let key = pos.key();
+        if !local_context.line.insert(key) {
+            // The position repeats one earlier in the line, which is a draw.
+            return 0;
+        }
+        let score = Self::negamax_node(
+            local_context,
+            shared_context,
+            pos,
+            alpha,
+            beta,
+            can_be_symmetric,
+            thread_id,
+        );
+        local_context.line.remove(&key);
+        score
+    }
+
+    /// The search of `negamax()` once repetitions are handled.
+    fn negamax_node(
+        local_context: &mut LocalContext,
+        shared_context: &SharedContext,
+        pos: &Position,
+        mut alpha: isize,
+        mut beta: isize,
+        can_be_symmetric: bool,
         thread_id: u8,
     ) -> isize {
         debug_assert!(alpha < beta);
@@ -290,7 +1863,9 @@ impl Searcher {
         // increment number of explored nodes
         local_context.increment_nodes();
 
-        if local_context.nodes() % 1024 == 0 && shared_context.abort_search() {
+        if local_context.nodes() & shared_context.settings.abort_check_mask == 0
+            && shared_context.abort_search()
+        {
             local_context.abort = true;
             return 0;
         }
@@ -300,7 +1875,9 @@ impl Searcher {
         if possible == 0 {
             return -pos.num_stones_left(0);
         }
-        // No stones left => draw
+        // At most two cells are left, so it's a draw: the current player can't win with
+        // their move, and after a non losing move the opponent can't win with the last cell.
+        // This only depends on the number of cells, so it holds for any board size.
         if pos.nb_moves() >= Position::WIDTH * Position::HEIGHT - 2 {
             return 0;
         }
@@ -325,44 +1902,75 @@ impl Searcher {
             }
         }
 
+        let playable = pos.possible();
+        if playable & (playable - 1) == 0 {
+            // Only one column is left, so the rest of the game is forced.
+            return Self::single_column_score(pos).clamp(alpha, beta);
+        }
+
+        // The best known bounds on the score, to recognize exact scores.
+        let (mut lower_bound, mut upper_bound) = (min, max);
         let key = pos.key();
+        let settings = shared_context.settings;
+        // A draw by repetition depends on the line that led to the position, so with
+        // repetitions the transposition table is not used at all: its scores would hold
+        // for every line, and the entries of other searches assume there are no repetitions.
+        let use_table = !settings.detect_repetitions;
         let mut best_column = None;
-        if let Some(posinfo) = shared_context.table.get(key) {
+        // An entry of the mirrored position also has the mirrored best column.
+        let entry = use_table
+            .then(|| shared_context.table.get(key))
+            .flatten()
+            .map(|info| (info, false))
+            .or_else(|| {
+                (use_table && settings.mirror_probe && can_be_symmetric)
+                    .then(|| shared_context.table.get(pos.mirrored_key()))
+                    .flatten()
+                    .map(|info| (info, true))
+            });
+        if let Some((posinfo, mirrored)) = entry {
             local_context.tt_hits += 1;
             // The node has been visited before
-            let val = posinfo.score();
-            if val > Position::MAX_SCORE - Position::MIN_SCORE + 1 {
-                // Lower bound was stored
-                let min = val + 2 * Position::MIN_SCORE - Position::MAX_SCORE - 2;
-                if alpha < min {
-                    alpha = min;
-                    if alpha >= beta {
-                        return alpha;
+            match TableBound::from_posinfo(&posinfo) {
+                // No need to search, we already know the score.
+                TableBound::Exact(score) => return score,
+                TableBound::Lower(min) => {
+                    lower_bound = lower_bound.max(min);
+                    if alpha < min {
+                        alpha = min;
+                        if alpha >= beta {
+                            return alpha;
+                        }
                     }
                 }
-            } else {
-                // Upper bound was stored
-                let max = val + Position::MIN_SCORE - 1;
-                if beta > max {
-                    beta = max;
-                    if alpha >= beta {
-                        return beta;
+                TableBound::Upper(max) => {
+                    upper_bound = upper_bound.min(max);
+                    if beta > max {
+                        beta = max;
+                        if alpha >= beta {
+                            return beta;
+                        }
                     }
                 }
             }
-            best_column = Some(posinfo.column());
+            best_column = Some(if mirrored {
+                Position::WIDTH - 1 - posinfo.column()
+            } else {
+                posinfo.column()
+            });
             debug_assert!(0 != possible & Position::column_mask(best_column.unwrap()));
-        } else {
-            // TODO: tt_miss counter, or some other way of getting a feel how useful our tb entries are.
+        } else if use_table {
+            local_context.tt_misses += 1;
         }
 
         let mut moves = MoveSorter::new();
         // Add some randomness to the search in order to saturate the search tree.
-        let collum_order = if thread_id % 2 == 0 || local_context.nodes() % thread_id as u64 == 2 {
-            Self::COLUMN_ORDER1
-        } else {
-            Self::COLUMN_ORDER2
-        };
+        let collum_order =
+            if thread_id.is_multiple_of(2) || local_context.nodes() % thread_id as u64 == 2 {
+                Self::COLUMN_ORDER1
+            } else {
+                Self::COLUMN_ORDER2
+            };
         // Add the moves to the sorter in reverse order, because the last moves
         // have a higher chance of getting good scores, this way the sorting
         // is faster
@@ -380,6 +1988,7 @@ impl Searcher {
         }
 
         let mut highest_score = None;
+        let alpha_start = alpha;
         for (bmove, col) in moves {
             let mut pos2 = pos.clone();
             pos2.play(bmove);
@@ -392,27 +2001,40 @@ impl Searcher {
                 can_be_symmetric,
                 thread_id,
             );
+            if local_context.abort {
+                // The score is meaningless, so don't store anything.
+                return 0;
+            }
             if score > alpha {
                 // We only need to search for better moves than the best so far
                 if score >= beta {
-                    // TODO: Potentially only store if better bound.
-                    debug_assert!((score + Position::MAX_SCORE - 2 * Position::MIN_SCORE + 2) > 0);
-                    shared_context.table.put_checked(
-                        key,
-                        (score + Position::MAX_SCORE - 2 * Position::MIN_SCORE + 2) as Column,
-                        col,
-                        false,
-                    );
-                    if can_be_symmetric && pos.nb_moves() < 10 {
+                    if !use_table {
+                        return score;
+                    }
+                    // If the score can't be any higher it's exact, otherwise it's a lower bound.
+                    let exact = score >= upper_bound;
+                    let value = if exact {
+                        score - Position::MIN_SCORE + 1
+                    } else {
+                        score + Position::MAX_SCORE - 2 * Position::MIN_SCORE + 2
+                    };
+                    debug_assert!(value > 0);
+                    let store = |key, col| {
+                        if exact {
+                            shared_context.table.put_exact(key, value as Column, col);
+                        } else {
+                            shared_context
+                                .table
+                                .put_checked(key, value as Column, col, false);
+                        }
+                    };
+                    store(key, col);
+                    if can_be_symmetric && pos.nb_moves() < settings.mirror_store_ply {
                         // Also store the mirrored position in the transposition table.
                         // If only a few moves have been made, the symmetric position is
-                        // likely to be reached in another branch.
-                        shared_context.table.put_checked(
-                            pos.mirrored_key(),
-                            (score + Position::MAX_SCORE - 2 * Position::MIN_SCORE + 2) as Column,
-                            col,
-                            false,
-                        );
+                        // likely to be reached in another branch, see `set_mirror_store_ply`.
+                        // The best column has to be mirrored as well.
+                        store(pos.mirrored_key(), Position::WIDTH - 1 - col);
                     }
 
                     // Save a lower bound
@@ -425,34 +2047,112 @@ impl Searcher {
                 highest_score = Some(score);
             }
         }
-        debug_assert!((alpha - Position::MIN_SCORE + 1) > 0);
-        // Save an upper bound
-        shared_context.table.put_checked(
-            key,
-            (alpha - Position::MIN_SCORE + 1) as Column,
-            best_column.unwrap(),
-            true,
+        debug_assert!((alpha - Position::MIN_SCORE + 1) > 0);
+        if !use_table {
+            return alpha;
+        }
+        if alpha > alpha_start || alpha <= lower_bound {
+            // A move was found inside the window, or the score can't be any lower.
+            shared_context.table.put_exact(
+                key,
+                (alpha - Position::MIN_SCORE + 1) as Column,
+                best_column.unwrap(),
+            );
+        } else {
+            // Save an upper bound
+            shared_context.table.put_checked(
+                key,
+                (alpha - Position::MIN_SCORE + 1) as Column,
+                best_column.unwrap(),
+                true,
+            );
+        }
+        alpha
+    }
+
+    /// Compute the middle of the search window `[min, max]`, which is where the
+    /// next null window search is done. Scores closer to 0 are searched first,
+    /// since positions with a score close to 0 are more common.
+    fn window_middle(min: isize, max: isize) -> isize {
+        let mut med = min + (max - min) / 2;
+        if med <= 0 && min / 2 < med {
+            med = min / 2;
+        } else if med >= 0 && max / 2 > med {
+            med = max / 2;
+        }
+        med
+    }
+
+    /// Search the position with the window `[alpha, beta]` on the current thread.
+    /// Returns the result of `negamax()` and the number of nodes searched.
+    fn search_window(&mut self, pos: &Position, alpha: isize, beta: isize) -> (isize, u64) {
+        let mut local_context = self.local_context.clone();
+        let score = Self::negamax(
+            &mut local_context,
+            &self.shared_context,
+            pos,
+            alpha,
+            beta,
+            pos.can_become_symmetric(),
+            0,
         );
-        alpha
+        (score, local_context.nodes())
+    }
+
+    /// Follow the best moves stored in the transposition table from the given position.
+    fn principal_variation(table: &TranspositionTable, pos: &Position) -> Vec<Column> {
+        let mut pv = Vec::new();
+        let mut pos = pos.clone();
+        loop {
+            let possible = pos.possible_non_losing_moves();
+            let best_column = if possible != 0 && possible & (possible - 1) == 0 {
+                // Forced moves aren't always stored, e.g. when only one column is left.
+                possible.trailing_zeros() as Column / (Position::HEIGHT + 1)
+            } else if let Some(posinfo) = table.get(pos.key()) {
+                posinfo.column()
+            } else {
+                break;
+            };
+            if !pos.can_play(best_column) || pos.is_winning_move(best_column) {
+                break;
+            }
+            pv.push(best_column);
+            pos.play_col(best_column);
+        }
+        pv
     }
 
     /// Create a searcher that will solve the position.
     ///
-    /// The searcher will return the number of nodes it searched,
+    /// The searcher will return the statistics of its search,
     /// the score is stored in the shared context.
     fn launch_searcher(
         &mut self,
-        output: bool,
+        verbosity: Verbosity,
         pos: &Position,
         weak: bool,
         thread_id: u8,
-    ) -> impl FnMut() -> u64 {
+    ) -> impl FnMut() -> ThreadStats {
         let thread_is_main = thread_id == 0;
+        let output = verbosity == Verbosity::Full && thread_is_main;
         let shared_context = self.shared_context.clone();
-        let mut local_context = self.local_context.clone();
+        let mut local_context = if thread_is_main {
+            // The main thread reuses the node counter of the searcher, which is only
+            // used by one search at a time. Cloning gives a new counter.
+            LocalContext {
+                abort: false,
+                nodes: Nodes(Arc::clone(&self.local_context.nodes.0)),
+                tt_hits: 0,
+                tt_misses: 0,
+                windows: 0,
+                line: HashSet::new(),
+            }
+        } else {
+            self.local_context.clone()
+        };
         self.node_counter
             .add_node_counter(thread_id as usize, local_context.nodes.0.clone());
-        let node_counter = if thread_is_main {
+        let node_counter = if output {
             Some(self.node_counter.clone())
         } else {
             None
@@ -460,8 +2160,7 @@ impl Searcher {
 
         // Essentially, we do a binary search for the actual score.
         let pos = pos.clone();
-        let mut min = -pos.num_stones_left(0);
-        let mut max = pos.num_stones_left(1);
+        let (mut min, mut max) = pos.score_bounds();
         if weak {
             // We only need to know if the actual score is
             // < 0 ==> loss
@@ -470,24 +2169,18 @@ impl Searcher {
             min = -1;
             max = 1;
         }
+        let initial_range = max - min;
 
         let can_be_symmetric = pos.can_become_symmetric();
         move || {
             let start = Instant::now();
-            let mut nodes = 0;
             local_context.reset_nodes();
             while min < max {
                 let local_timer = Instant::now();
-                // Compute the middle of our search window.
                 // TODO: explore making this value different for different threads.
-                let mut med = min + (max - min) / 2;
-                if med <= 0 && min / 2 < med {
-                    med = min / 2;
-                } else if med >= 0 && max / 2 > med {
-                    med = max / 2;
-                }
-                if output && thread_is_main {
-                    println!(
+                let med = Self::window_middle(min, max);
+                if output {
+                    debug!(
                         "Searching: alpha {} beta {} [min {min}, max {max}]",
                         med,
                         med + 1
@@ -503,71 +2196,97 @@ impl Searcher {
                     can_be_symmetric,
                     thread_id,
                 );
-                nodes = local_context.nodes();
-                if output && thread_is_main {
+                local_context.windows += 1;
+                if !local_context.abort {
+                    if r <= med {
+                        // Score was smaller, so update maximum.
+                        max = r;
+                    } else {
+                        // Score was bigger, so update minimum.
+                        min = r;
+                    }
+                }
+                let progress = SearchProgress::estimate(min, max, initial_range);
+                if thread_is_main {
+                    shared_context.progress.set(progress);
+                }
+                if output {
                     let total_nodes = node_counter.as_ref().unwrap().get_node_count();
                     let elapsed = start.elapsed();
-                    println!(
-                        "Took: {:?}, total nodes {}, kn/s: {}",
+                    debug!(
+                        "Took: {:?}, total nodes {}, kn/s: {}, progress: {progress}%",
                         local_timer.elapsed(),
                         total_nodes,
                         (total_nodes as u128 * 1000) / elapsed.as_millis().max(1) / 1000,
                     );
                     // Try and output the principal variation.
-                    print!("pv: ");
-                    let mut pos = pos.clone();
-                    while let Some(posinfo) = shared_context.table.get(pos.key()) {
-                        let best_column = posinfo.column();
-                        print!("{} ", best_column + 1);
-                        pos.play_col(best_column);
-                    }
-                    println!();
+                    let pv = Self::principal_variation(&shared_context.table, &pos)
+                        .iter()
+                        .map(|col| format!("{} ", col + 1))
+                        .collect::<String>();
+                    debug!("pv: {pv}");
                 }
                 if local_context.abort {
-                    return nodes;
-                }
-                if r <= med {
-                    // Score was smaller, so update maximum.
-                    max = r;
-                } else {
-                    // Score was bigger, so update minimum.
-                    min = r;
+                    return local_context.stats();
                 }
             }
             if shared_context.abort_search() {
-                return nodes;
+                return local_context.stats();
             }
             // We have solved the position. Alert the other threads that we are done.
             shared_context.abort_now();
             shared_context.score.store(min, Ordering::SeqCst);
-            nodes
+            local_context.stats()
         }
     }
 
     fn search(
         &mut self,
         num_threads: u8,
-        output: bool,
+        verbosity: Verbosity,
         pos: &Position,
         weak: bool,
-    ) -> (isize, u64) {
+    ) -> (Option<isize>, ThreadStats) {
+        // Without `std` there are no threads, and the main thread searches alone.
+        // The main thread always searches, so 0 threads is the same as 1.
+        let num_threads = if cfg!(feature = "std") {
+            num_threads.max(1)
+        } else {
+            1
+        };
         self.node_counter
             .initialize_node_counters(num_threads as usize);
-        let mut join_handlers = vec![];
-        for i in 1..num_threads {
-            join_handlers.push(std::thread::spawn(
-                self.launch_searcher(output, pos, weak, i),
-            ));
-        }
-        let mut total_nodes = self.launch_searcher(output, pos, weak, 0)();
-        for join_handler in join_handlers {
-            let nodes = join_handler.join().unwrap();
-            total_nodes += nodes;
-        }
+        self.shared_context.progress.set(0);
+        #[cfg(feature = "std")]
+        let join_handlers = (1..num_threads)
+            .map(|i| std::thread::spawn(self.launch_searcher(verbosity, pos, weak, i)))
+            .collect::<Vec<_>>();
+        let mut main_searcher = self.launch_searcher(verbosity, pos, weak, 0);
+        self.shared_context
+            .progress
+            .set_node_counter(self.node_counter.clone());
+        // Only the windows of the main thread are counted.
+        let stats = main_searcher();
+        #[cfg(feature = "std")]
+        let stats = {
+            let mut stats = stats;
+            for join_handler in join_handlers {
+                let thread_stats = join_handler.join().unwrap();
+                stats.nodes += thread_stats.nodes;
+                stats.tt_hits += thread_stats.tt_hits;
+                stats.tt_misses += thread_stats.tt_misses;
+            }
+            stats
+        };
+        self.shared_context.progress.set(100);
 
+        // If no thread finished the search, it was interrupted.
+        if !self.shared_context.abort_search.load(Ordering::SeqCst) {
+            return (None, stats);
+        }
         (
-            self.shared_context.score.load(Ordering::Relaxed),
-            total_nodes,
+            Some(self.shared_context.score.load(Ordering::Relaxed)),
+            stats,
         )
     }
 }
@@ -583,6 +2302,1038 @@ pub mod tests {
         }
     }
 
+    #[test]
+    fn tie_break() {
+        let scores = [None, Some(2), Some(-1), Some(0), Some(2), Some(2), None];
+        assert_eq!(TieBreak::default().select(&scores), Some(4));
+        assert_eq!(TieBreak::LeftFirst.select(&scores), Some(1));
+        assert_eq!(TieBreak::RightFirst.select(&scores), Some(5));
+        for seed in 0..20 {
+            let col = TieBreak::Random(seed).select(&scores).unwrap();
+            assert!([1, 4, 5].contains(&col));
+            // The same seed always gives the same column.
+            assert_eq!(TieBreak::Random(seed).select(&scores), Some(col));
+        }
+        assert_eq!(TieBreak::default().select(&[None; 7]), None);
+        assert_eq!(TieBreak::LeftFirst.select(&[None; 7]), None);
+    }
+
+    #[test]
+    fn analyze_unplayable_columns() {
+        let mut solver = Solver::new(None);
+        // The first column is full.
+        let pos = Position::from_string("2252576253462244111563365343671351441").unwrap();
+        let scores = solver.analyze(&pos, false);
+        assert_eq!(scores[0], None);
+        let (score, _) = solver.solve(&pos, false, Verbosity::Silent, 1);
+        assert_eq!(scores.iter().flatten().max(), Some(&score));
+        assert_eq!(
+            solver.best_move(&pos, false, TieBreak::LeftFirst),
+            scores
+                .iter()
+                .position(|&s| s == Some(score))
+                .map(|c| c as Column)
+        );
+    }
+
+    #[test]
+    fn analyze_until_win() {
+        let mut solver = Solver::new(None);
+        // The center column loses, and the next one from the center wins.
+        let pos = Position::from_string("4455221").unwrap();
+        let mut expected = [ColumnScore::Skipped; Position::WIDTH as usize];
+        expected[3] = ColumnScore::Score(-17);
+        expected[2] = ColumnScore::Score(5);
+        assert_eq!(solver.analyze_until_win(&pos, false), expected);
+        // A weak solve only knows that the move wins. The center move lets the opponent
+        // win right away, which always has an exact score.
+        expected[2] = ColumnScore::Score(1);
+        assert_eq!(solver.analyze_until_win(&pos, true), expected);
+        // An immediate win is found without searching.
+        let pos = Position::from_string("4444443311").unwrap();
+        let mut expected = [ColumnScore::Skipped; Position::WIDTH as usize];
+        expected[1] = ColumnScore::Score(pos.num_stones_left(1));
+        expected[3] = ColumnScore::Unplayable;
+        assert_eq!(solver.analyze_until_win(&pos, false), expected);
+        // Without a winning move every column is solved.
+        let pos = Position::from_string("44455554221").unwrap();
+        let scores = solver.analyze(&pos, false);
+        let partial = solver.analyze_until_win(&pos, false);
+        for (score, partial) in scores.iter().zip(&partial) {
+            assert_eq!(
+                *partial,
+                score.map_or(ColumnScore::Unplayable, ColumnScore::Score)
+            );
+        }
+    }
+
+    #[test]
+    fn all_best_moves() {
+        let mut solver = Solver::new(None);
+        // Every column loses equally fast.
+        let pos = Position::from_string("44455554221").unwrap();
+        assert_eq!(
+            solver.best_moves(&pos, false),
+            (0..Position::WIDTH).collect::<Vec<_>>()
+        );
+        // Both moves lose, but the sixth column loses later.
+        let pos = Position::from_string("2252576253462244111563365343671351441").unwrap();
+        assert_eq!(solver.best_moves(&pos, false), vec![5]);
+        let pos = Position::from_string("4444443311").unwrap();
+        let best = solver.best_moves(&pos, false);
+        assert_eq!(best, vec![1]);
+        for tie_break in [
+            TieBreak::CenterFirst,
+            TieBreak::LeftFirst,
+            TieBreak::RightFirst,
+        ] {
+            assert_eq!(solver.best_move(&pos, false, tie_break), Some(best[0]));
+        }
+        // A weak solve only keeps the outcome, so all the winning moves are best.
+        assert_eq!(solver.best_moves(&pos, true), vec![1, 2, 4, 5]);
+    }
+
+    #[test]
+    fn best_move_from_book() {
+        let pos = Position::from_string("4455221").unwrap();
+        let mut solver = Solver::new(None);
+        assert_eq!(solver.book_scores(&pos, false), None);
+        let scores = solver.analyze(&pos, false);
+
+        let mut booked_solver = Solver::new(None);
+        booked_solver.generate_book(&pos, pos.nb_moves() as usize + 1, false);
+        assert_eq!(booked_solver.book_scores(&pos, false), Some(scores.clone()));
+        // A weak book can't be used for a strong solve.
+        let mut weak_solver = Solver::new(None);
+        weak_solver.generate_book(&pos, pos.nb_moves() as usize + 1, true);
+        assert_eq!(weak_solver.book_scores(&pos, false), None);
+        assert!(weak_solver.book_scores(&pos, true).is_some());
+
+        // The positions after each column are all in the book, so nothing is searched.
+        booked_solver.reset_transposition_table();
+        for col in 0..Position::WIDTH {
+            if pos.can_play(col) && !pos.is_winning_move(col) {
+                let mut pos2 = pos.clone();
+                pos2.play_col(col);
+                assert_eq!(
+                    booked_solver
+                        .solve_full(&pos2, false, Verbosity::Silent, 1)
+                        .nodes,
+                    0
+                );
+            }
+        }
+        assert_eq!(
+            booked_solver.best_move(&pos, false, TieBreak::LeftFirst),
+            TieBreak::LeftFirst.select(&scores)
+        );
+    }
+
+    #[test]
+    fn node_count() {
+        let mut solver = Solver::new(None);
+        assert_eq!(solver.get_node_count(), 0);
+        let pos = Position::from_string("4455221").unwrap();
+        let (_, first) = solver.solve(&pos, false, Verbosity::Silent, 1);
+        assert!(first > 0);
+        assert_eq!(solver.get_node_count(), first);
+        // The count adds up over several searches.
+        let pos2 = Position::from_string("445522136").unwrap();
+        let (_, second) = solver.solve(&pos2, false, Verbosity::Silent, 1);
+        let (_, window) = solver.solve_window(&pos2, 0, 1);
+        assert_eq!(solver.get_node_count(), first + second + window);
+
+        // After a reset only the next search is counted, and the table is kept.
+        solver.reset_node_count();
+        assert_eq!(solver.get_node_count(), 0);
+        solver.clear_cache();
+        let (_, again) = solver.solve(&pos, false, Verbosity::Silent, 1);
+        assert_eq!(solver.get_node_count(), again);
+        assert!(again < first);
+    }
+
+    #[test]
+    fn review_game() {
+        assert_eq!(MoveTag::classify(3, 3), MoveTag::Best);
+        assert_eq!(MoveTag::classify(3, 1), MoveTag::Inaccuracy);
+        assert_eq!(MoveTag::classify(-1, -4), MoveTag::Inaccuracy);
+        assert_eq!(MoveTag::classify(3, 0), MoveTag::Blunder);
+        assert_eq!(MoveTag::classify(0, -2), MoveTag::Blunder);
+
+        let mut solver = Solver::new(None);
+        // 'o' can win right away in column 3 or 7, but first plays column 1, and then wins
+        // in column 3. The last move is after the end of the game.
+        let start = Position::from_string("445566").unwrap();
+        let reviews = solver.review_game(&start, &[0, 1, 2, 3]);
+        assert_eq!(reviews.len(), 3);
+        let first = reviews[0];
+        assert!(first.first_player);
+        assert_eq!((first.played, first.best), (0, 2));
+        assert_eq!(first.eval_before, start.num_stones_left(1));
+        assert_eq!(first.tag, MoveTag::Inaccuracy);
+        assert!(!reviews[1].first_player);
+        assert!(reviews[1].eval_before < 0);
+        assert_eq!(reviews[2].tag, MoveTag::Best);
+        assert_eq!(reviews[2].ply, 2);
+
+        // The reviews agree with `analyze()`.
+        let start = Position::from_string("52753311433677442422121").unwrap();
+        let moves = [3, 0, 4];
+        let reviews = solver.review_game(&start, &moves);
+        assert_eq!(reviews.len(), moves.len());
+        let mut pos = start.clone();
+        for (review, &col) in reviews.iter().zip(&moves) {
+            let scores = solver.analyze(&pos, false);
+            assert_eq!(
+                Some(review.eval_before),
+                scores.iter().flatten().max().copied()
+            );
+            assert_eq!(scores[col as usize], Some(review.eval_after));
+            assert_eq!(scores[review.best as usize], Some(review.eval_before));
+            pos.play_col(col);
+        }
+
+        // A move in a full column stops the review.
+        let start = Position::from_string("111111").unwrap();
+        assert!(solver.review_game(&start, &[0, 1]).is_empty());
+    }
+
+    #[test]
+    fn explain_best_move() {
+        let mut solver = Solver::new(None);
+        assert_eq!(
+            solver.explain_best_move(&Position::from_string("445566").unwrap()),
+            Some("Column 3 wins for 'o' right away with four in a row.".to_string())
+        );
+        let text = solver
+            .explain_best_move(&Position::from_string("2233").unwrap())
+            .unwrap();
+        assert!(
+            text.starts_with("Column 4 wins for 'o' in 2 move(s)."),
+            "{text}"
+        );
+        assert!(text.contains("two threats at once"), "{text}");
+        assert!(
+            text.contains("2 threat(s), on row(s) 1. 2 of them are on odd rows"),
+            "{text}"
+        );
+
+        let pos = Position::from_string("4455221").unwrap();
+        let text = solver.explain_best_move(&pos).unwrap();
+        let best = solver
+            .best_move(&pos, false, TieBreak::CenterFirst)
+            .unwrap();
+        assert!(
+            text.starts_with(&format!("Column {} wins for 'x'", best + 1)),
+            "{text}"
+        );
+        assert!(
+            text.contains(&format!("The main line is {} ", best + 1)),
+            "{text}"
+        );
+
+        let text = solver
+            .explain_best_move(&Position::from_string("22334").unwrap())
+            .unwrap();
+        assert!(text.starts_with("Every move loses"), "{text}");
+    }
+
+    #[test]
+    fn search_progress() {
+        assert_eq!(SearchProgress::estimate(-10, 10, 20), 0);
+        assert_eq!(SearchProgress::estimate(0, 10, 20), 50);
+        assert_eq!(SearchProgress::estimate(3, 3, 20), 100);
+        let mut solver = Solver::new(None);
+        let progress = solver.progress();
+        assert_eq!(progress.percentage(), 0);
+        let pos = Position::from_string("44455554221").unwrap();
+        solver.solve(&pos, false, Verbosity::Silent, 1);
+        assert_eq!(progress.percentage(), 100);
+    }
+
+    #[test]
+    fn near_full_boards() {
+        // Compare the solver with the exhaustive search of the tablebase, which doesn't
+        // stop early for draws, in positions where only a few cells are left.
+        let mut seed = 0;
+        let mut random = || {
+            seed += 1;
+            splitmix64(seed)
+        };
+        let mut games = 0;
+        while games < 20 {
+            // Play random moves that don't make an alignment, until the board is full.
+            let mut pos = Position::new();
+            let mut history = vec![pos.clone()];
+            loop {
+                let moves = (0..Position::WIDTH)
+                    .filter_map(|col| pos.with_move(col))
+                    .collect::<Vec<_>>();
+                if moves.is_empty() {
+                    break;
+                }
+                pos = moves[(random() % moves.len() as u64) as usize].clone();
+                history.push(pos.clone());
+            }
+            let cells = Position::WIDTH * Position::HEIGHT;
+            if pos.nb_moves() < cells - 6 {
+                continue;
+            }
+            games += 1;
+            let mut solver = Solver::new(None);
+            for pos in &history[(cells - 8) as usize..] {
+                let mut tablebase = Tablebase::new(pos.nb_moves());
+                tablebase.generate(pos);
+                let expected = tablebase.probe(pos).unwrap_or_else(|| {
+                    if pos.can_win_next() {
+                        pos.num_stones_left(1)
+                    } else {
+                        // A full board.
+                        0
+                    }
+                });
+                assert_eq!(solver.solve(pos, false, Verbosity::Silent, 1).0, expected);
+            }
+        }
+    }
+
+    #[test]
+    fn mirror_settings() {
+        let pos = Position::from_string("4444443311").unwrap();
+        assert!(pos.can_become_symmetric());
+        let mut solver = Solver::new(None);
+        let (expected, _) = solver.solve(&pos, false, Verbosity::Silent, 1);
+        for (ply, probe) in [(0, false), (0, true), (42, true)] {
+            let mut solver = Solver::new(None);
+            solver.set_mirror_store_ply(ply);
+            solver.set_mirror_probe(probe);
+            let config = solver.config();
+            assert_eq!((config.mirror_store_ply, config.mirror_probe), (ply, probe));
+            assert_eq!(solver.solve(&pos, false, Verbosity::Silent, 1).0, expected);
+            // The mirrored position can use the entries of the first search.
+            let mirrored = Position::from_string("4444445577").unwrap();
+            assert_eq!(
+                solver.solve(&mirrored, false, Verbosity::Silent, 1).0,
+                expected
+            );
+        }
+    }
+
+    #[test]
+    fn repetitions() {
+        let mut solver = Solver::new(None);
+        let mut detecting = Solver::new(None);
+        detecting.set_detect_repetitions(true);
+        assert!(detecting.config().detect_repetitions);
+        // Connect 4 positions don't repeat, so the scores are the same. Without the
+        // transposition table, more nodes are searched.
+        let lines = std::fs::read_to_string("./benchmark_files/end_easy").unwrap();
+        for line in lines.lines().take(10) {
+            let pos = Position::parse_pons(line.split(' ').next().unwrap()).unwrap();
+            let (score, nodes) = detecting.solve(&pos, false, Verbosity::Silent, 1);
+            let expected = solver.solve(&pos, false, Verbosity::Silent, 1);
+            assert_eq!(score, expected.0, "{line}");
+            assert!(nodes >= expected.1, "{line}");
+        }
+        assert!(detecting.searcher.local_context.line.is_empty());
+        // Nothing is stored in the transposition table.
+        let empty = |solver: &Solver| solver.trans_table.entries().all(|(_, value)| value == 0);
+        assert!(empty(&detecting));
+        assert!(!empty(&solver));
+
+        // A position that was already reached earlier in the line is a draw.
+        let pos = Position::from_string("2252576253462244111563365343671351441").unwrap();
+        detecting.searcher.reset(detecting.settings);
+        let searcher = &detecting.searcher;
+        let mut local_context = searcher.local_context.clone();
+        let search = |local_context: &mut LocalContext| {
+            Searcher::negamax(
+                local_context,
+                &searcher.shared_context,
+                &pos,
+                -1,
+                1,
+                false,
+                0,
+            )
+        };
+        assert!(search(&mut local_context) < 0);
+        assert!(local_context.line.is_empty());
+        local_context.line.insert(pos.key());
+        assert_eq!(search(&mut local_context), 0);
+        assert_eq!(local_context.line.len(), 1);
+        // The draws by repetition further down the line aren't stored either.
+        local_context.line.clear();
+        for next in (0..Position::WIDTH).filter_map(|col| pos.with_move(col)) {
+            local_context.line.insert(next.key());
+        }
+        assert_eq!(search(&mut local_context), 0);
+        assert!(empty(&detecting));
+    }
+
+    #[test]
+    fn evaluate_single_move() {
+        let mut solver = Solver::new(None);
+        let pos = Position::from_string("4445555556").unwrap();
+        let scores = solver.analyze(&pos, false);
+        for col in 0..Position::WIDTH {
+            assert_eq!(solver.evaluate_move(&pos, col, false), scores[col as usize]);
+        }
+        assert_eq!(solver.evaluate_move(&pos, 4, false), None);
+        // Winning move.
+        let pos = Position::from_string("445566").unwrap();
+        assert_eq!(
+            solver.evaluate_move(&pos, 2, false),
+            Some(pos.num_stones_left(1))
+        );
+        assert_eq!(
+            solver.evaluate_move(&pos, 2, true),
+            Some(pos.num_stones_left(1))
+        );
+    }
+
+    #[test]
+    fn solver_config() {
+        let mut solver = Solver::new(None);
+        assert_eq!(
+            solver.config(),
+            SolverConfig {
+                has_book: false,
+                tt_log_size: TranspositionTable::LOG_SIZE,
+                book_entries: 0,
+                mirror_store_ply: Solver::DEFAULT_MIRROR_STORE_PLY,
+                mirror_probe: false,
+                abort_check_interval: Solver::DEFAULT_ABORT_CHECK_INTERVAL,
+                cache_capacity: 0,
+                detect_repetitions: false,
+                verbosity: Verbosity::Full,
+                tablebase_entries: 0,
+            }
+        );
+        let mut book = OpeningBook::new();
+        book.put(&Position::new(), 1);
+        let entries = book.num_entries();
+        solver.set_book(book);
+        let config = solver.config();
+        assert!(config.has_book);
+        assert_eq!(config.book_entries, entries);
+        assert!(entries > 0);
+        solver.take_book();
+        assert!(!solver.config().has_book);
+        solver.set_verbosity(Verbosity::Silent);
+        assert_eq!(solver.config().verbosity, Verbosity::Silent);
+    }
+
+    #[test]
+    fn difficulty() {
+        let mut solver = Solver::new(None);
+        let pos = Position::new();
+        assert_eq!(solver.estimate_difficulty(&pos), Difficulty::Hard);
+        let mut book = OpeningBook::new();
+        book.put(&pos, 1);
+        solver.set_book(book);
+        assert_eq!(solver.estimate_difficulty(&pos), Difficulty::Easy);
+        // Can win in one move.
+        let pos = Position::from_string("445533").unwrap();
+        assert_eq!(solver.estimate_difficulty(&pos), Difficulty::Easy);
+        let pos = Position::from_string("2252576253462244111563365343671351441").unwrap();
+        assert_eq!(solver.estimate_difficulty(&pos), Difficulty::Easy);
+        let pos = Position::from_string("13712").unwrap();
+        assert_eq!(solver.estimate_difficulty(&pos), Difficulty::Hard);
+        let pos = Position::from_string("274552224131661").unwrap();
+        assert_eq!(solver.estimate_difficulty(&pos), Difficulty::Medium);
+    }
+
+    #[test]
+    fn solve_report() {
+        let mut solver = Solver::new(None);
+        let pos = Position::from_string("4455221").unwrap();
+        let report = solver.solve_full(&pos, false, Verbosity::Silent, 1);
+        assert_eq!(report.score, 5);
+        assert!(report.nodes > 0);
+        assert!(report.windows_searched > 0);
+        assert!(report.tt_hits + report.tt_misses <= report.nodes);
+        assert!(!report.pv.is_empty());
+        let mut pv_pos = pos.clone();
+        for &col in &report.pv {
+            assert!(pv_pos.can_play(col));
+            pv_pos.play_col(col);
+        }
+        // Winning in one move doesn't need a search.
+        let pos = Position::from_string("445533").unwrap();
+        let report = solver.solve_full(&pos, false, Verbosity::Silent, 1);
+        assert_eq!(report.score, pos.num_stones_left(1));
+        assert_eq!(report.nodes, 0);
+    }
+
+    /// The recursive version of `Solver::generate_book`, which only adds the
+    /// positions to the book if they aren't in it yet.
+    fn generate_book_recursive(
+        solver: &mut Solver,
+        book: &mut OpeningBook,
+        pos: &Position,
+        depth: usize,
+        added: &mut Vec<Position>,
+    ) {
+        if book.get(pos).is_some() || pos.nb_moves() as usize > depth {
+            return;
+        }
+        let (score, _) = solver.solve(pos, false, Verbosity::Silent, 1);
+        book.put(pos, score);
+        added.push(pos.clone());
+        for col in Searcher::COLUMN_ORDER1 {
+            if !pos.can_play(col) || pos.is_winning_move(col) {
+                continue;
+            }
+            let mut p2 = pos.clone();
+            p2.play_col(col);
+            generate_book_recursive(solver, book, &p2, depth, added);
+        }
+    }
+
+    #[test]
+    fn generate_book_iteratively() {
+        let pos = Position::from_string("44552217").unwrap();
+        let depth = pos.nb_moves() as usize + 2;
+        let mut solver = Solver::new(None);
+        solver.generate_book(&pos, depth, false);
+        let mut book = OpeningBook::new();
+        let mut added = Vec::new();
+        generate_book_recursive(&mut solver, &mut book, &pos, depth, &mut added);
+        assert!(added.len() > Position::WIDTH as usize);
+        assert_eq!(solver.get_book().num_entries(), book.num_entries());
+        for pos in &added {
+            assert_eq!(solver.get_book().get(pos), book.get(pos));
+        }
+        // A position that is already in the book is skipped, with the positions after it.
+        let num_entries = solver.get_book().num_entries();
+        solver.generate_book(&pos, depth + 1, false);
+        assert_eq!(solver.get_book().num_entries(), num_entries);
+    }
+
+    #[test]
+    fn generate_book_streaming() {
+        for weak in [false, true] {
+            let pos = Position::from_string("44552217").unwrap();
+            let depth = pos.nb_moves() as usize + 2;
+            // The entries that are already in the book are written to the file as well.
+            let other = Position::from_string("4455221").unwrap();
+            let mut solver = Solver::new(None);
+            solver.generate_book(&other, other.nb_moves() as usize, weak);
+            solver.generate_book(&pos, depth, weak);
+            let mut streaming_solver = Solver::new(None);
+            streaming_solver.generate_book(&other, other.nb_moves() as usize, weak);
+            let path = &std::env::temp_dir().join(format!("connect4_streaming_book_{weak}.book"));
+            streaming_solver
+                .generate_book_streaming(&pos, depth, weak, path)
+                .unwrap();
+            let loaded = OpeningBook::load(path).unwrap();
+            std::fs::remove_file(path).unwrap();
+            assert_eq!(loaded.kind(), solver.get_book().kind());
+            assert_eq!(loaded.num_entries(), solver.get_book().num_entries());
+            // Storing both books sorts the entries, so the files have to be the same.
+            solver.get_book().store(path).unwrap();
+            let expected = std::fs::read(path).unwrap();
+            loaded.store(path).unwrap();
+            let actual = std::fs::read(path).unwrap();
+            std::fs::remove_file(path).unwrap();
+            assert_eq!(actual, expected);
+        }
+    }
+
+    #[test]
+    fn exact_table_entries() {
+        let mut solver = Solver::new(None);
+        let pos = Position::from_string("52753311433677442422121").unwrap();
+        // Forced moves are not stored in the table.
+        assert!(pos.possible_non_losing_moves().count_ones() > 1);
+        // The score is inside the window, so it's stored as an exact score.
+        let (score, _) = solver.solve_window(&pos, Position::MIN_SCORE, Position::MAX_SCORE);
+        assert_eq!(score, 8);
+        let posinfo = solver.trans_table.get(pos.key()).unwrap();
+        assert!(posinfo.is_exact());
+        assert_eq!(posinfo.score() + Position::MIN_SCORE - 1, score);
+        // Which is used directly by the next searches.
+        assert_eq!(solver.solve_window(&pos, 0, 1), (8, 1));
+        assert_eq!(solver.solve(&pos, false, Verbosity::Silent, 1).0, 8);
+    }
+
+    #[test]
+    fn probe_table_bounds() {
+        let mut solver = Solver::new(None);
+        let pos = Position::from_string("52753311433677442422121").unwrap();
+        assert_eq!(solver.probe_table(&pos), None);
+        // The score is 8, so this window fails high and stores a lower bound.
+        assert!(solver.solve_window(&pos, 6, 7).0 >= 7);
+        let entry = solver.probe_table(&pos).unwrap();
+        assert!(matches!(entry.bound, TableBound::Lower(7..=8)), "{entry:?}");
+        assert!(pos.can_play(entry.column));
+        // This one fails low and stores an upper bound. With 23 moves played, 9 is
+        // already the highest possible score, so a higher window returns before storing.
+        solver.reset_transposition_table();
+        assert!(solver.solve_window(&pos, 8, 9).0 <= 8);
+        let entry = solver.probe_table(&pos).unwrap();
+        assert_eq!(entry.bound, TableBound::Upper(8));
+        solver.solve_window(&pos, Position::MIN_SCORE, Position::MAX_SCORE);
+        assert_eq!(
+            solver.probe_table(&pos).unwrap().bound,
+            TableBound::Exact(8)
+        );
+    }
+
+    #[test]
+    fn node_estimates() {
+        let full = Position::from_string("65617356615221567642233737747314144542532").unwrap();
+        assert!(Solver::estimate_nodes(&full) <= 10);
+        let middle = Position::from_string("52753311433677442422121").unwrap();
+        let begin = Position::from_string("4455").unwrap();
+        assert!(Solver::estimate_nodes(&middle) > 1000);
+        assert!(Solver::estimate_nodes(&begin) > 100 * Solver::estimate_nodes(&middle));
+        assert!(Solver::estimate_nodes(&Position::new()) >= Solver::estimate_nodes(&begin));
+
+        // The node count of the last search can be read from the progress handle.
+        let mut solver = Solver::new(None);
+        let progress = solver.progress();
+        let (_, nodes) = solver.solve(&middle, false, Verbosity::Silent, 2);
+        assert_eq!(progress.nodes(), nodes);
+    }
+
+    #[test]
+    fn zero_threads() {
+        let pos = Position::from_string("52753311433677442422121").unwrap();
+        let mut solver = Solver::new(None);
+        let expected = solver.solve_full(&pos, false, Verbosity::Silent, 1);
+        solver.reset_transposition_table();
+        // No threads searches on the calling thread, like a single thread.
+        let report = solver.solve_full(&pos, false, Verbosity::Silent, 0);
+        assert_eq!(report.score, expected.score);
+        assert_eq!(report.nodes, expected.nodes);
+        assert_eq!(solver.progress().nodes(), expected.nodes);
+    }
+
+    #[test]
+    fn result_cache() {
+        let mut solver = Solver::new(None);
+        let pos = Position::from_string("52753311433677442422121").unwrap();
+        let other = Position::from_string("4455221").unwrap();
+        // Without a cache, solving again searches again.
+        let first = solver.solve_full(&pos, false, Verbosity::Silent, 1);
+        assert!(first.nodes > 0);
+        assert!(solver.solve_full(&pos, false, Verbosity::Silent, 1).nodes > 0);
+
+        solver.set_cache_capacity(2);
+        assert_eq!(solver.config().cache_capacity, 2);
+        solver.solve_full(&pos, false, Verbosity::Silent, 1);
+        solver.reset_transposition_table();
+        let cached = solver.solve_full(&pos, false, Verbosity::Silent, 1);
+        assert_eq!(cached.nodes, 0);
+        assert_eq!(cached.score, first.score);
+        assert_eq!(cached.pv, first.pv);
+        // A weak solve has another score, so it isn't taken from the cache.
+        let weak = solver.solve_full(&pos, true, Verbosity::Silent, 1);
+        assert!(weak.nodes > 0);
+        assert_eq!(weak.score, first.score.signum());
+        // The cache is full, so the least recently used result, the strong solve, goes.
+        solver.solve(&other, false, Verbosity::Silent, 1);
+        assert_eq!(solver.solve(&pos, true, Verbosity::Silent, 1).1, 0);
+        assert!(solver.solve(&pos, false, Verbosity::Silent, 1).1 > 0);
+
+        solver.clear_cache();
+        assert!(solver.solve(&pos, false, Verbosity::Silent, 1).1 > 0);
+
+        // An interrupted search is not cached.
+        solver.clear_cache();
+        solver.interrupt_handle().store(true, Ordering::SeqCst);
+        assert!(
+            solver
+                .solve_full(&other, false, Verbosity::Silent, 1)
+                .interrupted
+        );
+        solver.interrupt_handle().store(false, Ordering::SeqCst);
+        solver.reset_transposition_table();
+        let report = solver.solve_full(&other, false, Verbosity::Silent, 1);
+        assert!(!report.interrupted);
+        assert!(report.nodes > 0);
+
+        // A result found while detecting repetitions is only used with that setting.
+        solver.set_detect_repetitions(true);
+        assert!(solver.solve(&pos, false, Verbosity::Silent, 1).1 > 0);
+        assert_eq!(solver.solve(&pos, false, Verbosity::Silent, 1).1, 0);
+        solver.set_detect_repetitions(false);
+        assert!(solver.solve(&pos, false, Verbosity::Silent, 1).1 > 0);
+        solver.clear_cache();
+        assert!(solver.solve(&pos, false, Verbosity::Silent, 1).1 > 0);
+        solver.set_detect_repetitions(true);
+        assert!(solver.solve(&pos, false, Verbosity::Silent, 1).1 > 0);
+    }
+
+    #[test]
+    fn result_cache_order() {
+        let mut cache = ResultCache {
+            capacity: 3,
+            ..ResultCache::default()
+        };
+        let key = |i: u64| (i, false, false);
+        for i in 0..3 {
+            cache.insert(key(i), i as isize, vec![]);
+        }
+        // Many lookups don't make the order grow without bound.
+        for _ in 0..1000 {
+            assert_eq!(cache.get(key(0)).map(|(score, _)| score), Some(0));
+            assert_eq!(cache.get(key(2)).map(|(score, _)| score), Some(2));
+        }
+        assert!(cache.order.len() <= 2 * 3 + 16);
+        // 1 is the least recently used, then 0.
+        cache.insert(key(3), 3, vec![]);
+        assert!(cache.get(key(1)).is_none());
+        cache.insert(key(4), 4, vec![]);
+        assert!(cache.get(key(0)).is_none());
+        assert_eq!(cache.entries.len(), 3);
+        // Inserting a key again makes it the most recently used.
+        cache.insert(key(2), 5, vec![]);
+        cache.insert(key(5), 5, vec![]);
+        assert!(cache.get(key(3)).is_none());
+        assert!(cache.get(key(2)).is_some());
+        cache.shrink_to(0);
+        assert!(cache.entries.is_empty());
+    }
+
+    #[test]
+    fn abort_check_interval() {
+        let mut solver = Solver::new(None);
+        let interrupt = solver.interrupt_handle();
+        // The search is interrupted from the start, so it stops at the first check.
+        interrupt.store(true, Ordering::SeqCst);
+        for interval in [1, 16, Solver::DEFAULT_ABORT_CHECK_INTERVAL] {
+            solver.set_abort_check_interval(interval);
+            assert_eq!(solver.config().abort_check_interval, interval);
+            let report = solver.solve_full(&Position::new(), false, Verbosity::Silent, 1);
+            assert!(report.interrupted);
+            // A smaller interval stops the search after fewer nodes.
+            assert_eq!(report.nodes, interval);
+            solver.reset_transposition_table();
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "power of two")]
+    fn abort_check_interval_power_of_two() {
+        Solver::new(None).set_abort_check_interval(1000);
+    }
+
+    #[test]
+    fn interrupt_search() {
+        let mut solver = Solver::new(None);
+        let interrupt = solver.interrupt_handle();
+        let handle = {
+            let interrupt = interrupt.clone();
+            std::thread::spawn(move || {
+                std::thread::sleep(Duration::from_millis(50));
+                interrupt.store(true, Ordering::SeqCst);
+            })
+        };
+        // Solving the empty position takes far longer than that.
+        let report = solver.solve_full(&Position::new(), false, Verbosity::Silent, 2);
+        handle.join().unwrap();
+        assert!(report.interrupted);
+        assert!(report.elapsed < Duration::from_secs(10));
+        // The flag is not reset by the solver.
+        let pos = Position::from_string("4455221").unwrap();
+        assert!(
+            solver
+                .solve_full(&pos, false, Verbosity::Silent, 1)
+                .interrupted
+        );
+        // No partial results.
+        assert_eq!(
+            solver.analyze(&pos, false),
+            vec![None; Position::WIDTH as usize]
+        );
+        let (_, nodes) = solver.solve_window(&Position::new(), -1, 1);
+        assert!(nodes <= Solver::DEFAULT_ABORT_CHECK_INTERVAL);
+
+        interrupt.store(false, Ordering::SeqCst);
+        solver.reset_transposition_table();
+        let report = solver.solve_full(&pos, false, Verbosity::Silent, 1);
+        assert!(!report.interrupted);
+        assert_eq!(report.score, 5);
+    }
+
+    #[test]
+    fn incremental_solve() {
+        let mut solver = Solver::new(None);
+        let pos = Position::from_string("4455221").unwrap();
+        let mut updates = Vec::new();
+        let score = solver.solve_incremental(&pos, &mut |bounds| updates.push(bounds));
+        assert_eq!(score, 5);
+        // The first update already knows the outcome.
+        assert_eq!(updates[0].outcome(), Some(Outcome::Win));
+        assert_eq!(updates.last(), Some(&ScoreBounds::exact(5)));
+        for pair in updates.windows(2) {
+            assert!(pair[0].min <= pair[1].min && pair[1].max <= pair[0].max);
+        }
+        // Compare with a cold solve.
+        solver.reset_transposition_table();
+        assert_eq!(solver.solve(&pos, false, Verbosity::Silent, 1).0, 5);
+    }
+
+    #[test]
+    fn solve_window() {
+        let mut solver = Solver::new(None);
+        let pos = Position::from_string("4455221").unwrap();
+        // The score is 5.
+        assert!(solver.solve_window(&pos, 2, 3).0 >= 3);
+        assert!(solver.solve_window(&pos, 7, 8).0 <= 7);
+        assert_eq!(solver.solve_window(&pos, 4, 6).0, 5);
+    }
+
+    #[test]
+    fn single_column_endgames() {
+        let mut solver = Solver::new(None);
+        let mut seed = 54321u64;
+        let mut checked = 0;
+        while checked < 20 {
+            // Play random games until only one column is left.
+            let mut pos = Position::new();
+            while !pos.can_win_next() && pos.possible_non_losing_moves() != 0 {
+                let playable = pos.possible();
+                if playable & (playable - 1) == 0 {
+                    let (score, _) = solver.solve(&pos, false, Verbosity::Silent, 1);
+                    let mut child = pos.clone();
+                    child.play(playable);
+                    let (child_score, _) = solver.solve(&child, false, Verbosity::Silent, 1);
+                    assert_eq!(score, -child_score);
+                    // The search stops at the root instead of playing out the column.
+                    solver.searcher.reset(solver.settings);
+                    let (min, max) = pos.score_bounds();
+                    let window = solver.searcher.search_window(&pos, min - 1, max + 1);
+                    assert_eq!(window, (score, 1));
+                    checked += 1;
+                    break;
+                }
+                seed = splitmix64(seed);
+                let col = (seed % u64::from(Position::WIDTH)) as Column;
+                if pos.can_play(col) && !pos.is_winning_move(col) {
+                    pos.play_col(col);
+                }
+            }
+        }
+        // Without the shortcut, the search took 9902 nodes on these positions.
+        let mut solver = Solver::new(None);
+        let lines = std::fs::read_to_string("./benchmark_files/end_easy").unwrap();
+        let nodes = lines
+            .lines()
+            .take(200)
+            .map(|line| {
+                let pos = Position::parse_pons(line.split(' ').next().unwrap()).unwrap();
+                solver.solve(&pos, false, Verbosity::Silent, 1).1
+            })
+            .sum::<u64>();
+        assert!(nodes < 9902, "{nodes} nodes");
+    }
+
+    #[test]
+    fn forced_moves() {
+        let mut solver = Solver::new(None);
+        let mut seed = 12345u64;
+        let mut checked = 0;
+        while checked < 20 {
+            // Play random games until we reach a position with a single non-losing move.
+            let mut pos = Position::new();
+            loop {
+                if pos.can_win_next() {
+                    break;
+                }
+                let possible = pos.possible_non_losing_moves();
+                if possible == 0 {
+                    break;
+                }
+                if possible & (possible - 1) == 0 {
+                    let mut child = pos.clone();
+                    child.play(possible);
+                    let (score, _) = solver.solve(&pos, false, Verbosity::Silent, 1);
+                    let (child_score, _) = solver.solve(&child, false, Verbosity::Silent, 1);
+                    assert_eq!(score, -child_score);
+                    checked += 1;
+                    break;
+                }
+                seed = seed.wrapping_mul(6364136223846793005).wrapping_add(1);
+                let col = ((seed >> 33) % u64::from(Position::WIDTH)) as Column;
+                if pos.can_play(col) && !pos.is_winning_move(col) {
+                    pos.play_col(col);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn approximate_analysis() {
+        let solver = Solver::new(None);
+        // 'o' can win immediately in columns 2 and 6 (1-based).
+        let pos = Position::from_string("445533").unwrap();
+        let scores = solver.analyze_to_depth(&pos, 2);
+        assert_eq!(scores.len(), Position::WIDTH as usize);
+        assert_eq!(scores[1], Some(ApproxScore::exact(pos.num_stones_left(1))));
+        assert_eq!(scores[5], scores[1]);
+        assert_eq!(scores[1].unwrap().decided(), Some(pos.num_stones_left(1)));
+        // 'x' can't stop both threats, so other moves also win, but later.
+        assert!(scores[0].unwrap() > ApproxScore(0));
+        assert!(scores[0] < scores[1]);
+        // Nothing is decided a few moves into the game, so the heuristic is used.
+        let pos = Position::from_string("4").unwrap();
+        for score in solver.analyze_to_depth(&pos, 2).into_iter().flatten() {
+            assert_eq!(score.decided(), None);
+            assert!(score.0.abs() < ApproxScore::DECIDED);
+        }
+        assert_eq!(ApproxScore::exact(-3).decided(), Some(-3));
+        assert_eq!(ApproxScore::exact(0).decided(), None);
+        // Full columns can't be played.
+        let pos = Position::from_string("111111").unwrap();
+        let scores = solver.analyze_to_depth(&pos, 3);
+        assert_eq!(scores[0], None);
+        assert!(scores[1..].iter().all(Option::is_some));
+    }
+
+    #[test]
+    fn analyze_deepening() {
+        let mut solver = Solver::new(None);
+        let pos = Position::from_string("4455221").unwrap();
+        let mut results = Vec::new();
+        let last = solver.analyze_deepening(&pos, 6, None, &mut |result| results.push(result));
+        assert_eq!(last, results.last().copied());
+        assert_eq!(results.len(), 6);
+        for (i, result) in results.iter().enumerate() {
+            assert_eq!(result.depth, i + 1);
+            // The same scores as without remembering the best moves.
+            let scores = solver.analyze_to_depth(&pos, result.depth - 1);
+            assert_eq!(Some(result.score), scores.iter().flatten().max().copied());
+            assert_eq!(scores[result.best_move as usize], Some(result.score));
+        }
+        assert_eq!(
+            solver.analyze_deepening(&pos, 6, Some(Duration::ZERO), &mut |_| {}),
+            None
+        );
+
+        // Searching until the board is full gives the exact score and a best move.
+        let lines = std::fs::read_to_string("./benchmark_files/end_easy").unwrap();
+        for line in lines.lines().take(10) {
+            let pos = Position::parse_pons(line.split(' ').next().unwrap()).unwrap();
+            let last = solver
+                .analyze_deepening(&pos, usize::MAX, None, &mut |_| {})
+                .unwrap();
+            let (score, _) = solver.solve(&pos, false, Verbosity::Silent, 1);
+            assert_eq!(last.score, ApproxScore::exact(score), "{line}");
+            assert!(solver.best_moves(&pos, false).contains(&last.best_move));
+        }
+    }
+
+    #[test]
+    fn deepening_keeps_best_move() {
+        let solver = Solver::new(None);
+        let lines = std::fs::read_to_string("./benchmark_files/middle_easy").unwrap();
+        let mut kept = 0;
+        for line in lines.lines().take(20) {
+            let pos = Position::parse_pons(line.split(' ').next().unwrap()).unwrap();
+            let mut results = Vec::new();
+            solver.analyze_deepening(&pos, 6, None, &mut |result| results.push(result));
+            for pair in results.windows(2) {
+                let (previous, result) = (pair[0], pair[1]);
+                let scores = solver.analyze_to_depth(&pos, result.depth - 1);
+                let previous_score = scores[previous.best_move as usize].unwrap();
+                // The new best move is never worse than the last one at the new depth,
+                // and if the last one is still as good, it is kept.
+                assert!(result.score >= previous_score, "{line}");
+                if result.score == previous_score {
+                    assert_eq!(result.best_move, previous.best_move, "{line}");
+                    kept += 1;
+                }
+            }
+        }
+        assert!(kept > 0);
+    }
+
+    #[test]
+    fn solve_in_moves() {
+        let mut solver = Solver::new(None);
+        let lines = std::fs::read_to_string("./benchmark_files/end_easy").unwrap();
+        for line in lines.lines().take(20) {
+            let pos = Position::parse_pons(line.split(' ').next().unwrap()).unwrap();
+            let (outcome, plies) = solver.solve_in_moves(&pos, false);
+            let plies = plies.unwrap();
+            assert_eq!(solver.solve_in_moves(&pos, true), (outcome, None));
+            // After the best move the game is decided one ply sooner, with the opposite
+            // outcome for the other player.
+            let col = solver
+                .best_move(&pos, false, TieBreak::CenterFirst)
+                .unwrap();
+            let Some(next) = pos.with_move(col) else {
+                assert_eq!((outcome, plies), (Outcome::Win, 1), "{line}");
+                continue;
+            };
+            let expected = match outcome {
+                Outcome::Win => Outcome::Loss,
+                Outcome::Draw => Outcome::Draw,
+                Outcome::Loss => Outcome::Win,
+            };
+            assert_eq!(
+                solver.solve_in_moves(&next, false),
+                (expected, Some(plies - 1)),
+                "{line}"
+            );
+        }
+    }
+
+    #[test]
+    fn mirrored_table_entries() {
+        // The entries for mirrored positions used to store the best column without mirroring
+        // it, so looking up the mirrored position could suggest a column that can't be played.
+        fn check_entries(table: &TranspositionTable, pos: &Position, depth: usize) {
+            if pos.can_win_next() {
+                return;
+            }
+            let possible = pos.possible_non_losing_moves();
+            if let Some(posinfo) = table.get(pos.key()) {
+                assert!(possible & Position::column_mask(posinfo.column()) != 0);
+            }
+            if depth == 0 {
+                return;
+            }
+            for col in 0..Position::WIDTH {
+                if possible & Position::column_mask(col) != 0 {
+                    let mut pos2 = pos.clone();
+                    pos2.play_col(col);
+                    check_entries(table, &pos2, depth - 1);
+                }
+            }
+        }
+        let mut solver = Solver::new(None);
+        let pos = Position::from_string("33742").unwrap();
+        assert!(pos.can_become_symmetric());
+        assert_eq!(solver.solve(&pos, false, Verbosity::Silent, 1).0, 6);
+        // Now search the mirrored position, which uses the mirrored entries.
+        let mirrored = Position::from_string("55146").unwrap();
+        check_entries(&solver.trans_table, &mirrored, 4);
+        assert_eq!(solver.solve(&mirrored, false, Verbosity::Silent, 1).0, 6);
+    }
+
+    #[test]
+    fn shared_table() {
+        let table = Arc::new(TranspositionTable::new());
+        let mut solver1 = Solver::with_table(table.clone(), None);
+        let mut solver2 = Solver::with_table(table, None);
+        let pos = Position::from_string("4455221").unwrap();
+        let (score, cold_nodes) = solver1.solve(&pos, false, Verbosity::Silent, 1);
+        let (warm_score, warm_nodes) = solver2.solve(&pos, false, Verbosity::Silent, 1);
+        assert_eq!(score, warm_score);
+        assert!(warm_nodes < cold_nodes);
+        // Both solvers are cleared.
+        solver2.reset_transposition_table();
+        assert_eq!(
+            solver1.solve(&pos, false, Verbosity::Silent, 1).1,
+            cold_nodes
+        );
+    }
+
     #[test]
     fn test_scores() {
         let mut pos = Position::new();
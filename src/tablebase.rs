@@ -0,0 +1,257 @@
+#[cfg(feature = "std")]
+use crate::error::Connect4Error;
+#[cfg(feature = "std")]
+use crate::opening_book::{check_key_format, key_format_header, ParseBookEntryError};
+use crate::position::Position;
+#[cfg(not(feature = "std"))]
+use alloc::collections::BTreeMap as HashMap;
+#[cfg(feature = "std")]
+use std::{
+    collections::HashMap,
+    fs::File,
+    io::{BufRead, BufReader, BufWriter, Read, Write},
+    path::Path,
+};
+
+/// The first line of a tablebase file starts with this, followed by `min_moves`.
+#[cfg(feature = "std")]
+const HEADER_PREFIX: &str = "#connect4-tablebase ";
+
+/// A `Tablebase` stores the exact score of every position near the end of the game,
+/// i.e. with at least `min_moves` moves played, below some starting position. This is
+/// the counterpart of the `OpeningBook` at the other end of the game: the positions are
+/// quick to solve one by one, but the search still visits a lot of them.
+///
+/// Unlike the opening book, the positions are stored by their `Position::key()`, so a
+/// position and its mirror image have separate entries. Positions that can be won in
+/// one move are not stored, since the solver recognizes them directly.
+pub struct Tablebase {
+    min_moves: u8,
+    entries: HashMap<u64, i8>,
+}
+
+impl Tablebase {
+    /// Create an empty tablebase for positions with at least `min_moves` moves.
+    #[must_use]
+    pub fn new(min_moves: u8) -> Self {
+        Self {
+            min_moves,
+            entries: HashMap::new(),
+        }
+    }
+
+    /// The number of moves a position needs to be in the tablebase.
+    #[must_use]
+    pub fn min_moves(&self) -> u8 {
+        self.min_moves
+    }
+
+    #[must_use]
+    pub fn num_entries(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Get the score of the given position, if it is in the tablebase.
+    #[must_use]
+    pub fn probe(&self, pos: &Position) -> Option<isize> {
+        if pos.nb_moves() < self.min_moves {
+            return None;
+        }
+        self.entries
+            .get(&pos.key())
+            .map(|&score| isize::from(score))
+    }
+
+    /// Add all the positions with at least `min_moves` moves that can be reached from `pos`.
+    ///
+    /// The positions are solved by visiting every move until the end of the game, without
+    /// any pruning, so this is only feasible if few moves are left after `min_moves`.
+    /// If `pos` has fewer moves, every position after it with `min_moves` moves is
+    /// solved, which quickly becomes a lot of positions.
+    pub fn generate(&mut self, pos: &Position) {
+        if pos.nb_moves() >= self.min_moves {
+            self.solve(pos);
+            return;
+        }
+        for col in 0..Position::WIDTH {
+            if let Some(next) = pos.with_move(col) {
+                self.generate(&next);
+            }
+        }
+    }
+
+    /// Compute the exact score of the position, and store it together with the
+    /// scores of all the positions after it.
+    fn solve(&mut self, pos: &Position) -> isize {
+        if pos.can_win_next() {
+            return pos.num_stones_left(1);
+        }
+        if pos.nb_moves() == Position::WIDTH * Position::HEIGHT {
+            return 0;
+        }
+        if let Some(&score) = self.entries.get(&pos.key()) {
+            return isize::from(score);
+        }
+        let mut best = None;
+        for col in 0..Position::WIDTH {
+            // No move wins, so every playable column gives a new position.
+            if let Some(next) = pos.with_move(col) {
+                let score = -self.solve(&next);
+                best = Some(best.map_or(score, |best: isize| best.max(score)));
+            }
+        }
+        // The board isn't full, so there is at least one move.
+        let score = best.unwrap();
+        self.entries.insert(pos.key(), score as i8);
+        score
+    }
+
+    /// Load a tablebase from a file. If errors occured while
+    /// loading or parsing the file an `Err` is returned.
+    #[cfg(feature = "std")]
+    pub fn load(path: &Path) -> Result<Self, Connect4Error> {
+        let file = File::open(path)?;
+        let mut file = BufReader::new(file);
+        let mut first_line = String::new();
+        file.read_line(&mut first_line)?;
+        let (min_moves, key_format) = first_line
+            .trim_end()
+            .strip_prefix(HEADER_PREFIX)
+            .map(|header| header.split_once(' ').unwrap_or((header, "")))
+            .ok_or(ParseBookEntryError::Header)?;
+        check_key_format(key_format)?;
+        let min_moves = min_moves
+            .parse::<u8>()
+            .map_err(|_| ParseBookEntryError::Header)?;
+        let mut bytes = Vec::new();
+        file.read_to_end(&mut bytes)?;
+        // Every entry is the key in little endian, followed by a single byte for the score.
+        let chunks = bytes.chunks_exact(9);
+        if !chunks.remainder().is_empty() {
+            return Err(ParseBookEntryError::Truncated.into());
+        }
+        let mut tablebase = Self::new(min_moves);
+        for chunk in chunks {
+            let key = u64::from_le_bytes(chunk[..8].try_into().unwrap());
+            tablebase.entries.insert(key, chunk[8] as i8);
+        }
+        Ok(tablebase)
+    }
+
+    /// Store the tablebase in a file, overwriting the file if it exists.
+    #[cfg(feature = "std")]
+    pub fn store(&self, path: &Path) -> Result<(), Connect4Error> {
+        let file = File::create(path)?;
+        let mut file = BufWriter::new(file);
+        writeln!(
+            &mut file,
+            "{HEADER_PREFIX}{} {}",
+            self.min_moves,
+            key_format_header()
+        )?;
+        for (key, &score) in &self.entries {
+            file.write_all(&key.to_le_bytes())?;
+            file.write_all(&[score as u8])?;
+        }
+        file.flush()?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Tablebase;
+    use crate::error::Connect4Error;
+    use crate::opening_book::ParseBookEntryError;
+    use crate::position::Position;
+    use crate::solver::{Solver, Verbosity};
+
+    #[test]
+    fn generate_and_probe() {
+        let pos = Position::from_string("2252576253462244111563365343671351441").unwrap();
+        let mut tablebase = Tablebase::new(pos.nb_moves());
+        assert_eq!(tablebase.probe(&pos), None);
+        tablebase.generate(&pos);
+        assert!(tablebase.num_entries() > 1);
+        let mut solver = Solver::new(None);
+        let (score, _) = solver.solve(&pos, false, Verbosity::Silent, 1);
+        assert_eq!(tablebase.probe(&pos), Some(score));
+        for col in 0..Position::WIDTH {
+            if let Some(next) = pos.with_move(col) {
+                if next.can_win_next() {
+                    assert_eq!(tablebase.probe(&next), None);
+                } else {
+                    let (score, _) = solver.solve(&next, false, Verbosity::Silent, 1);
+                    assert_eq!(tablebase.probe(&next), Some(score));
+                }
+            }
+        }
+        // Positions before `min_moves` are never in the tablebase.
+        let before = Position::from_string("225257625346224411156336534367135144").unwrap();
+        assert_eq!(tablebase.probe(&before), None);
+
+        // The solver doesn't have to search the positions in the tablebase.
+        let entries = tablebase.num_entries();
+        let mut solver = Solver::new(None);
+        solver.set_tablebase(tablebase);
+        assert_eq!(solver.config().tablebase_entries, entries);
+        assert_eq!(solver.solve(&pos, false, Verbosity::Silent, 1), (score, 0));
+    }
+
+    #[test]
+    fn weak_probe() {
+        let pos = Position::from_string("22525762534622441115633653436713514").unwrap();
+        let mut tablebase = Tablebase::new(pos.nb_moves());
+        tablebase.generate(&pos);
+        assert_eq!(tablebase.probe(&pos), Some(-2));
+        let mut solver = Solver::new(None);
+        solver.set_tablebase(tablebase);
+        // A weak solve only gets the sign of the score, like without a tablebase.
+        assert_eq!(solver.solve(&pos, true, Verbosity::Silent, 1), (-1, 0));
+        assert_eq!(solver.solve(&pos, false, Verbosity::Silent, 1), (-2, 0));
+    }
+
+    #[test]
+    fn generate_from_earlier_position() {
+        let pos = Position::from_string("225257625346224411156336534367135144").unwrap();
+        let mut tablebase = Tablebase::new(pos.nb_moves() + 1);
+        tablebase.generate(&pos);
+        assert_eq!(tablebase.probe(&pos), None);
+        let mut solver = Solver::new(None);
+        for col in 0..Position::WIDTH {
+            if let Some(next) = pos.with_move(col).filter(|next| !next.can_win_next()) {
+                let (score, _) = solver.solve(&next, false, Verbosity::Silent, 1);
+                assert_eq!(tablebase.probe(&next), Some(score));
+            }
+        }
+    }
+
+    #[test]
+    fn store_and_load() {
+        let pos = Position::from_string("2252576253462244111563365343671351441").unwrap();
+        let mut tablebase = Tablebase::new(pos.nb_moves());
+        tablebase.generate(&pos);
+        let path = std::env::temp_dir().join("connect4_store_and_load.tablebase");
+        tablebase.store(&path).unwrap();
+        let loaded = Tablebase::load(&path).unwrap();
+        assert_eq!(loaded.min_moves(), tablebase.min_moves());
+        assert_eq!(loaded.entries, tablebase.entries);
+
+        std::fs::write(&path, "#connect4-book strong\n").unwrap();
+        assert!(matches!(
+            Tablebase::load(&path),
+            Err(Connect4Error::Parse(ParseBookEntryError::Header))
+        ));
+        std::fs::write(&path, "#connect4-tablebase 30 key-format 2\n").unwrap();
+        assert!(matches!(
+            Tablebase::load(&path),
+            Err(Connect4Error::Parse(ParseBookEntryError::KeyFormat(2)))
+        ));
+        std::fs::write(&path, "#connect4-tablebase 30\n12345").unwrap();
+        assert!(matches!(
+            Tablebase::load(&path),
+            Err(Connect4Error::Parse(ParseBookEntryError::Truncated))
+        ));
+        std::fs::remove_file(&path).unwrap();
+    }
+}
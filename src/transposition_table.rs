@@ -1,8 +1,10 @@
-use std::sync::atomic::{AtomicU16, AtomicU32, Ordering};
+use alloc::boxed::Box;
+use core::sync::atomic::{AtomicU16, AtomicU32, Ordering};
 
+use crate::logging::debug;
 use crate::position::Position;
 
-/// The following are functions to find the next prime factor at compile time
+// The following are functions to find the next prime factor at compile time
 
 const fn med(min: u64, max: u64) -> u64 {
     (min + max) / 2
@@ -15,7 +17,7 @@ const fn has_factor(n: u64, min: u64, max: u64) -> bool {
     }
     // do not search for factor above sqrt(n)
     else if min + 1 >= max {
-        n % min == 0
+        n.is_multiple_of(min)
     } else {
         has_factor(n, min, med(min, max)) || has_factor(n, med(min, max), max)
     }
@@ -32,15 +34,16 @@ const fn next_prime(n: u64) -> u64 {
 }
 
 type KeyType = u64;
-type PartialKeyType = u32;
+/// The part of the key that is stored in the table, see `TranspositionTable::entries`.
+pub type PartialKeyType = u32;
 type AtomicPartialKeyType = AtomicU32;
 type AtomicValueType = AtomicU16;
 
 #[derive(Debug, PartialEq, Eq)]
 pub struct PosInfo {
-    /// The score in this position (either a lower bound or an upper bound)
+    /// The score in this position (either a lower bound, an upper bound or the exact score)
     score: u8,
-    /// The column from which we got this bound.
+    /// The column from which we got this bound. The highest bit is set if the score is exact.
     column: u8,
 }
 
@@ -61,8 +64,18 @@ impl PosInfo {
     }
 
     pub fn column(&self) -> u8 {
-        self.column
+        self.column & !Self::EXACT
     }
+
+    /// Whether the stored score is the exact score of the position, instead of a bound.
+    /// Exact scores are stored like upper bounds.
+    pub fn is_exact(&self) -> bool {
+        self.column & Self::EXACT != 0
+    }
+
+    /// The bit of `column` that is set for exact scores.
+    /// Columns are small, so this doesn't need a wider value type.
+    const EXACT: u8 = 1 << 7;
 }
 
 /// Transposition Table is a simple hash map with fixed storage size.
@@ -85,8 +98,9 @@ pub struct TranspositionTable {
 }
 impl TranspositionTable {
     /// Base 2 log of the size of the Transposition Table.
-    const LOG_SIZE: usize = 24;
-    const SIZE: u64 = next_prime(1 << Self::LOG_SIZE);
+    pub const LOG_SIZE: usize = 24;
+    /// The number of entries in the table.
+    pub const SIZE: u64 = next_prime(1 << Self::LOG_SIZE);
 }
 
 impl Default for TranspositionTable {
@@ -106,7 +120,7 @@ impl TranspositionTable {
     /// assert_eq!(table.get(5).unwrap().score() , 2);
     /// ```
     pub fn new() -> Self {
-        println!("Initialized transposition table with size: {}", Self::SIZE);
+        debug!("Initialized transposition table with size: {}", Self::SIZE);
         // Initialize with `Self::SIZE + 1` to guarantee that we will always see
         // uninitialized entries as uninitialized. Using `Option<PartialKeyType>`
         // was too slow.
@@ -115,7 +129,11 @@ impl TranspositionTable {
                 .map(|_| AtomicPartialKeyType::new(Self::SIZE as PartialKeyType + 1))
                 .collect(),
             values: (0..Self::SIZE)
-                .map(|_| AtomicValueType::new(unsafe { std::mem::transmute(PosInfo::zero()) }))
+                .map(|_| {
+                    AtomicValueType::new(unsafe {
+                        core::mem::transmute::<PosInfo, u16>(PosInfo::zero())
+                    })
+                })
                 .collect(),
         }
     }
@@ -126,7 +144,7 @@ impl TranspositionTable {
             // uninitialized entries as uninitialized.
             self.keys[i as usize].store((Self::SIZE + 1) as PartialKeyType, Ordering::Relaxed);
             self.values[i as usize].store(
-                unsafe { std::mem::transmute(PosInfo::zero()) },
+                unsafe { core::mem::transmute::<PosInfo, u16>(PosInfo::zero()) },
                 Ordering::Relaxed,
             );
         }
@@ -144,7 +162,7 @@ impl TranspositionTable {
         }
         // We need to use the xor trick to ensure that key and value were set by the same thread.
         if r_key == key as PartialKeyType ^ value as PartialKeyType {
-            Some(unsafe { std::mem::transmute(value) })
+            Some(unsafe { core::mem::transmute::<u16, PosInfo>(value) })
         } else {
             None
         }
@@ -152,7 +170,7 @@ impl TranspositionTable {
     /// Store a key value pair in the table. Previous entries are overwritten on collision.
     pub fn put(&self, key: KeyType, score: u8, column: u8) {
         let index = Self::index(key);
-        let value = unsafe { std::mem::transmute(PosInfo::new(score, column)) };
+        let value = unsafe { core::mem::transmute::<PosInfo, u16>(PosInfo::new(score, column)) };
         // Importantly, we xor with the value. This allows us to verify that we got the correct
         // entry upon retrieval, by xor-ing again.
         unsafe {
@@ -166,9 +184,20 @@ impl TranspositionTable {
         }
     }
 
+    /// Store the exact score of the position, encoded like an upper bound.
+    /// Unlike bounds, exact scores are never overwritten by `put_checked`.
+    pub fn put_exact(&self, key: KeyType, score: u8, column: u8) {
+        debug_assert!(column < PosInfo::EXACT);
+        self.put(key, score, column | PosInfo::EXACT);
+    }
+
     /// Same as put, but we first query the hashtable to see if this is actually a better bound.
     pub fn put_checked(&self, key: KeyType, score: u8, column: u8, is_upper_bound: bool) {
         if let Some(pos_info) = self.get(key) {
+            if pos_info.is_exact() {
+                // Nothing is better than the exact score.
+                return;
+            }
             // Check if the new score is a better bound.
             let val = pos_info.score();
             if val > Position::MAX_SCORE - Position::MIN_SCORE + 1 {
@@ -185,6 +214,34 @@ impl TranspositionTable {
         self.put(key, score, column)
     }
 
+    /// The number of slots in the table.
+    #[must_use]
+    pub fn size(&self) -> usize {
+        self.keys.len()
+    }
+
+    /// Iterate over the raw contents of all the slots of the table, in order of their index.
+    /// This can be used to copy a table, together with `put_raw`.
+    ///
+    /// Each item is the stored key and the packed value of a slot. **Warning**: the stored
+    /// key is not the partial key of the position, but the partial key xor-ed with the value,
+    /// see the documentation of `TranspositionTable`. Empty slots are also returned.
+    pub fn entries(&self) -> impl Iterator<Item = (PartialKeyType, u16)> + '_ {
+        self.keys
+            .iter()
+            .zip(self.values.iter())
+            .map(|(key, value)| (key.load(Ordering::Relaxed), value.load(Ordering::Relaxed)))
+    }
+
+    /// Store the raw contents of a slot, as returned by `entries`, at the given `index`.
+    /// The `key` should already be xor-ed with the `value`.
+    ///
+    /// Panics if `index` is not smaller than `size()`.
+    pub fn put_raw(&self, index: usize, key: PartialKeyType, value: u16) {
+        self.keys[index].store(key, Ordering::Relaxed);
+        self.values[index].store(value, Ordering::Relaxed);
+    }
+
     /// Get the index for the given `key`.
     fn index(key: KeyType) -> usize {
         (key % Self::SIZE) as usize
@@ -221,6 +278,48 @@ mod tests {
         }
     }
 
+    #[test]
+    fn exact_entries() {
+        let tb = TranspositionTable::new();
+        let key = Position::from_string("4455").unwrap().key();
+        tb.put_checked(key, 10, 2, true);
+        assert!(!tb.get(key).unwrap().is_exact());
+        tb.put_exact(key, 12, 6);
+        let posinfo = tb.get(key).unwrap();
+        assert!(posinfo.is_exact());
+        assert_eq!(posinfo.score(), 12);
+        assert_eq!(posinfo.column(), 6);
+        // Bounds don't replace the exact score.
+        tb.put_checked(key, 3, 1, true);
+        tb.put_checked(key, 60, 1, false);
+        assert_eq!(tb.get(key), Some(posinfo));
+        // `put` always overwrites.
+        tb.put(key, 3, 1);
+        assert!(!tb.get(key).unwrap().is_exact());
+    }
+
+    #[test]
+    fn raw_entries() {
+        let tb = TranspositionTable::new();
+        let mut pos = position::Position::new();
+        let mut keys = Vec::new();
+        for j in 0..20 {
+            pos.play_col(j * 5 % Position::WIDTH);
+            tb.put(pos.key(), j, j % Position::WIDTH);
+            keys.push(pos.key());
+        }
+        let copy = TranspositionTable::new();
+        assert_eq!(copy.size(), tb.size());
+        for (index, (key, value)) in tb.entries().enumerate() {
+            copy.put_raw(index, key, value);
+        }
+        for (j, &key) in keys.iter().enumerate() {
+            let j = j as u8;
+            assert_eq!(copy.get(key), Some(PosInfo::new(j, j % Position::WIDTH)));
+        }
+        assert_eq!(copy.get(Position::new().key()), None);
+    }
+
     #[test]
     fn uninitialized() {
         let tb = TranspositionTable::new();